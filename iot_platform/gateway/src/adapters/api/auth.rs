@@ -0,0 +1,199 @@
+use crate::config::{ApiKeyConfig, AuthConfig, Role};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+// Identity attached to every authenticated request (available to handlers via Extension)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Principal {
+    fn anonymous() -> Self {
+        Self {
+            name: "anonymous".into(),
+            role: Role::Admin,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Authenticator {
+    enabled: bool,
+    keys: Arc<Vec<ApiKeyConfig>>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Self {
+        if config.enabled && config.keys.is_empty() {
+            warn!("API authentication enabled without any keys, all protected routes will reject requests");
+        }
+
+        Self {
+            enabled: config.enabled,
+            keys: Arc::new(config.keys.clone()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(&AuthConfig::default())
+    }
+
+    // Accepts "Authorization: Bearer <token>" or "X-API-Key: <token>"
+    pub fn authenticate(&self, headers: &HeaderMap) -> Option<Principal> {
+        if !self.enabled {
+            return Some(Principal::anonymous());
+        }
+
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))?
+            .trim();
+
+        self.keys
+            .iter()
+            .find(|key| constant_time_eq(key.token.as_bytes(), token.as_bytes()))
+            .map(|key| Principal {
+                name: key.name.clone(),
+                role: key.role,
+            })
+    }
+
+    // Creates the middleware state for a route group requiring at least `role`
+    pub fn require(&self, role: Role) -> RequiredRole {
+        RequiredRole {
+            auth: self.clone(),
+            role,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequiredRole {
+    auth: Authenticator,
+    role: Role,
+}
+
+// Used with axum::middleware::from_fn_with_state as a route layer
+pub async fn require_role(
+    State(required): State<RequiredRole>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+
+    let Some(principal) = required.auth.authenticate(req.headers()) else {
+        warn!("API: Rejected unauthenticated request {} {}", method, path);
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response();
+    };
+
+    if principal.role < required.role {
+        warn!(
+            "API: {} ({:?}) is not allowed to call {} {} (requires {:?})",
+            principal.name, principal.role, method, path, required.role
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    req.extensions_mut().insert(principal.clone());
+    let response = next.run(req).await;
+
+    if is_mutating(&method) {
+        info!(
+            target: "audit",
+            "API audit: principal={} role={:?} method={} path={} status={}",
+            principal.name,
+            principal.role,
+            method,
+            path,
+            response.status().as_u16()
+        );
+    }
+
+    response
+}
+
+fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// Avoids leaking the matching prefix length of a token through response timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn authenticator() -> Authenticator {
+        Authenticator::new(&AuthConfig {
+            enabled: true,
+            keys: vec![ApiKeyConfig {
+                name: "dashboard".into(),
+                token: "secret".into(),
+                role: Role::Viewer,
+            }],
+        })
+    }
+
+    #[test]
+    fn accepts_bearer_and_api_key_headers() {
+        let auth = authenticator();
+
+        let mut bearer = HeaderMap::new();
+        bearer.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        let mut api_key = HeaderMap::new();
+        api_key.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+
+        assert_eq!(auth.authenticate(&bearer).unwrap().name, "dashboard");
+        assert_eq!(auth.authenticate(&api_key).unwrap().role, Role::Viewer);
+    }
+
+    #[test]
+    fn rejects_unknown_or_missing_token() {
+        let auth = authenticator();
+
+        let mut wrong = HeaderMap::new();
+        wrong.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer nope"),
+        );
+
+        assert!(auth.authenticate(&wrong).is_none());
+        assert!(auth.authenticate(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn disabled_auth_yields_anonymous_admin() {
+        let principal = Authenticator::disabled()
+            .authenticate(&HeaderMap::new())
+            .unwrap();
+
+        assert_eq!(principal.role, Role::Admin);
+    }
+}
@@ -1,10 +1,90 @@
+pub mod auth;
+pub mod tls;
+
+use crate::config::Role;
+use crate::core::alarms::{Alarm, AlarmState};
 use crate::core::device::{Device, DeviceInput};
 use crate::core::events::GatewayEvent;
+use crate::core::health::{ComponentStatus, HealthReport};
+use crate::core::metrics::Metrics;
+use crate::core::reload::{ConfigDiff, ReloadTrigger};
 use crate::core::state::AppState;
-use axum::extract::Path;
-use axum::{extract::State, http::StatusCode, Json};
+use auth::{require_role, Authenticator, Principal};
+use axum::extract::{MatchedPath, Path, Query, Request};
+use axum::http::header;
+use axum::middleware::{from_fn_with_state, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::Router;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Deserialize;
+use std::time::Instant;
 use tracing::info;
 
+// Builds the REST API with per-route role enforcement (health stays public)
+pub fn router(app_state: AppState, auth: Authenticator) -> Router {
+    let viewer = Router::new()
+        .route("/devices", get(get_devices))
+        .route("/metrics", get(get_metrics))
+        .route("/alarms", get(get_alarms))
+        .route_layer(from_fn_with_state(auth.require(Role::Viewer), require_role));
+
+    let operator = Router::new()
+        .route("/devices", post(create_device))
+        .route("/devices/{id}", put(update_device))
+        .route("/alarms/{id}/acknowledge", post(acknowledge_alarm))
+        .route_layer(from_fn_with_state(
+            auth.require(Role::Operator),
+            require_role,
+        ));
+
+    let admin = Router::new()
+        .route("/devices/{id}", delete(delete_device))
+        .route("/config/reload", post(reload_config))
+        .route_layer(from_fn_with_state(auth.require(Role::Admin), require_role));
+
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
+        .merge(viewer)
+        .merge(operator)
+        .merge(admin)
+        .layer(from_fn_with_state(
+            app_state.metrics.clone(),
+            track_requests,
+        ))
+        .with_state(app_state)
+}
+
+// Records latency per route template (not per concrete path, to bound label cardinality)
+async fn track_requests(State(metrics): State<Metrics>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".into());
+
+    let response = next.run(req).await;
+
+    metrics.observe_http_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_secs_f64(),
+    );
+    response
+}
+
+pub async fn get_metrics(State(app): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        app.metrics.render(),
+    )
+}
+
 pub async fn get_devices(State(app): State<AppState>) -> Json<Vec<Device>> {
     let state = app.state.lock().await;
     Json(state.devices.clone())
@@ -15,12 +95,13 @@ pub async fn create_device(
     Json(payload): Json<DeviceInput>,
 ) -> Result<Json<Device>, StatusCode> {
     info!("API: Creating device id={}", payload.id);
+    ensure_writable(&app, &payload.id).await?;
     let timestamp = chrono::Utc::now().timestamp_millis();
 
     app.tx
         .send(GatewayEvent::DeviceCreated {
             id: payload.id.clone(),
-            timestamp: timestamp,
+            timestamp,
         })
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -28,7 +109,7 @@ pub async fn create_device(
     Ok(Json(Device {
         id: payload.id,
         value: Some(payload.value),
-        timestamp: timestamp,
+        timestamp,
     }))
 }
 
@@ -37,6 +118,7 @@ pub async fn update_device(
     Json(payload): Json<DeviceInput>,
 ) -> Result<Json<Device>, StatusCode> {
     info!("API: Updating device id={}", payload.id);
+    ensure_writable(&app, &payload.id).await?;
 
     let timestamp = chrono::Utc::now().timestamp_millis();
 
@@ -44,7 +126,7 @@ pub async fn update_device(
         .send(GatewayEvent::DeviceValueObserved {
             id: payload.id.clone(),
             value: payload.value,
-            timestamp: timestamp,
+            timestamp,
         })
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -52,7 +134,7 @@ pub async fn update_device(
     Ok(Json(Device {
         id: payload.id.clone(),
         value: Some(payload.value),
-        timestamp: timestamp,
+        timestamp,
     }))
 }
 
@@ -61,10 +143,11 @@ pub async fn delete_device(
     Path(id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
     info!("API: Deleting device id={}", id);
+    ensure_writable(&app, &id).await?;
 
     app.tx
         .send(GatewayEvent::DeviceRemoved {
-            id: id,
+            id,
             timestamp: chrono::Utc::now().timestamp_millis(),
         })
         .await
@@ -73,6 +156,105 @@ pub async fn delete_device(
     Ok(StatusCode::NO_CONTENT)
 }
 
-pub async fn health_check() -> &'static str {
-    "OK"
+// Virtual devices are computed by the gateway and cannot be written through the API
+async fn ensure_writable(app: &AppState, id: &str) -> Result<(), StatusCode> {
+    if app.state.lock().await.virtual_devices.contains(id) {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlarmFilter {
+    pub state: Option<String>,
+}
+
+// Open alarms and the most recently cleared ones, optionally filtered by state
+pub async fn get_alarms(
+    State(app): State<AppState>,
+    Query(filter): Query<AlarmFilter>,
+) -> Json<Vec<Alarm>> {
+    let state = app.state.lock().await;
+    let alarms = state
+        .alarms
+        .alarms()
+        .into_iter()
+        .filter(|a| {
+            filter
+                .state
+                .as_deref()
+                .is_none_or(|s| s == a.state.as_str())
+        })
+        .collect();
+    Json(alarms)
+}
+
+// Checked against the current state first so callers get 404 / 409 instead of a silently
+// ignored event; the acknowledgement itself goes through the event loop
+pub async fn acknowledge_alarm(
+    State(app): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    info!("API: Acknowledging alarm id={} by {}", id, principal.name);
+
+    match app.state.lock().await.alarms.get(&id) {
+        None => return Err(StatusCode::NOT_FOUND),
+        Some(alarm) if alarm.state != AlarmState::Active => return Err(StatusCode::CONFLICT),
+        Some(_) => {}
+    }
+
+    app.tx
+        .send(GatewayEvent::AlarmAcknowledged {
+            alarm_id: id,
+            by: principal.name,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// Applies the config file to the running gateway; an invalid file is rejected with the
+// reason and changes nothing
+pub async fn reload_config(
+    State(app): State<AppState>,
+    Extension(principal): Extension<Principal>,
+) -> Result<Json<ConfigDiff>, (StatusCode, String)> {
+    info!("API: Config reload requested by {}", principal.name);
+    let Some(reload) = &app.reload else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "config reload is not available".into(),
+        ));
+    };
+
+    reload
+        .reload(ReloadTrigger::Api)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))
+}
+
+// Kept for existing probes; equivalent to /health/live
+pub async fn health_check(state: State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    health_live(state).await
+}
+
+pub async fn health_live(State(app): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    health_response(app.health.liveness())
+}
+
+pub async fn health_ready(State(app): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    health_response(app.health.readiness())
+}
+
+fn health_response(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = match report.status {
+        ComponentStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+
+    (status, Json(report))
 }
@@ -0,0 +1,91 @@
+use crate::config::TlsConfig;
+use anyhow::{anyhow, Context, Result};
+use axum::Router;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use std::{fs::File, io::BufReader, sync::Arc, time::Duration};
+use tokio::sync::broadcast;
+use tracing::info;
+
+// Builds the rustls server config; client certificates are required when a client CA is set
+pub fn load_server_config(config: &TlsConfig) -> Result<ServerConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let certs = load_certs(&config.cert_path)?;
+    let key = load_key(&config.key_path)?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Unsupported TLS protocol versions")?;
+
+    let builder = match &config.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("Invalid client CA certificate in {}", ca_path))?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .context("Failed to build client certificate verifier")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    builder
+        .with_single_cert(certs, key)
+        .context("Server certificate does not match private key")
+}
+
+// Serves the router over TLS until the shutdown signal is received
+pub async fn serve(
+    listener: std::net::TcpListener,
+    app: Router,
+    server_config: ServerConfig,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<()> {
+    let handle = Handle::new();
+
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        shutdown.recv().await.ok();
+        info!("HTTPS server shutting down");
+        shutdown_handle.graceful_shutdown(Some(Duration::from_secs(5)));
+    });
+
+    listener.set_nonblocking(true)?;
+    axum_server::from_tcp_rustls(listener, RustlsConfig::from_config(Arc::new(server_config)))
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).with_context(|| format!("Cannot open certificate {}", path))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid PEM certificate in {}", path))?;
+
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in {}", path));
+    }
+
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path).with_context(|| format!("Cannot open private key {}", path))?;
+
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| format!("Invalid PEM private key in {}", path))?
+        .ok_or_else(|| anyhow!("No private key found in {}", path))
+}
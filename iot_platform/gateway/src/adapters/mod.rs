@@ -2,4 +2,4 @@ pub mod api;
 pub mod modbus;
 pub mod mqtt;
 pub mod simulation;
-pub mod spawn_service;
+pub mod sources;
@@ -1,8 +1,11 @@
 use crate::config::ModbusConfig;
-use crate::core::{events::GatewayEvent, lifecycle::Lifecycle};
+use crate::core::{
+    events::GatewayEvent, health::HealthRegistry, lifecycle::Lifecycle, metrics::Metrics,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use std::net::SocketAddr;
+use std::time::Instant;
 use tokio::net::lookup_host;
 use tokio::{
     select,
@@ -13,34 +16,63 @@ use tokio_modbus::client::tcp;
 use tokio_modbus::prelude::*;
 use tracing::{debug, error, info, warn};
 
+pub mod scan;
+
+pub const HEALTH_COMPONENT: &str = "source.modbus";
+
+// One register is read as unsigned 16 bit, two as a big-endian 32 bit signed value
+pub fn decode(registers: &[u16]) -> Option<i32> {
+    match registers {
+        [value] => Some(*value as i32),
+        [high, low] => Some((((*high as u32) << 16) | *low as u32) as i32),
+        _ => None,
+    }
+}
+
 #[derive(Clone)]
 pub struct ModbusPoller {
     config: ModbusConfig,
     tx: Sender<GatewayEvent>,
+    health: HealthRegistry,
+    metrics: Metrics,
 }
 
 impl ModbusPoller {
-    pub fn new(config: ModbusConfig, tx: Sender<GatewayEvent>) -> Self {
-        Self { config, tx }
+    pub fn new(
+        config: ModbusConfig,
+        tx: Sender<GatewayEvent>,
+        health: HealthRegistry,
+        metrics: Metrics,
+    ) -> Self {
+        // Not critical: readiness stays degraded, not down, while the source reconnects
+        health.register(HEALTH_COMPONENT, false);
+        // A few missed cycles are tolerated before the source counts as stale
+        health.set_stale_after(HEALTH_COMPONENT, (config.poll_interval_ms * 3).max(5000));
+        Self {
+            config,
+            tx,
+            health,
+            metrics,
+        }
     }
 
     async fn poll_once(&self, ctx: &mut tokio_modbus::client::Context) -> Result<()> {
         for mapping in &self.config.registers {
-            let registers = ctx
+            let started = Instant::now();
+            let result = ctx
                 .read_holding_registers(mapping.address, mapping.count)
-                .await??;
-
-            let raw_value: i32 = match mapping.count {
-                1 => registers[0] as i32,
-                2 => {
-                    let high = registers[0] as u32;
-                    let low = registers[1] as u32;
-                    ((high << 16) | low) as i32
-                }
-                _ => {
-                    warn!("Unsupported register count: {}, skipping", mapping.count);
-                    continue;
-                }
+                .await;
+            self.metrics.observe_modbus_poll(
+                &mapping.device_id,
+                mapping.address,
+                started.elapsed().as_secs_f64(),
+                !matches!(result, Ok(Ok(_))),
+            );
+            let registers = result??;
+
+            let Some(raw_value) = decode(&registers) else {
+                warn!("Unsupported register count: {}, skipping", mapping.count);
+                continue;
             };
 
             let scaled_value = raw_value as f64 * mapping.scale;
@@ -81,12 +113,17 @@ impl ModbusPoller {
 
         loop {
             match self.poll_once(&mut ctx).await {
-                Ok(_) => debug!("{}: Modbus poll successful", self.config.device_name),
+                Ok(_) => {
+                    self.health.mark_success(HEALTH_COMPONENT);
+                    debug!("{}: Modbus poll successful", self.config.device_name)
+                }
                 Err(e) => {
                     error!(
                         "{}: Modbus poll failed: {}, retrying...",
                         self.config.device_name, e
                     );
+                    self.health
+                        .mark_degraded(HEALTH_COMPONENT, format!("poll failed: {}", e));
                     sleep(Duration::from_secs(1)).await;
                     continue;
                 }
@@ -133,6 +170,10 @@ impl Lifecycle for ModbusPoller {
                         "{}: Modbus connection failed: {}",
                         self.config.device_name, e
                     );
+                    self.health.mark_down(
+                        HEALTH_COMPONENT,
+                        format!("reconnecting after connection failure: {}", e),
+                    );
                     info!("{}: Retrying in 5 seconds...", self.config.device_name);
                     select! {
                         _ = sleep(Duration::from_secs(5)) => {},
@@ -145,6 +186,7 @@ impl Lifecycle for ModbusPoller {
             }
         }
 
+        self.health.mark_down(HEALTH_COMPONENT, "stopped");
         info!("Modbus poller stopped");
     }
 }
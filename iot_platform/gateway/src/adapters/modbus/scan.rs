@@ -0,0 +1,76 @@
+use super::decode;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use tokio::net::lookup_host;
+use tokio::time::{timeout, Duration};
+use tokio_modbus::client::tcp;
+use tokio_modbus::prelude::*;
+
+// What to probe; each value spans `count` registers, so addresses advance by `count`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanRequest {
+    pub host: String,
+    pub port: u16,
+    pub slaves: RangeInclusive<u8>,
+    pub addresses: RangeInclusive<u16>,
+    pub count: u16,
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reading {
+    // Raw registers and the value as ModbusPoller would decode it (before scaling)
+    Value { registers: Vec<u16>, decoded: i32 },
+    // Modbus exception or timeout, e.g. an unmapped address
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanResult {
+    pub slave: u8,
+    pub address: u16,
+    pub reading: Reading,
+}
+
+// Reads holding registers one value at a time over a single connection. Only a failed
+// connection is an error; per-address failures are part of the result.
+pub async fn scan(request: &ScanRequest) -> Result<Vec<ScanResult>, String> {
+    let addr = format!("{}:{}", request.host, request.port);
+    let socket_addr: SocketAddr = lookup_host(&addr)
+        .await
+        .map_err(|e| format!("cannot resolve {}: {}", addr, e))?
+        .next()
+        .ok_or_else(|| format!("no address found for {}", addr))?;
+    let mut ctx = tcp::connect_slave(socket_addr, Slave(*request.slaves.start()))
+        .await
+        .map_err(|e| format!("cannot connect to {}: {}", socket_addr, e))?;
+
+    let count = request.count.max(1);
+    let mut results = Vec::new();
+    for slave in request.slaves.clone() {
+        ctx.set_slave(Slave(slave));
+        for address in request.addresses.clone().step_by(count as usize) {
+            let read = timeout(
+                Duration::from_millis(request.timeout_ms),
+                ctx.read_holding_registers(address, count),
+            )
+            .await;
+            let reading = match read {
+                Ok(Ok(Ok(registers))) => match decode(&registers) {
+                    Some(decoded) => Reading::Value { registers, decoded },
+                    None => Reading::Failed(format!("cannot decode {} registers", count)),
+                },
+                Ok(Ok(Err(exception))) => Reading::Failed(format!("exception: {}", exception)),
+                Ok(Err(e)) => Reading::Failed(e.to_string()),
+                Err(_) => Reading::Failed(format!("no reply within {} ms", request.timeout_ms)),
+            };
+            results.push(ScanResult {
+                slave,
+                address,
+                reading,
+            });
+        }
+    }
+
+    Ok(results)
+}
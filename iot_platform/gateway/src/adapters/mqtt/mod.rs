@@ -1,15 +1,22 @@
-use crate::config::MqttConfig;
+use crate::config::{AggregationConfig, MqttConfig};
+use crate::core::aggregation::AggregateSink;
+use crate::core::alarms::AlarmState;
+use crate::core::health::{HealthRegistry, MQTT};
+use crate::core::metrics::Metrics;
 use crate::core::state::ListenerError;
 use crate::core::state::{StateChange, StateListener};
 use async_trait::async_trait;
-use rumqttc::{AsyncClient, MqttOptions};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
 use shared_models::{
-    DeviceContext, DeviceCreatedPayload, DeviceRemovedPayload, DeviceValueObservedPayload, Metadata,
+    AggregateWindow, AlarmPayload, DeviceAggregatePayload, DeviceContext, DeviceCreatedPayload,
+    DeviceRemovedPayload, DeviceValueObservedPayload, GatewayStatus, GatewayStatusPayload,
+    Metadata,
 };
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot, Notify};
 use tokio::task;
-use tracing::error;
+use tracing::{error, info, warn};
 
 struct MqttMessage {
     topic: String,
@@ -17,11 +24,23 @@ struct MqttMessage {
     retain: bool,
 }
 
+enum Outbound {
+    Message(MqttMessage),
+    // Queued behind all messages: publish the offline status, then disconnect
+    Shutdown(oneshot::Sender<()>),
+}
+
 pub struct MqttPublisher {
     pub config: MqttConfig,
     pub gateway_id: String,
     pub gateway_name: String,
-    sender: mpsc::Sender<MqttMessage>,
+    sender: mpsc::Sender<Outbound>,
+    // Notified once the MQTT event loop has written the DISCONNECT packet
+    disconnected: Arc<Notify>,
+    health: HealthRegistry,
+    metrics: Metrics,
+    // Raw values of these devices are replaced by aggregate windows
+    aggregation: Option<AggregationConfig>,
 }
 
 impl MqttPublisher {
@@ -29,54 +48,209 @@ impl MqttPublisher {
         config: MqttConfig,
         gateway_id: String,
         gateway_name: String,
+        health: HealthRegistry,
+        metrics: Metrics,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let asset_name = config.device_name.clone();
         let mut mqtt_options = MqttOptions::new(&config.client_id, &config.broker, config.port);
         mqtt_options.set_keep_alive(Duration::from_secs(5));
 
+        // Retained, so subscribers see whether the gateway is up; the broker publishes the
+        // last will when the connection drops without a DISCONNECT
+        let status_topic = format!("{}/status", gateway_name);
+        let status = |status| {
+            serde_json::to_vec(&GatewayStatusPayload {
+                gateway_id: gateway_id.clone(),
+                gateway_name: gateway_name.clone(),
+                status,
+                meta: Metadata {
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                },
+            })
+        };
+        mqtt_options.set_last_will(LastWill::new(
+            &status_topic,
+            status(GatewayStatus::Offline)?,
+            QoS::AtLeastOnce,
+            true,
+        ));
+
         let (client, mut eventloop) = AsyncClient::new(mqtt_options, 1000);
 
+        let eventloop_health = health.clone();
+        let eventloop_client = client.clone();
+        let eventloop_metrics = metrics.clone();
+        let online = status(GatewayStatus::Online)?;
+        let online_topic = status_topic.clone();
+        let disconnected = Arc::new(Notify::new());
+        let eventloop_disconnected = disconnected.clone();
         task::spawn(async move {
             loop {
                 match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        eventloop_health.mark_up(MQTT);
+                        // Not awaited: the request queue is drained by this loop
+                        if let Err(e) = eventloop_client.try_publish(
+                            &online_topic,
+                            QoS::AtLeastOnce,
+                            true,
+                            online.clone(),
+                        ) {
+                            warn!("{}: MQTT online status not sent: {}", asset_name, e);
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::PubAck(_))) => eventloop_metrics.mqtt_acknowledged(),
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                        eventloop_disconnected.notify_one();
+                        break;
+                    }
                     Ok(_) => {}
                     Err(e) => {
                         error!("{}: MQTT eventloop error: {}", asset_name, e);
+                        eventloop_health.mark_down(MQTT, format!("broker connection: {}", e));
                         tokio::time::sleep(Duration::from_secs(2)).await;
                     }
                 }
             }
         });
 
-        let (tx, mut rx) = mpsc::channel::<MqttMessage>(1000);
+        let (tx, mut rx) = mpsc::channel::<Outbound>(1000);
 
         let client_clone = client.clone();
 
         let asset_name = config.device_name.clone();
+        let publish_health = health.clone();
+        let publish_metrics = metrics.clone();
+        let offline = status(GatewayStatus::Offline)?;
 
         tokio::spawn(async move {
-            while let Some(msg) = rx.recv().await {
-                if let Err(e) = client_clone
-                    .publish(
-                        msg.topic,
-                        rumqttc::QoS::AtLeastOnce,
-                        msg.retain,
-                        msg.payload,
-                    )
+            while let Some(outbound) = rx.recv().await {
+                let msg = match outbound {
+                    Outbound::Message(msg) => msg,
+                    Outbound::Shutdown(done) => {
+                        // Anything queued behind the marker is dropped
+                        let offline = offline.clone();
+                        let _ = client_clone
+                            .publish(&status_topic, QoS::AtLeastOnce, true, offline)
+                            .await;
+                        let _ = client_clone.disconnect().await;
+                        let _ = done.send(());
+                        break;
+                    }
+                };
+                publish_health.set_queue_depth(MQTT, rx.len());
+                publish_metrics.set_mqtt_queue_depth(rx.len());
+                let result = client_clone
+                    .publish(msg.topic, QoS::AtLeastOnce, msg.retain, msg.payload)
                     .await
-                {
-                    error!("{}: MQTT publish failed: {}", asset_name, e);
-                }
+                    .map_err(|e| e.to_string());
+                record_publish(&publish_health, &publish_metrics, &asset_name, result);
             }
         });
 
         Ok(Self {
-            config: config,
+            config,
             gateway_id,
             gateway_name,
             sender: tx,
+            disconnected,
+            health,
+            metrics,
+            aggregation: None,
         })
     }
+
+    // Last shutdown phase: publishes everything queued so far, then the offline status,
+    // and disconnects. Gives up after `flush_timeout_ms`, e.g. while the broker is down.
+    pub async fn shutdown(&self) {
+        let timeout = Duration::from_millis(self.config.flush_timeout_ms);
+        let queued = self.sender.max_capacity() - self.sender.capacity();
+        let flush = async {
+            let (done, flushed) = oneshot::channel();
+            self.sender.send(Outbound::Shutdown(done)).await.ok()?;
+            flushed.await.ok()?;
+            self.disconnected.notified().await;
+            Some(())
+        };
+
+        match tokio::time::timeout(timeout, flush).await {
+            Ok(Some(())) => info!("MQTT: flushed {} queued messages and disconnected", queued),
+            Ok(None) => warn!("MQTT: publisher already stopped, offline status not sent"),
+            Err(_) => warn!(
+                "MQTT: not flushed within {} ms, up to {} queued messages and the offline status are lost",
+                self.config.flush_timeout_ms, queued
+            ),
+        }
+    }
+
+    pub fn with_aggregation(mut self, aggregation: Option<AggregationConfig>) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    async fn send(
+        &self,
+        topic: String,
+        payload: Vec<u8>,
+        retain: bool,
+    ) -> Result<(), ListenerError> {
+        self.sender
+            .send(Outbound::Message(MqttMessage {
+                topic,
+                payload,
+                retain,
+            }))
+            .await
+            .map_err(|e| ListenerError::Mqtt(e.to_string()))?;
+
+        let queue_depth = self.sender.max_capacity() - self.sender.capacity();
+        self.health.set_queue_depth(MQTT, queue_depth);
+        self.metrics.set_mqtt_queue_depth(queue_depth);
+
+        Ok(())
+    }
+}
+
+// A failed publish degrades MQTT until the next one succeeds
+fn record_publish(
+    health: &HealthRegistry,
+    metrics: &Metrics,
+    asset_name: &str,
+    result: Result<(), String>,
+) {
+    metrics.mqtt_enqueued(result.is_ok());
+    match result {
+        Ok(()) => health.mark_recovered(MQTT),
+        Err(e) => {
+            error!("{}: MQTT publish failed: {}", asset_name, e);
+            health.mark_degraded(MQTT, format!("publish failed: {}", e));
+        }
+    }
+}
+
+#[async_trait]
+impl AggregateSink for MqttPublisher {
+    async fn publish_aggregate(
+        &self,
+        device_id: &str,
+        window: AggregateWindow,
+    ) -> Result<(), ListenerError> {
+        let topic = format!("{}/devices/{}/aggregate", self.gateway_name, device_id);
+        let payload = DeviceAggregatePayload {
+            ctx: DeviceContext {
+                gateway_id: self.gateway_id.clone(),
+                gateway_name: self.gateway_name.clone(),
+                device_name: self.config.device_name.clone(),
+                device_id: device_id.to_string(),
+            },
+            meta: Metadata {
+                timestamp: window.end,
+            },
+            window,
+        };
+        let bytes = serde_json::to_vec(&payload).map_err(|e| ListenerError::Mqtt(e.to_string()))?;
+        self.send(topic, bytes, false).await
+    }
 }
 
 #[async_trait]
@@ -98,6 +272,14 @@ impl StateListener for MqttPublisher {
                     serde_json::to_vec(&payload).map_err(|e| ListenerError::Mqtt(e.to_string()))?;
                 (topic, bytes, true) // retain = true
             }
+            StateChange::DeviceUpdated { id, .. }
+                if self
+                    .aggregation
+                    .as_ref()
+                    .is_some_and(|a| !a.publish_raw && a.aggregates(&id)) =>
+            {
+                return Ok(());
+            }
             StateChange::DeviceUpdated {
                 id,
                 value,
@@ -133,17 +315,69 @@ impl StateListener for MqttPublisher {
                     serde_json::to_vec(&payload).map_err(|e| ListenerError::Mqtt(e.to_string()))?;
                 (topic, bytes, true) // retain = true
             }
+            StateChange::AlarmRaised(alarm)
+            | StateChange::AlarmAcknowledged(alarm)
+            | StateChange::AlarmCleared(alarm) => {
+                let suffix = match alarm.state {
+                    AlarmState::Active => "alarm_raised",
+                    AlarmState::Acknowledged => "alarm_acknowledged",
+                    AlarmState::Cleared => "alarm_cleared",
+                };
+                let topic = format!(
+                    "{}/devices/{}/{}",
+                    self.gateway_name, alarm.device_id, suffix
+                );
+                let payload = AlarmPayload {
+                    ctx: DeviceContext {
+                        gateway_id: self.gateway_id.clone(),
+                        gateway_name: self.gateway_name.clone(),
+                        device_name: self.config.device_name.clone(),
+                        device_id: alarm.device_id.clone(),
+                    },
+                    alarm: alarm.record(),
+                    meta: Metadata {
+                        timestamp: alarm
+                            .cleared_at
+                            .or(alarm.acknowledged_at)
+                            .unwrap_or(alarm.raised_at),
+                    },
+                };
+                let bytes =
+                    serde_json::to_vec(&payload).map_err(|e| ListenerError::Mqtt(e.to_string()))?;
+                (topic, bytes, false) // retain = false
+            }
         };
 
-        self.sender
-            .send(MqttMessage {
-                topic,
-                payload: payload_bytes,
-                retain,
-            })
-            .await
-            .map_err(|e| ListenerError::Mqtt(e.to_string()))?;
+        self.send(topic, payload_bytes, retain).await
+    }
 
-        Ok(())
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::health::ComponentStatus;
+
+    fn mqtt_status(health: &HealthRegistry) -> ComponentStatus {
+        health.readiness().components[MQTT].status
+    }
+
+    #[test]
+    fn successful_publish_lifts_degradation_but_not_a_lost_connection() {
+        let (health, metrics) = (HealthRegistry::new(), Metrics::new());
+        health.register(MQTT, true);
+        health.mark_up(MQTT);
+
+        record_publish(&health, &metrics, "MQTT", Err("request queue full".into()));
+        assert_eq!(mqtt_status(&health), ComponentStatus::Degraded);
+        record_publish(&health, &metrics, "MQTT", Ok(()));
+        assert_eq!(mqtt_status(&health), ComponentStatus::Up);
+
+        health.mark_down(MQTT, "broker connection: refused");
+        record_publish(&health, &metrics, "MQTT", Ok(()));
+        assert_eq!(mqtt_status(&health), ComponentStatus::Down);
     }
 }
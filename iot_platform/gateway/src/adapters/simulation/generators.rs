@@ -0,0 +1,230 @@
+use crate::config::SignalGenerator;
+use rand::Rng;
+use std::f64::consts::TAU;
+use std::path::Path;
+
+// A generator with its runtime state
+pub enum Signal {
+    // Depends only on the elapsed time
+    Periodic(SignalGenerator),
+    RandomWalk {
+        current: f64,
+        step: f64,
+        min: f64,
+        max: f64,
+    },
+    Replay {
+        values: Vec<f64>,
+        next: usize,
+        repeat: bool,
+    },
+}
+
+impl Signal {
+    // Reads the file of a replay generator
+    pub fn new(generator: &SignalGenerator) -> Result<Self, String> {
+        Ok(match generator {
+            SignalGenerator::RandomWalk {
+                start,
+                step,
+                min,
+                max,
+            } => Signal::RandomWalk {
+                current: *start,
+                step: *step,
+                min: *min,
+                max: *max,
+            },
+            SignalGenerator::Replay {
+                path,
+                column,
+                repeat,
+            } => Signal::Replay {
+                values: load_csv(path, *column)?,
+                next: 0,
+                repeat: *repeat,
+            },
+            other => Signal::Periodic(other.clone()),
+        })
+    }
+
+    // None once a replay without repeat has run out of rows
+    pub fn sample(&mut self, elapsed_ms: u64, rng: &mut impl Rng) -> Option<f64> {
+        match self {
+            Signal::Periodic(generator) => Some(periodic(generator, elapsed_ms, rng)),
+            Signal::RandomWalk {
+                current,
+                step,
+                min,
+                max,
+            } => {
+                let value = *current;
+                *current = (*current + rng.random_range(-1.0..=1.0) * *step).clamp(*min, *max);
+                Some(value)
+            }
+            Signal::Replay {
+                values,
+                next,
+                repeat,
+            } => {
+                if *next >= values.len() {
+                    if !*repeat {
+                        return None;
+                    }
+                    *next = 0;
+                }
+                *next += 1;
+                Some(values[*next - 1])
+            }
+        }
+    }
+}
+
+fn periodic(generator: &SignalGenerator, elapsed_ms: u64, rng: &mut impl Rng) -> f64 {
+    match generator {
+        SignalGenerator::Constant { value } => *value,
+        SignalGenerator::Random { min, max } => min + rng.random::<f64>() * (max - min),
+        SignalGenerator::Sine {
+            amplitude,
+            period_ms,
+            offset,
+        } => {
+            let phase = (elapsed_ms % period_ms) as f64 / *period_ms as f64;
+            offset + amplitude * (phase * TAU).sin()
+        }
+        SignalGenerator::Sawtooth {
+            min,
+            max,
+            period_ms,
+        } => {
+            let phase = (elapsed_ms % period_ms) as f64 / *period_ms as f64;
+            min + phase * (max - min)
+        }
+        SignalGenerator::Step { values, hold_ms } => {
+            values[(elapsed_ms / hold_ms) as usize % values.len()]
+        }
+        SignalGenerator::RandomWalk { start, .. } => *start,
+        SignalGenerator::Replay { .. } => unreachable!("replay keeps its rows in Signal::Replay"),
+    }
+}
+
+// Normally distributed with mean 0 (Box-Muller)
+pub fn gaussian(std_dev: f64, rng: &mut impl Rng) -> f64 {
+    if std_dev <= 0.0 {
+        return 0.0;
+    }
+    let u1 = rng.random::<f64>().max(f64::MIN_POSITIVE);
+    let u2 = rng.random::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos() * std_dev
+}
+
+// Values of one column, the last one by default
+pub fn load_csv(path: &Path, column: Option<usize>) -> Result<Vec<f64>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let values = parse_csv(&content, column);
+    if values.is_empty() {
+        return Err(format!("{} has no numeric values", path.display()));
+    }
+    Ok(values)
+}
+
+fn parse_csv(content: &str, column: Option<usize>) -> Vec<f64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = match column {
+                Some(i) => fields.get(i)?,
+                None => fields.last()?,
+            };
+            field.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn samples(generator: SignalGenerator, times: &[u64]) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut signal = Signal::new(&generator).unwrap();
+        times
+            .iter()
+            .map(|t| signal.sample(*t, &mut rng).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn periodic_generators_follow_elapsed_time() {
+        let sine = samples(
+            SignalGenerator::Sine {
+                amplitude: 10.0,
+                period_ms: 1000,
+                offset: 20.0,
+            },
+            &[0, 250, 750, 1250],
+        );
+        for (value, expected) in sine.iter().zip([20.0, 30.0, 10.0, 30.0]) {
+            assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+        }
+
+        let sawtooth = SignalGenerator::Sawtooth {
+            min: 0.0,
+            max: 100.0,
+            period_ms: 1000,
+        };
+        assert_eq!(
+            samples(sawtooth, &[0, 250, 500, 1000]),
+            [0.0, 25.0, 50.0, 0.0]
+        );
+
+        let step = SignalGenerator::Step {
+            values: vec![1.0, 2.0, 3.0],
+            hold_ms: 100,
+        };
+        assert_eq!(
+            samples(step, &[0, 99, 100, 250, 300]),
+            [1.0, 1.0, 2.0, 3.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn random_walk_stays_within_its_bounds() {
+        let walk = SignalGenerator::RandomWalk {
+            start: 50.0,
+            step: 5.0,
+            min: 45.0,
+            max: 55.0,
+        };
+        let values = samples(walk, &[0; 200]);
+        assert_eq!(values[0], 50.0);
+        assert!(values.iter().all(|v| (45.0..=55.0).contains(v)));
+        assert!(values.windows(2).all(|w| (w[1] - w[0]).abs() <= 5.0));
+    }
+
+    #[test]
+    fn replay_reads_a_column_and_repeats_or_stops() {
+        let csv = "timestamp,flow,pressure\n1,10.5,2.0\n2,11,2.1\n\n3,bad,2.2\n";
+        assert_eq!(parse_csv(csv, None), [2.0, 2.1, 2.2]);
+        assert_eq!(parse_csv(csv, Some(1)), [10.5, 11.0]);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut once = Signal::Replay {
+            values: vec![1.0, 2.0],
+            next: 0,
+            repeat: false,
+        };
+        let mut looped = Signal::Replay {
+            values: vec![1.0, 2.0],
+            next: 0,
+            repeat: true,
+        };
+        let once: Vec<_> = (0..3).map(|_| once.sample(0, &mut rng)).collect();
+        let looped: Vec<_> = (0..3).map(|_| looped.sample(0, &mut rng)).collect();
+        assert_eq!(once, [Some(1.0), Some(2.0), None]);
+        assert_eq!(looped, [Some(1.0), Some(2.0), Some(1.0)]);
+    }
+}
@@ -1,52 +1,179 @@
-use crate::config::SimulationConfig;
-use crate::core::{events::GatewayEvent, lifecycle::Lifecycle};
+pub mod generators;
+
+use crate::config::{SimulatedDeviceConfig, SimulationConfig};
+use crate::core::{events::GatewayEvent, health::HealthRegistry, lifecycle::Lifecycle};
 use async_trait::async_trait;
+use generators::{gaussian, Signal};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use tokio::{
     select,
     sync::{broadcast, mpsc::Sender},
-    time::{sleep, Duration},
+    time::{sleep_until, Duration, Instant},
 };
-use tracing::info;
+use tracing::{error, info};
+
+pub const HEALTH_COMPONENT: &str = "source.simulation";
 
 #[derive(Clone)]
 pub struct SimulationPoller {
     config: SimulationConfig,
     tx: Sender<GatewayEvent>,
+    health: HealthRegistry,
+}
+
+// One configured device with its generator state and schedule
+struct SimulatedDevice {
+    config: SimulatedDeviceConfig,
+    signal: Signal,
+    interval: Duration,
+    // None once a replay has ended
+    next_due: Option<Instant>,
 }
 
 impl SimulationPoller {
-    pub fn new(config: SimulationConfig, tx: Sender<GatewayEvent>) -> Self {
-        Self { config, tx }
+    pub fn new(config: SimulationConfig, tx: Sender<GatewayEvent>, health: HealthRegistry) -> Self {
+        // Not critical: a missing source degrades readiness without failing it
+        health.register(HEALTH_COMPONENT, false);
+        // Any device's sample counts, so the fastest one sets the pace
+        let fastest = config
+            .devices()
+            .iter()
+            .map(|d| d.interval_ms(&config))
+            .min()
+            .unwrap_or(config.interval_ms);
+        health.set_stale_after(HEALTH_COMPONENT, fastest * 3);
+        Self { config, tx, health }
+    }
+
+    fn devices(&self, start: Instant) -> Result<Vec<SimulatedDevice>, String> {
+        self.config
+            .devices()
+            .into_iter()
+            .map(|config| {
+                let signal = Signal::new(&config.generator)
+                    .map_err(|e| format!("device \"{}\": {}", config.id, e))?;
+                Ok(SimulatedDevice {
+                    interval: Duration::from_millis(config.interval_ms(&self.config).max(1)),
+                    signal,
+                    config,
+                    next_due: Some(start),
+                })
+            })
+            .collect()
+    }
+
+    // Samples every device that is due and schedules its next sample
+    async fn tick(&self, devices: &mut [SimulatedDevice], start: Instant, rng: &mut StdRng) {
+        let now = Instant::now();
+        let elapsed_ms = now.duration_since(start).as_millis() as u64;
+        for device in devices.iter_mut() {
+            let Some(due) = device.next_due.filter(|due| *due <= now) else {
+                continue;
+            };
+            // Behind schedule (e.g. a full event channel): skip the missed samples
+            let mut next = due + device.interval;
+            while next <= now {
+                next += device.interval;
+            }
+            device.next_due = Some(next);
+
+            let Some(value) = device.signal.sample(elapsed_ms, rng) else {
+                info!(
+                    "{}: Replay for device {} finished",
+                    self.config.device_name, device.config.id
+                );
+                device.next_due = None;
+                continue;
+            };
+            if rng.random::<f64>() < device.config.dropout {
+                continue;
+            }
+            let value = value + gaussian(device.config.noise, rng) + self.config.add_value as f64;
+            let _ = self
+                .tx
+                .send(GatewayEvent::DeviceValueObserved {
+                    id: device.config.id.clone(),
+                    value,
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                })
+                .await;
+        }
+        self.health.mark_success(HEALTH_COMPONENT);
     }
 }
 
 #[async_trait]
 impl Lifecycle for SimulationPoller {
-    async fn run(self, shutdown: broadcast::Receiver<()>) {
-        let tx = self.tx.clone();
-        let mut tick_shutdown = shutdown.resubscribe();
-
-        tokio::spawn(async move {
-            loop {
-                select! {
-                    _ = sleep(Duration::from_millis(self.config.interval_ms)) => {
-                        for device_id in 1..=2 { // Simulate 2 devices
-                            let _ = tx.send(GatewayEvent::DeviceValueObserved {
-                                id: device_id.to_string(),
-                                value: rand::random::<f64>() * 100.0 + self.config.add_value as f64,
-                                timestamp: chrono::Utc::now().timestamp_millis(),
-                            }).await;
-                        }
-                    }
-
-                    _ = tick_shutdown.recv() => {
-                        info!("{}: Simulation task shutting down", self.config.device_name);
-                        break;
-                    }
+    // Runs until shutdown; returning early would make the supervisor restart it
+    async fn run(self, mut shutdown: broadcast::Receiver<()>) {
+        let start = Instant::now();
+        let mut devices = match self.devices(start) {
+            Ok(devices) => devices,
+            Err(e) => {
+                error!("{}: {}", self.config.device_name, e);
+                self.health.mark_down(HEALTH_COMPONENT, e);
+                return;
+            }
+        };
+        let mut rng = StdRng::from_os_rng();
+
+        loop {
+            let next_due = devices.iter().filter_map(|d| d.next_due).min();
+            select! {
+                // Disabled once every replay has ended; then it idles until shutdown
+                _ = sleep_until(next_due.unwrap_or(start)), if next_due.is_some() => {
+                    self.tick(&mut devices, start, &mut rng).await;
+                }
+
+                _ = shutdown.recv() => {
+                    info!("{}: Simulation task shutting down", self.config.device_name);
+                    break;
                 }
             }
+        }
 
-            info!("{}: Simulation task stopped", self.config.device_name);
+        self.health.mark_down(HEALTH_COMPONENT, "stopped");
+        info!("{}: Simulation task stopped", self.config.device_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::core::{health::ServiceState, metrics::Metrics, supervisor::Supervisor};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn default_poller_keeps_running_under_the_supervisor() {
+        let config = Config::default();
+        let health = HealthRegistry::new();
+        let supervisor = Supervisor::new(config.supervisor, health.clone(), Metrics::new());
+        let (stop, _) = broadcast::channel(1);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let simulation = config.simulation;
+        let poller_health = health.clone();
+        supervisor.spawn(HEALTH_COMPONENT, stop.subscribe(), move || {
+            SimulationPoller::new(simulation.clone(), tx.clone(), poller_health.clone())
         });
+
+        // Every device samples right away; a run that returned early would be restarted by now
+        let event = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await;
+        assert!(matches!(
+            event,
+            Ok(Some(GatewayEvent::DeviceValueObserved { .. }))
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let service = health.services()[HEALTH_COMPONENT].clone();
+        assert_eq!(service.state, ServiceState::Running);
+        assert_eq!(service.restarts, 0);
+
+        stop.send(()).unwrap();
+        supervisor.shutdown().await;
+        assert_eq!(
+            health.services()[HEALTH_COMPONENT].state,
+            ServiceState::Stopped
+        );
     }
 }
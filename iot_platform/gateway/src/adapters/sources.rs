@@ -0,0 +1,89 @@
+use crate::adapters::{
+    modbus::{self, ModbusPoller},
+    simulation::{self, SimulationPoller},
+};
+use crate::config::{Config, SourceMode};
+use crate::core::{
+    events::GatewayEvent, health::HealthRegistry, metrics::Metrics, reload::SourceControl,
+    supervisor::Supervisor,
+};
+use tokio::sync::{broadcast, mpsc::Sender};
+use tracing::info;
+
+// Runs the source selected by `mode` with its own stop signal, so a config reload can
+// restart it without shutting down the rest of the gateway
+pub struct Sources {
+    tx: Sender<GatewayEvent>,
+    health: HealthRegistry,
+    metrics: Metrics,
+    supervisor: Supervisor,
+    running: Option<(SourceMode, broadcast::Sender<()>)>,
+}
+
+impl Sources {
+    pub fn new(
+        tx: Sender<GatewayEvent>,
+        health: HealthRegistry,
+        metrics: Metrics,
+        supervisor: Supervisor,
+    ) -> Self {
+        Self {
+            tx,
+            health,
+            metrics,
+            supervisor,
+            running: None,
+        }
+    }
+}
+
+fn health_component(mode: &SourceMode) -> &'static str {
+    match mode {
+        SourceMode::Modbus => modbus::HEALTH_COMPONENT,
+        SourceMode::Simulation => simulation::HEALTH_COMPONENT,
+    }
+}
+
+impl SourceControl for Sources {
+    fn start(&mut self, config: &Config) {
+        let previous = self.running.as_ref().map(|(mode, _)| mode.clone());
+        self.stop();
+        // A source that no longer runs must not keep readiness down
+        if let Some(mode) = previous.filter(|mode| *mode != config.mode) {
+            self.health.unregister(health_component(&mode));
+        }
+
+        // Supervised under the health component name, e.g. "source.modbus"
+        let (stop, _) = broadcast::channel(1);
+        let (tx, health, metrics) = (self.tx.clone(), self.health.clone(), self.metrics.clone());
+        match config.mode {
+            SourceMode::Modbus => {
+                let modbus = config.modbus.clone();
+                self.supervisor
+                    .spawn(modbus::HEALTH_COMPONENT, stop.subscribe(), move || {
+                        ModbusPoller::new(
+                            modbus.clone(),
+                            tx.clone(),
+                            health.clone(),
+                            metrics.clone(),
+                        )
+                    });
+            }
+            SourceMode::Simulation => {
+                let simulation = config.simulation.clone();
+                self.supervisor
+                    .spawn(simulation::HEALTH_COMPONENT, stop.subscribe(), move || {
+                        SimulationPoller::new(simulation.clone(), tx.clone(), health.clone())
+                    });
+            }
+        }
+        self.running = Some((config.mode.clone(), stop));
+    }
+
+    fn stop(&mut self) {
+        if let Some((mode, stop)) = self.running.take() {
+            info!("Stopping {:?} source", mode);
+            let _ = stop.send(());
+        }
+    }
+}
@@ -0,0 +1,502 @@
+use crate::adapters::modbus::scan::{scan, Reading, ScanRequest};
+use crate::config::{Config, ConfigSource};
+use http_body_util::{BodyExt, Empty};
+use hyper::body::Bytes;
+use hyper::header::{ACCEPT, AUTHORIZATION};
+use hyper::{Request, StatusCode};
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use tokio::time::{timeout, Duration};
+
+pub const USAGE: &str = "\
+usage: gateway [--config <path>] [--set <key>=<value>]... [command]
+
+commands:
+  run                    start the gateway (default)
+  check-config           validate the config, exit code 1 on problems
+  print-default-config   print the built-in default config as TOML
+  scan-modbus            probe holding registers and print decoded values
+      --host <host>              default: modbus.host
+      --port <port>              default: modbus.port
+      --slave <id|from-to>       default: modbus.slave_id
+      --address <addr|from-to>   default: 0-9
+      --count <1|2>              registers per value, default 1
+      --scale <factor>           applied to decoded values, default 1
+      --timeout-ms <ms>          per read, default 1000
+  dump-state             print devices, alarms and readiness of a running gateway
+      --url <http://host:port>   default: from the [api] section
+      --token <key>              API key when authentication is enabled
+      --timeout-ms <ms>          per request, default 5000
+  simulate               run with the simulation source
+      --interval-ms <ms>
+      --add-value <n>
+
+options:
+  --config <path>        config file (default: $GATEWAY_CONFIG or /config.toml)
+  --set <key>=<value>    override a config key, e.g. --set mqtt.broker=localhost
+  -h, --help             print this help";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // `simulate` is `run` with simulation overrides
+    Run,
+    Help,
+    CheckConfig,
+    PrintDefaultConfig,
+    ScanModbus(ScanOptions),
+    DumpState(DumpOptions),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub slaves: Option<RangeInclusive<u8>>,
+    pub addresses: RangeInclusive<u16>,
+    pub count: u16,
+    pub scale: f64,
+    pub timeout_ms: u64,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            host: None,
+            port: None,
+            slaves: None,
+            addresses: 0..=9,
+            count: 1,
+            scale: 1.0,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpOptions {
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub timeout_ms: u64,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub overrides: Vec<(String, String)>,
+    pub command: Command,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+        let mut args = Args(args.into_iter().collect::<Vec<_>>().into_iter());
+        let mut cli = Cli {
+            config: None,
+            overrides: Vec::new(),
+            command: Command::Run,
+        };
+        let mut command: Option<String> = None;
+        let mut scan = ScanOptions::default();
+        let mut dump = DumpOptions::default();
+
+        while let Some((flag, inline)) = args.next_flag() {
+            match (command.as_deref(), flag.as_str()) {
+                (_, "-h" | "--help") => {
+                    cli.command = Command::Help;
+                    return Ok(cli);
+                }
+                (_, "--config") => cli.config = Some(args.value(&flag, inline)?.into()),
+                (_, "--set") => {
+                    let assignment = args.value(&flag, inline)?;
+                    let (key, value) = assignment.split_once('=').ok_or_else(|| {
+                        format!("--set expects <key>=<value>, got '{}'", assignment)
+                    })?;
+                    cli.overrides.push((key.trim().into(), value.trim().into()));
+                }
+                (None, name) if !name.starts_with('-') => command = Some(name.to_string()),
+                (Some("scan-modbus"), "--host") => scan.host = Some(args.value(&flag, inline)?),
+                (Some("scan-modbus"), "--port") => scan.port = Some(args.parsed(&flag, inline)?),
+                (Some("scan-modbus"), "--slave") => {
+                    scan.slaves = Some(range(&args.value(&flag, inline)?)?)
+                }
+                (Some("scan-modbus"), "--address") => {
+                    scan.addresses = range(&args.value(&flag, inline)?)?
+                }
+                (Some("scan-modbus"), "--count") => scan.count = args.parsed(&flag, inline)?,
+                (Some("scan-modbus"), "--scale") => scan.scale = args.parsed(&flag, inline)?,
+                (Some("scan-modbus"), "--timeout-ms") => {
+                    scan.timeout_ms = args.parsed(&flag, inline)?
+                }
+                (Some("dump-state"), "--url") => dump.url = Some(args.value(&flag, inline)?),
+                (Some("dump-state"), "--token") => dump.token = Some(args.value(&flag, inline)?),
+                (Some("dump-state"), "--timeout-ms") => {
+                    dump.timeout_ms = args.parsed(&flag, inline)?
+                }
+                (Some("simulate"), "--interval-ms") => {
+                    let ms: u64 = args.parsed(&flag, inline)?;
+                    cli.overrides
+                        .push(("simulation.interval_ms".into(), ms.to_string()));
+                }
+                (Some("simulate"), "--add-value") => {
+                    let value: i32 = args.parsed(&flag, inline)?;
+                    cli.overrides
+                        .push(("simulation.add_value".into(), value.to_string()));
+                }
+                (_, other) => return Err(format!("unknown argument '{}'\n\n{}", other, USAGE)),
+            }
+        }
+
+        cli.command = match command.as_deref() {
+            None | Some("run") => Command::Run,
+            Some("check-config") => Command::CheckConfig,
+            Some("print-default-config") => Command::PrintDefaultConfig,
+            Some("scan-modbus") => {
+                if !(1..=2).contains(&scan.count) {
+                    return Err("--count must be 1 or 2".into());
+                }
+                Command::ScanModbus(scan)
+            }
+            Some("dump-state") => Command::DumpState(dump),
+            Some("simulate") => {
+                // Before the simulate options so those still apply on top
+                cli.overrides
+                    .insert(0, ("mode".into(), "Simulation".into()));
+                Command::Run
+            }
+            Some(other) => return Err(format!("unknown command '{}'\n\n{}", other, USAGE)),
+        };
+        Ok(cli)
+    }
+}
+
+struct Args(std::vec::IntoIter<String>);
+
+impl Args {
+    // Splits "--flag=value" so both spellings work
+    fn next_flag(&mut self) -> Option<(String, Option<String>)> {
+        let arg = self.0.next()?;
+        Some(match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.into(), Some(value.into())),
+            _ => (arg, None),
+        })
+    }
+
+    fn value(&mut self, flag: &str, inline: Option<String>) -> Result<String, String> {
+        inline
+            .or_else(|| self.0.next())
+            .ok_or_else(|| format!("{} needs a value", flag))
+    }
+
+    fn parsed<T: std::str::FromStr>(
+        &mut self,
+        flag: &str,
+        inline: Option<String>,
+    ) -> Result<T, String> {
+        let value = self.value(flag, inline)?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+    }
+}
+
+// "7" or "0-9"
+fn range<T: std::str::FromStr + PartialOrd + Copy>(
+    value: &str,
+) -> Result<RangeInclusive<T>, String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<T>()
+            .map_err(|_| format!("invalid range '{}'", value))
+    };
+    let (from, to) = match value.split_once('-') {
+        Some((from, to)) => (parse(from)?, parse(to)?),
+        None => {
+            let single = parse(value)?;
+            (single, single)
+        }
+    };
+    if from > to {
+        return Err(format!("invalid range '{}', start is after end", value));
+    }
+    Ok(from..=to)
+}
+
+// Loads and validates every layer; returns the process exit code
+pub fn check_config(source: &ConfigSource) -> i32 {
+    match source.load() {
+        Ok(config) => {
+            println!(
+                "{}: OK ({:?} mode, {} devices)",
+                source.path.display(),
+                config.mode,
+                config.device_ids().len()
+            );
+            for fragment in source.fragments() {
+                println!("  + {}", fragment.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+pub fn print_default_config() -> i32 {
+    print!(
+        "{}",
+        toml::to_string_pretty(&Config::default()).expect("default config serializes")
+    );
+    0
+}
+
+// Connection defaults come from the [modbus] section, so a configured gateway can be
+// checked against its PLC with no further options
+pub async fn scan_modbus(source: &ConfigSource, options: ScanOptions) -> i32 {
+    let modbus = match source.load() {
+        Ok(config) => config.modbus,
+        Err(_) if options.host.is_some() => Config::default().modbus,
+        Err(e) => {
+            eprintln!("{}\n(pass --host to scan without a config)", e);
+            return 1;
+        }
+    };
+    let request = ScanRequest {
+        host: options.host.unwrap_or(modbus.host),
+        port: options.port.unwrap_or(modbus.port),
+        slaves: options.slaves.unwrap_or(modbus.slave_id..=modbus.slave_id),
+        addresses: options.addresses,
+        count: options.count,
+        timeout_ms: options.timeout_ms,
+    };
+
+    println!(
+        "Scanning {}:{} slaves {:?} addresses {:?} ({} register(s) per value)",
+        request.host, request.port, request.slaves, request.addresses, request.count
+    );
+    let results = match scan(&request).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    println!(
+        "{:>5} {:>7}  {:<12} {:>12} {:>14}",
+        "slave", "address", "raw", "decoded", "scaled"
+    );
+    let mut found = 0;
+    for result in &results {
+        match &result.reading {
+            Reading::Value { registers, decoded } => {
+                found += 1;
+                let raw: Vec<String> = registers.iter().map(|r| format!("{:04x}", r)).collect();
+                println!(
+                    "{:>5} {:>7}  {:<12} {:>12} {:>14}",
+                    result.slave,
+                    result.address,
+                    raw.join(" "),
+                    decoded,
+                    *decoded as f64 * options.scale
+                );
+            }
+            Reading::Failed(reason) => {
+                println!("{:>5} {:>7}  {}", result.slave, result.address, reason);
+            }
+        }
+    }
+    println!("{} of {} reads returned values", found, results.len());
+    if found == 0 {
+        1
+    } else {
+        0
+    }
+}
+
+// Reads the REST API of a running gateway, by default the one described by the config
+pub async fn dump_state(source: &ConfigSource, options: DumpOptions) -> i32 {
+    let url = match options.url {
+        Some(url) => url,
+        None => match source.load() {
+            Ok(config) if config.api.tls.is_some() => {
+                eprintln!("dump-state speaks plain HTTP only, the API uses TLS");
+                return 1;
+            }
+            Ok(config) => {
+                // A wildcard bind address is reachable on loopback
+                let host = match config.api.host.as_str() {
+                    "0.0.0.0" => "127.0.0.1".to_string(),
+                    "::" => "[::1]".to_string(),
+                    host => host.to_string(),
+                };
+                format!("http://{}:{}", host, config.api.port)
+            }
+            Err(e) => {
+                eprintln!("{}\n(pass --url to skip the config)", e);
+                return 1;
+            }
+        },
+    };
+
+    let timeout = Duration::from_millis(options.timeout_ms);
+    match fetch_state(&url, options.token.as_deref(), timeout).await {
+        Ok(state) => {
+            println!("{}", serde_json::to_string_pretty(&state).unwrap());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// Devices, alarms and the readiness report of the gateway API at `url`; the timeout applies
+// per request so an unresponsive gateway cannot hang the command
+pub async fn fetch_state(
+    url: &str,
+    token: Option<&str>,
+    timeout: Duration,
+) -> Result<serde_json::Value, String> {
+    let client = Client::builder(TokioExecutor::new()).build_http();
+    let mut state = serde_json::Map::new();
+    for (key, path) in [
+        ("devices", "/devices"),
+        ("alarms", "/alarms"),
+        ("health", "/health/ready"),
+    ] {
+        let value = get_json(&client, url, path, token, timeout)
+            .await
+            .map_err(|e| format!("GET {}{}: {}", url, path, e))?;
+        state.insert(key.into(), value);
+    }
+    Ok(serde_json::Value::Object(state))
+}
+
+async fn get_json(
+    client: &Client<HttpConnector, Empty<Bytes>>,
+    base: &str,
+    path: &str,
+    token: Option<&str>,
+    limit: Duration,
+) -> Result<serde_json::Value, String> {
+    if !base.starts_with("http://") {
+        return Err("only http:// URLs are supported".into());
+    }
+    let mut request = Request::get(format!("{}{}", base.trim_end_matches('/'), path))
+        .header(ACCEPT, "application/json");
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = request.body(Empty::new()).map_err(|e| e.to_string())?;
+
+    let exchange = async {
+        let response = client.request(request).await.map_err(|e| {
+            // The legacy client's own message ("client error (Connect)") hides the cause
+            match std::error::Error::source(&e) {
+                Some(cause) => format!("{}: {}", e, cause),
+                None => e.to_string(),
+            }
+        })?;
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| e.to_string())?
+            .to_bytes();
+        Ok::<_, String>((status, body))
+    };
+    let (status, body) = timeout(limit, exchange)
+        .await
+        .map_err(|_| format!("no response within {} ms", limit.as_millis()))??;
+
+    // Readiness answers 503 with a report that is still worth printing
+    if !status.is_success() && status != StatusCode::SERVICE_UNAVAILABLE {
+        return Err(format!("HTTP {}", status));
+    }
+    serde_json::from_slice(&body).map_err(|e| format!("invalid JSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, String> {
+        Cli::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn parses_commands_and_global_options() {
+        assert_eq!(parse("").unwrap().command, Command::Run);
+
+        let cli = parse("--config=/etc/gw.toml --set mqtt.broker=localhost check-config").unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("/etc/gw.toml")));
+        assert_eq!(cli.overrides, [("mqtt.broker".into(), "localhost".into())]);
+        assert_eq!(cli.command, Command::CheckConfig);
+
+        let cli = parse("scan-modbus --host plc --slave 1-3 --address=100 --count 2").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::ScanModbus(ScanOptions {
+                host: Some("plc".into()),
+                slaves: Some(1..=3),
+                addresses: 100..=100,
+                count: 2,
+                ..ScanOptions::default()
+            })
+        );
+
+        assert_eq!(parse("scan-modbus --help").unwrap().command, Command::Help);
+
+        let cli = parse("dump-state --url http://gw:8080 --timeout-ms 200").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::DumpState(DumpOptions {
+                url: Some("http://gw:8080".into()),
+                token: None,
+                timeout_ms: 200,
+            })
+        );
+
+        let cli = parse("simulate --interval-ms 500 --add-value 3").unwrap();
+        assert_eq!(cli.command, Command::Run);
+        assert_eq!(
+            cli.overrides,
+            [
+                ("mode".into(), "Simulation".into()),
+                ("simulation.interval_ms".into(), "500".into()),
+                ("simulation.add_value".into(), "3".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        for (args, error) in [
+            ("frobnicate", "unknown command"),
+            ("run --host plc", "unknown argument '--host'"),
+            ("scan-modbus --slave 5-1", "start is after end"),
+            ("scan-modbus --count 3", "--count must be 1 or 2"),
+            ("scan-modbus --port x", "invalid value 'x' for --port"),
+            ("--set broker", "--set expects"),
+            ("--config", "--config needs a value"),
+        ] {
+            let err = parse(args).unwrap_err();
+            assert!(err.contains(error), "{}: {}", args, err);
+        }
+    }
+}
@@ -1,21 +1,49 @@
-use std::error::Error;
+mod layers;
+mod validation;
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub use layers::{ConfigSource, CONFIG_ENV, CONFIG_PATH, ENV_PREFIX};
+pub use validation::validate;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ApiConfig {
     pub device_name: String,
     pub host: String,
     pub port: u16,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    // Enables mutual TLS: clients must present a certificate signed by this CA
+    pub client_ca_path: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct MqttConfig {
     pub device_name: String,
     pub broker: String,
     pub port: u16,
     pub client_id: String,
+    // How long shutdown waits for queued messages and the offline status to go out
+    #[serde(default = "default_flush_timeout_ms")]
+    pub flush_timeout_ms: u64,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+fn default_flush_timeout_ms() -> u64 {
+    3000
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct RegisterMapping {
     pub address: u16,
     pub count: u16,
@@ -23,7 +51,8 @@ pub struct RegisterMapping {
     pub scale: f64,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ModbusConfig {
     pub device_name: String,
     pub host: String,
@@ -33,11 +62,336 @@ pub struct ModbusConfig {
     pub registers: Vec<RegisterMapping>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct SimulationConfig {
     pub device_name: String,
+    // Default sample interval for devices without their own
     pub interval_ms: u64,
+    // Offset added to every generated value
     pub add_value: i32,
+    // Without any, devices "1" and "2" emit random values between 0 and 100
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<SimulatedDeviceConfig>,
+}
+
+impl SimulationConfig {
+    pub fn devices(&self) -> Vec<SimulatedDeviceConfig> {
+        if !self.devices.is_empty() {
+            return self.devices.clone();
+        }
+        (1..=2)
+            .map(|id: u32| SimulatedDeviceConfig {
+                id: id.to_string(),
+                interval_ms: None,
+                noise: 0.0,
+                dropout: 0.0,
+                generator: SignalGenerator::Random {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SimulatedDeviceConfig {
+    pub id: String,
+    pub interval_ms: Option<u64>,
+    // Standard deviation of gaussian noise added to every sample
+    #[serde(default)]
+    pub noise: f64,
+    // Probability that a sample is skipped, like a lost reading
+    #[serde(default)]
+    pub dropout: f64,
+    pub generator: SignalGenerator,
+}
+
+impl SimulatedDeviceConfig {
+    pub fn interval_ms(&self, simulation: &SimulationConfig) -> u64 {
+        self.interval_ms.unwrap_or(simulation.interval_ms)
+    }
+}
+
+// Waveforms over the time since the source started; `random_walk` and `replay` keep state
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SignalGenerator {
+    Constant {
+        value: f64,
+    },
+    // Uniformly distributed between min and max
+    Random {
+        min: f64,
+        max: f64,
+    },
+    Sine {
+        amplitude: f64,
+        period_ms: u64,
+        #[serde(default)]
+        offset: f64,
+    },
+    // Rises from min to max over each period, then jumps back
+    Sawtooth {
+        min: f64,
+        max: f64,
+        period_ms: u64,
+    },
+    // Moves up to `step` per sample, kept between min and max
+    RandomWalk {
+        start: f64,
+        step: f64,
+        min: f64,
+        max: f64,
+    },
+    // Holds each value for `hold_ms`, then moves to the next and wraps around
+    Step {
+        values: Vec<f64>,
+        hold_ms: u64,
+    },
+    // One row per sample from a CSV file; non-numeric rows such as a header are skipped
+    Replay {
+        path: PathBuf,
+        // Zero-based; the last column by default
+        column: Option<usize>,
+        // Start over at the end instead of stopping the device
+        #[serde(default = "default_repeat")]
+        repeat: bool,
+    },
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+// Alarm checks for one device; every check is optional and compares the scaled value
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmRule {
+    pub device_id: String,
+    pub hi_hi: Option<f64>,
+    pub hi: Option<f64>,
+    pub lo: Option<f64>,
+    pub lo_lo: Option<f64>,
+    // Hysteresis: a limit alarm clears only once the value is this far back inside the limit
+    #[serde(default)]
+    pub deadband: f64,
+    // A condition has to hold this long before its alarm is raised
+    #[serde(default)]
+    pub delay_ms: u64,
+    // Maximum change per second
+    pub rate_of_change: Option<f64>,
+    // Raised when the value has not changed for this long
+    pub stuck_ms: Option<u64>,
+    // Raised when no value has arrived for this long
+    pub stale_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmsConfig {
+    // How often stale data and alarm delays are checked
+    pub check_interval_ms: u64,
+    #[serde(default)]
+    pub rules: Vec<AlarmRule>,
+}
+
+impl Default for AlarmsConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_ms: 1000,
+            rules: Vec::new(),
+        }
+    }
+}
+
+// Tumbling-window statistics per device, published on the `aggregate` topic
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AggregationConfig {
+    pub window_ms: u64,
+    // How long a window stays open after its end for samples still in flight
+    #[serde(default = "default_grace_ms")]
+    pub grace_ms: u64,
+    // Set to false to send only windows for aggregated devices, e.g. over cellular links
+    #[serde(default = "default_publish_raw")]
+    pub publish_raw: bool,
+    // Devices to aggregate; all devices when empty
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+fn default_grace_ms() -> u64 {
+    1000
+}
+
+fn default_publish_raw() -> bool {
+    true
+}
+
+impl AggregationConfig {
+    pub fn aggregates(&self, device_id: &str) -> bool {
+        self.devices.is_empty() || self.devices.iter().any(|d| d == device_id)
+    }
+}
+
+// Reloading on SIGHUP and `POST /config/reload` is always available; this controls
+// whether the config file is also watched for changes
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReloadConfig {
+    pub watch: bool,
+    pub poll_interval_ms: u64,
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: true,
+            poll_interval_ms: 2000,
+        }
+    }
+}
+
+// How a supervised service is restarted after it panicked or returned unexpectedly.
+// Delays double from `initial_backoff_ms` up to `max_backoff_ms`; a run that lasted
+// `reset_after_ms` counts as healthy and resets the delay and the restart budget.
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RestartPolicy {
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // Consecutive restarts before the service is given up as failed; 0 never restarts
+    pub max_restarts: u32,
+    pub reset_after_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            max_restarts: 10,
+            reset_after_ms: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SupervisorConfig {
+    // How long shutdown waits for services to stop before aborting them
+    pub shutdown_timeout_ms: u64,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    // Per-service policies by service name, e.g. `[supervisor.services."source.modbus"]`
+    #[serde(default)]
+    pub services: BTreeMap<String, RestartPolicy>,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            shutdown_timeout_ms: 5000,
+            restart: RestartPolicy::default(),
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    pub fn policy(&self, service: &str) -> &RestartPolicy {
+        self.services.get(service).unwrap_or(&self.restart)
+    }
+}
+
+// `sequential` awaits every listener in turn inside the event loop; `queued` gives each
+// listener its own bounded queue and task, so a slow listener only delays itself
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatchMode {
+    #[default]
+    Sequential,
+    Queued,
+}
+
+// What a full listener queue does with a new change
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // Wait for space, slowing down the event loop like sequential dispatch
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ListenerQueueConfig {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for ListenerQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct DispatchConfig {
+    pub mode: DispatchMode,
+    pub queue: ListenerQueueConfig,
+    // Per-listener queues by listener name (`console_logger`, `mqtt`); aggregation
+    // shares the `mqtt` queue
+    pub listeners: BTreeMap<String, ListenerQueueConfig>,
+}
+
+impl DispatchConfig {
+    pub fn queue(&self, listener: &str) -> &ListenerQueueConfig {
+        self.listeners.get(listener).unwrap_or(&self.queue)
+    }
+}
+
+// Device computed from other devices, e.g. "voltage * current * 0.001" or "avg(#temperatures)"
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VirtualDeviceConfig {
+    pub id: String,
+    pub expression: String,
+}
+
+#[derive(
+    Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
@@ -46,28 +400,87 @@ pub enum SourceMode {
     Modbus,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub gateway_id: String,
     pub gateway_name: String,
     pub mode: SourceMode,
     pub api: ApiConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
     pub mqtt: MqttConfig,
     pub modbus: ModbusConfig,
     pub simulation: SimulationConfig,
+    #[serde(default)]
+    pub alarms: AlarmsConfig,
+    pub aggregation: Option<AggregationConfig>,
+    #[serde(default)]
+    pub virtual_devices: Vec<VirtualDeviceConfig>,
+    // Named groups of device ids, used by aggregates in virtual device expressions
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub reload: ReloadConfig,
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    #[serde(default)]
+    pub dispatch: DispatchConfig,
 }
 
-impl Config {
-    pub fn load() -> Result<Self, Box<dyn Error>> {
-        let contents = std::fs::read_to_string("/config.toml")?;
-        Ok(toml::from_str(&contents)?)
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // `origin` is the file or environment variable the problem came from
+    Parse {
+        origin: String,
+        message: String,
+    },
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { origin, message } => write!(f, "{}: {}", origin, message),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
     }
+}
+
+impl std::error::Error for ConfigError {}
 
-    pub fn load_or_default() -> Self {
-        Config::load().unwrap_or_else(|e| {
-            eprintln!("⚠ config.toml not found, using defaults: {}", e);
-            Config::default()
-        })
+impl Config {
+    // Devices the gateway creates at startup: those of the active source plus virtual ones
+    pub fn device_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = match self.mode {
+            SourceMode::Modbus => self
+                .modbus
+                .registers
+                .iter()
+                .map(|r| r.device_id.clone())
+                .collect(),
+            SourceMode::Simulation => self
+                .simulation
+                .devices()
+                .into_iter()
+                .map(|d| d.id)
+                .collect(),
+        };
+        ids.extend(self.virtual_devices.iter().map(|d| d.id.clone()));
+        ids
     }
 }
 
@@ -81,12 +494,15 @@ impl Default for Config {
                 device_name: "API".into(),
                 host: "127.0.0.1".into(),
                 port: 8080,
+                tls: None,
             },
+            auth: AuthConfig::default(),
             mqtt: MqttConfig {
                 device_name: "MQTT".into(),
                 broker: "localhost".into(),
                 port: 1883,
                 client_id: "rust-gateway".into(),
+                flush_timeout_ms: default_flush_timeout_ms(),
             },
             modbus: ModbusConfig {
                 device_name: "Modbus".into(),
@@ -113,7 +529,15 @@ impl Default for Config {
                 device_name: "Simulation".into(),
                 interval_ms: 2000,
                 add_value: 1,
+                devices: Vec::new(),
             },
+            alarms: AlarmsConfig::default(),
+            aggregation: None,
+            virtual_devices: Vec::new(),
+            tags: BTreeMap::new(),
+            reload: ReloadConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            dispatch: DispatchConfig::default(),
         }
     }
 }
@@ -0,0 +1,344 @@
+use super::{validate, Config, ConfigError};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use toml::Value;
+use tracing::warn;
+
+pub const CONFIG_PATH: &str = "/config.toml";
+// Overrides the config path when no --config flag is given
+pub const CONFIG_ENV: &str = "GATEWAY_CONFIG";
+// GATEWAY__MQTT__BROKER=broker.local sets mqtt.broker
+pub const ENV_PREFIX: &str = "GATEWAY__";
+
+// Where the configuration comes from. Layers, later ones win:
+//   1. the config file (defaults when the default path does not exist)
+//   2. `config.d/*.toml` next to it, in file name order
+//   3. GATEWAY__SECTION__KEY environment variables
+//   4. command line overrides (`--set mqtt.broker=localhost`, `simulate` options)
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    // Set for explicitly chosen paths; a missing file is then an error
+    pub required: bool,
+    // Dotted keys with raw values, kept so reloads apply them again
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ConfigSource {
+    // A file that has to exist, without command line overrides
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            required: true,
+            overrides: Vec::new(),
+        }
+    }
+
+    // --config flag, then GATEWAY_CONFIG, then /config.toml
+    pub fn resolve(cli_path: Option<PathBuf>, overrides: Vec<(String, String)>) -> Self {
+        let explicit = cli_path.or_else(|| std::env::var_os(CONFIG_ENV).map(PathBuf::from));
+        match explicit {
+            Some(path) => Self {
+                overrides,
+                ..Self::file(path)
+            },
+            None => Self {
+                path: PathBuf::from(CONFIG_PATH),
+                required: false,
+                overrides,
+            },
+        }
+    }
+
+    pub fn fragments_dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("config.d")
+    }
+
+    pub fn fragments(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(self.fragments_dir()) else {
+            return Vec::new();
+        };
+        let mut fragments: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        fragments.sort();
+        fragments
+    }
+
+    // Newest modification time of the file, the fragment directory and its fragments
+    pub fn modified(&self) -> Option<SystemTime> {
+        std::iter::once(self.path.clone())
+            .chain(std::iter::once(self.fragments_dir()))
+            .chain(self.fragments())
+            .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+            .max()
+    }
+
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_with_env(std::env::vars())
+    }
+
+    // Merges all layers, then validates the result
+    pub fn load_with_env(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Config, ConfigError> {
+        let mut root = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => parse(&self.path, &contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !self.required => {
+                warn!("{} not found, starting from defaults", self.path.display());
+                Value::try_from(Config::default()).expect("default config serializes")
+            }
+            Err(source) => {
+                return Err(ConfigError::Read {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+        };
+
+        for fragment in self.fragments() {
+            let contents =
+                std::fs::read_to_string(&fragment).map_err(|source| ConfigError::Read {
+                    path: fragment.clone(),
+                    source,
+                })?;
+            merge(&mut root, parse(&fragment, &contents)?);
+        }
+
+        let mut overrides: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        overrides.sort();
+        for (key, raw) in overrides {
+            let path: Vec<String> = key[ENV_PREFIX.len()..]
+                .split("__")
+                .map(|s| s.to_lowercase())
+                .collect();
+            set(&mut root, &path, &raw).map_err(|message| ConfigError::Parse {
+                origin: key.clone(),
+                message,
+            })?;
+        }
+        for (key, raw) in &self.overrides {
+            let path: Vec<String> = key.split('.').map(str::to_string).collect();
+            set(&mut root, &path, raw).map_err(|message| ConfigError::Parse {
+                origin: format!("--set {}", key),
+                message,
+            })?;
+        }
+
+        // Type errors name the key (e.g. "in `api.port`"), not the layer it came from
+        let config: Config = root
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse {
+                origin: self.path.display().to_string(),
+                message: e.to_string().replace('\n', " "),
+            })?;
+
+        let problems = validate(&config);
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+        Ok(config)
+    }
+}
+
+fn parse(path: &Path, contents: &str) -> Result<Value, ConfigError> {
+    contents
+        .parse::<toml::Table>()
+        .map(Value::Table)
+        .map_err(|e| ConfigError::Parse {
+            origin: path.display().to_string(),
+            message: e.to_string().trim_end().to_string(),
+        })
+}
+
+// Tables merge key by key and arrays are extended, so a fragment can add registers,
+// alarm rules or virtual devices; any other value replaces the earlier one
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) => base.extend(overlay),
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// Sets one key from an environment variable. Numeric segments index into arrays
+// (GATEWAY__MODBUS__REGISTERS__0__SCALE). Values replacing a string stay strings,
+// everything else is read as a TOML value and falls back to a string.
+fn set(root: &mut Value, path: &[String], raw: &str) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("empty key")?;
+    let mut node = root;
+    for segment in parents {
+        node = match node {
+            Value::Table(table) => table
+                .entry(segment.clone())
+                .or_insert_with(|| Value::Table(toml::Table::new())),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .ok_or_else(|| format!("no entry {} in array", segment))?,
+            _ => return Err(format!("{} is not a table", segment)),
+        };
+    }
+
+    let value = |existing: Option<&Value>| match existing {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        _ => format!("v = {}", raw)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+    };
+    match node {
+        Value::Table(table) => {
+            let value = value(table.get(last));
+            table.insert(last.clone(), value);
+        }
+        Value::Array(items) => {
+            let slot = last
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .ok_or_else(|| format!("no entry {} in array", last))?;
+            *slot = value(Some(slot));
+        }
+        _ => return Err(format!("cannot set {} on a plain value", last)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SourceMode;
+
+    const BASE: &str = r#"
+        mode = "Modbus"
+        gateway_id = "gw-1"
+        gateway_name = "Gateway"
+
+        [api]
+        device_name = "API"
+        host = "0.0.0.0"
+        port = 8080
+
+        [mqtt]
+        device_name = "MQTT"
+        broker = "localhost"
+        port = 1883
+        client_id = "gw"
+
+        [modbus]
+        device_name = "Modbus"
+        host = "plc"
+        port = 502
+        slave_id = 1
+        poll_interval_ms = 1000
+
+        [[modbus.registers]]
+        address = 0
+        count = 1
+        device_id = "100"
+        scale = 1.0
+
+        [simulation]
+        device_name = "Simulation"
+        interval_ms = 1000
+        add_value = 1
+    "#;
+
+    // A scratch directory with config.toml and the given fragments
+    fn source(name: &str, base: &str, fragments: &[(&str, &str)]) -> ConfigSource {
+        let dir =
+            std::env::temp_dir().join(format!("gateway-config-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("config.d")).unwrap();
+        std::fs::write(dir.join("config.toml"), base).unwrap();
+        for (file, contents) in fragments {
+            std::fs::write(dir.join("config.d").join(file), contents).unwrap();
+        }
+        ConfigSource::file(dir.join("config.toml"))
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fragments_and_environment_are_layered_over_the_file() {
+        let mut source = source(
+            "layers",
+            BASE,
+            &[
+                (
+                    "20-more.toml",
+                    "[[modbus.registers]]\naddress = 4\ncount = 2\ndevice_id = \"200\"\nscale = 0.1\n",
+                ),
+                ("10-mqtt.toml", "[mqtt]\nbroker = \"fragment\"\n"),
+                ("ignored.txt", "not toml"),
+            ],
+        );
+
+        source.overrides = vec![("modbus.poll_interval_ms".into(), "250".into())];
+        let config = source
+            .load_with_env(env(&[
+                ("GATEWAY__MQTT__BROKER", "env-broker"),
+                ("GATEWAY__MODBUS__REGISTERS__1__SCALE", "0.5"),
+                ("GATEWAY__GATEWAY_ID", "1234"),
+                ("OTHER__API__PORT", "1"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.mode, SourceMode::Modbus);
+        assert_eq!(config.mqtt.broker, "env-broker");
+        // Stays a string because the file has a string there
+        assert_eq!(config.gateway_id, "1234");
+        assert_eq!(config.device_ids(), ["100", "200"]);
+        assert_eq!(config.modbus.registers[1].scale, 0.5);
+        assert_eq!(config.api.port, 8080);
+        assert_eq!(config.modbus.poll_interval_ms, 250);
+    }
+
+    #[test]
+    fn errors_name_the_file_or_variable() {
+        let broken = source("syntax", BASE, &[("10-bad.toml", "[mqtt\n")]);
+        let err = broken.load_with_env(Vec::new()).unwrap_err().to_string();
+        assert!(err.contains("10-bad.toml"), "{}", err);
+
+        let typo = source("typo", &BASE.replace("broker =", "brokr ="), &[]);
+        let err = typo.load_with_env(Vec::new()).unwrap_err().to_string();
+        assert!(err.contains("unknown field `brokr`"), "{}", err);
+
+        let plain = source("env", BASE, &[]);
+        let err = plain
+            .load_with_env(env(&[("GATEWAY__API__PORT", "70000")]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("api.port"), "{}", err);
+
+        let missing = ConfigSource::file(plain.path.with_file_name("missing.toml"));
+        assert!(matches!(
+            missing.load_with_env(Vec::new()),
+            Err(ConfigError::Read { .. })
+        ));
+    }
+}
@@ -0,0 +1,440 @@
+use super::{Config, SignalGenerator, SourceMode};
+use crate::adapters::simulation::generators;
+use crate::core::virtual_devices::VirtualDevices;
+use std::collections::HashMap;
+
+// Checks what deserialization cannot: value ranges and consistency between sections.
+// Every problem is reported with the key it concerns, not just the first one.
+pub fn validate(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut require = |ok: bool, problem: String| {
+        if !ok {
+            problems.push(problem);
+        }
+    };
+
+    for (key, value) in [
+        ("gateway_id", &config.gateway_id),
+        ("gateway_name", &config.gateway_name),
+        ("api.host", &config.api.host),
+        ("mqtt.broker", &config.mqtt.broker),
+        ("mqtt.client_id", &config.mqtt.client_id),
+        ("modbus.host", &config.modbus.host),
+    ] {
+        require(
+            !value.trim().is_empty(),
+            format!("{} must not be empty", key),
+        );
+    }
+    for (key, port) in [
+        ("api.port", config.api.port),
+        ("mqtt.port", config.mqtt.port),
+        ("modbus.port", config.modbus.port),
+    ] {
+        require(port != 0, format!("{} must be between 1 and 65535", key));
+    }
+    for (key, interval) in [
+        ("modbus.poll_interval_ms", config.modbus.poll_interval_ms),
+        ("simulation.interval_ms", config.simulation.interval_ms),
+        ("alarms.check_interval_ms", config.alarms.check_interval_ms),
+        ("reload.poll_interval_ms", config.reload.poll_interval_ms),
+    ] {
+        require(interval > 0, format!("{} must be greater than 0", key));
+    }
+    if let Some(aggregation) = &config.aggregation {
+        require(
+            aggregation.window_ms > 0,
+            "aggregation.window_ms must be greater than 0".into(),
+        );
+    }
+
+    require(
+        config.supervisor.shutdown_timeout_ms > 0,
+        "supervisor.shutdown_timeout_ms must be greater than 0".into(),
+    );
+    let policies = std::iter::once(("supervisor.restart".to_string(), &config.supervisor.restart))
+        .chain(
+            config
+                .supervisor
+                .services
+                .iter()
+                .map(|(name, policy)| (format!("supervisor.services.\"{}\"", name), policy)),
+        );
+    for (key, policy) in policies {
+        require(
+            policy.max_backoff_ms >= policy.initial_backoff_ms,
+            format!("{}: max_backoff_ms is below initial_backoff_ms", key),
+        );
+    }
+
+    let queues = std::iter::once(("dispatch.queue".to_string(), &config.dispatch.queue)).chain(
+        config
+            .dispatch
+            .listeners
+            .iter()
+            .map(|(name, queue)| (format!("dispatch.listeners.{}", name), queue)),
+    );
+    for (key, queue) in queues {
+        require(
+            queue.capacity > 0,
+            format!("{}.capacity must be greater than 0", key),
+        );
+    }
+
+    problems.extend(registers(config));
+    problems.extend(simulated_devices(config));
+    problems.extend(devices(config));
+    problems.extend(alarm_rules(config));
+    problems.extend(auth_keys(config));
+    problems
+}
+
+fn registers(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let registers = &config.modbus.registers;
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    for (i, r) in registers.iter().enumerate() {
+        let key = format!("modbus.registers[{}]", i);
+        if r.device_id.trim().is_empty() {
+            problems.push(format!("{}.device_id must not be empty", key));
+        } else if let Some(first) = seen.insert(&r.device_id, i) {
+            problems.push(format!(
+                "{}: device_id \"{}\" is already used by modbus.registers[{}]",
+                key, r.device_id, first
+            ));
+        }
+        // The poller decodes 16 bit values and 32 bit values from two registers
+        if !(1..=2).contains(&r.count) {
+            problems.push(format!("{}.count must be 1 or 2, got {}", key, r.count));
+        }
+        if r.address as u32 + r.count as u32 > 65536 {
+            problems.push(format!(
+                "{}: address {} with count {} exceeds the register range",
+                key, r.address, r.count
+            ));
+        }
+        if !r.scale.is_finite() {
+            problems.push(format!("{}.scale must be a finite number", key));
+        }
+
+        let end = r.address as u32 + r.count.max(1) as u32;
+        for (j, other) in registers.iter().enumerate().take(i) {
+            let other_end = other.address as u32 + other.count.max(1) as u32;
+            if (r.address as u32) < other_end && (other.address as u32) < end {
+                problems.push(format!(
+                    "{} (device \"{}\", registers {}..{}) overlaps modbus.registers[{}] (device \"{}\", registers {}..{})",
+                    key, r.device_id, r.address, end, j, other.device_id, other.address, other_end
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn simulated_devices(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    for (i, device) in config.simulation.devices.iter().enumerate() {
+        let key = format!("simulation.devices[{}]", i);
+        let mut require = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(format!("{}{}", key, problem));
+            }
+        };
+        require(!device.id.trim().is_empty(), ".id must not be empty");
+        require(
+            device.interval_ms != Some(0),
+            ".interval_ms must be greater than 0",
+        );
+        require(device.noise >= 0.0, ".noise must not be negative");
+        require(
+            (0.0..=1.0).contains(&device.dropout),
+            ".dropout must be between 0 and 1",
+        );
+        match &device.generator {
+            SignalGenerator::Sine { period_ms, .. }
+            | SignalGenerator::Sawtooth { period_ms, .. } => require(
+                *period_ms > 0,
+                ".generator.period_ms must be greater than 0",
+            ),
+            SignalGenerator::Step { values, hold_ms } => {
+                require(!values.is_empty(), ".generator.values must not be empty");
+                require(*hold_ms > 0, ".generator.hold_ms must be greater than 0");
+            }
+            _ => {}
+        }
+        match &device.generator {
+            SignalGenerator::Random { min, max }
+            | SignalGenerator::Sawtooth { min, max, .. }
+            | SignalGenerator::RandomWalk { min, max, .. } => {
+                require(min < max, ".generator: min must be below max")
+            }
+            _ => {}
+        }
+        // Only read when it is about to be replayed
+        if let SignalGenerator::Replay { path, column, .. } = &device.generator {
+            if config.mode == SourceMode::Simulation {
+                if let Err(e) = generators::load_csv(path, *column) {
+                    problems.push(format!("{}.generator: {}", key, e));
+                }
+            }
+        }
+
+        if device.id.trim().is_empty() {
+            continue;
+        }
+        if let Some(first) = seen.insert(&device.id, i) {
+            problems.push(format!(
+                "{}: id \"{}\" is already used by simulation.devices[{}]",
+                key, device.id, first
+            ));
+        }
+    }
+
+    problems
+}
+
+fn devices(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let source_ids: Vec<String> = Config {
+        virtual_devices: Vec::new(),
+        ..config.clone()
+    }
+    .device_ids();
+    let source = match config.mode {
+        SourceMode::Modbus => "Modbus register",
+        SourceMode::Simulation => "simulated device",
+    };
+
+    for (i, device) in config.virtual_devices.iter().enumerate() {
+        if source_ids.contains(&device.id) {
+            problems.push(format!(
+                "virtual_devices[{}]: id \"{}\" is already a {}",
+                i, device.id, source
+            ));
+        }
+    }
+    if let Err(e) = VirtualDevices::new(&config.virtual_devices, &config.tags) {
+        problems.push(format!("virtual_devices: {}", e));
+    }
+
+    problems
+}
+
+fn alarm_rules(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    for (i, rule) in config.alarms.rules.iter().enumerate() {
+        let key = format!("alarms.rules[{}]", i);
+        if let Some(first) = seen.insert(&rule.device_id, i) {
+            problems.push(format!(
+                "{}: device_id \"{}\" already has a rule in alarms.rules[{}]",
+                key, rule.device_id, first
+            ));
+        }
+        if rule.deadband < 0.0 {
+            problems.push(format!("{}.deadband must not be negative", key));
+        }
+        // Pairs that must be ordered from high to low
+        let limits = [
+            ("hi_hi", rule.hi_hi, "hi", rule.hi),
+            ("hi", rule.hi, "lo", rule.lo),
+            ("lo", rule.lo, "lo_lo", rule.lo_lo),
+        ];
+        for (upper_name, upper, lower_name, lower) in limits {
+            if let (Some(upper), Some(lower)) = (upper, lower) {
+                if upper < lower {
+                    problems.push(format!(
+                        "{}: {} ({}) is below {} ({})",
+                        key, upper_name, upper, lower_name, lower
+                    ));
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+fn auth_keys(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    for (i, key) in config.auth.keys.iter().enumerate() {
+        let token = key.token.trim();
+        if token.is_empty() {
+            // Unused keys may stay blank until auth is switched on
+            if config.auth.enabled {
+                problems.push(format!("auth.keys[{}].token must not be empty", i));
+            }
+        } else if config.auth.enabled && is_placeholder(token) {
+            problems.push(format!(
+                "auth.keys[{}].token is a placeholder, set a random secret",
+                i
+            ));
+        } else if let Some(first) = seen.insert(&key.token, i) {
+            problems.push(format!(
+                "auth.keys[{}]: token is already used by auth.keys[{}]",
+                i, first
+            ));
+        }
+    }
+
+    problems
+}
+
+// Example values from docs and templates, which are public knowledge. Only exact
+// matches count, random tokens may well start with "secret" or "token".
+const PLACEHOLDER_TOKENS: &[&str] = &[
+    "change-me",
+    "changeme",
+    "change-me-viewer",
+    "change-me-operator",
+    "change-me-admin",
+    "<random secret>",
+    "secret",
+    "token",
+    "password",
+];
+
+fn is_placeholder(token: &str) -> bool {
+    PLACEHOLDER_TOKENS
+        .iter()
+        .any(|placeholder| token.eq_ignore_ascii_case(placeholder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        AlarmRule, ApiKeyConfig, RegisterMapping, Role, SimulatedDeviceConfig, VirtualDeviceConfig,
+    };
+
+    fn register(device_id: &str, address: u16, count: u16) -> RegisterMapping {
+        RegisterMapping {
+            address,
+            count,
+            device_id: device_id.into(),
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(validate(&Config::default()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_key() {
+        let mut config = Config {
+            mode: SourceMode::Modbus,
+            ..Config::default()
+        };
+        config.api.port = 0;
+        config.modbus.registers = vec![
+            register("100", 0, 2),
+            register("200", 1, 1),
+            register("100", 10, 3),
+        ];
+        config.virtual_devices.push(VirtualDeviceConfig {
+            id: "200".into(),
+            expression: "{100} * 2".into(),
+        });
+        config.alarms.rules.push(AlarmRule {
+            device_id: "100".into(),
+            hi: Some(10.0),
+            hi_hi: Some(5.0),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            validate(&config),
+            [
+                "api.port must be between 1 and 65535",
+                "modbus.registers[1] (device \"200\", registers 1..2) overlaps modbus.registers[0] (device \"100\", registers 0..2)",
+                "modbus.registers[2]: device_id \"100\" is already used by modbus.registers[0]",
+                "modbus.registers[2].count must be 1 or 2, got 3",
+                "virtual_devices[0]: id \"200\" is already a Modbus register",
+                "alarms.rules[0]: hi_hi (5) is below hi (10)",
+            ]
+        );
+    }
+
+    #[test]
+    fn enabled_auth_rejects_blank_and_placeholder_tokens() {
+        let key = |token: &str| ApiKeyConfig {
+            name: "dashboard".into(),
+            token: token.into(),
+            role: Role::Viewer,
+        };
+        let mut config = Config::default();
+        config.auth.keys = vec![
+            key(""),
+            key("change-me-admin"),
+            key("k3q9-long-random"),
+            key("tokenA9f3c2e7"),
+            key("secret-7c1e90ab"),
+            key("Password"),
+        ];
+        assert_eq!(validate(&config), Vec::<String>::new());
+
+        config.auth.enabled = true;
+        assert_eq!(
+            validate(&config),
+            [
+                "auth.keys[0].token must not be empty",
+                "auth.keys[1].token is a placeholder, set a random secret",
+                "auth.keys[5].token is a placeholder, set a random secret",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_simulated_devices() {
+        let device = |id: &str, generator| SimulatedDeviceConfig {
+            id: id.into(),
+            interval_ms: None,
+            noise: 0.0,
+            dropout: 0.0,
+            generator,
+        };
+        let mut config = Config::default();
+        config.simulation.devices = vec![
+            device("flow", SignalGenerator::Constant { value: 1.0 }),
+            SimulatedDeviceConfig {
+                dropout: 1.5,
+                ..device(
+                    "flow",
+                    SignalGenerator::Sawtooth {
+                        min: 10.0,
+                        max: 0.0,
+                        period_ms: 0,
+                    },
+                )
+            },
+            device(
+                "level",
+                SignalGenerator::Replay {
+                    path: "does/not/exist.csv".into(),
+                    column: None,
+                    repeat: true,
+                },
+            ),
+        ];
+
+        assert_eq!(
+            validate(&config),
+            [
+                "simulation.devices[1].dropout must be between 0 and 1",
+                "simulation.devices[1].generator.period_ms must be greater than 0",
+                "simulation.devices[1].generator: min must be below max",
+                "simulation.devices[1]: id \"flow\" is already used by simulation.devices[0]",
+                "simulation.devices[2].generator: cannot read does/not/exist.csv: No such file or directory (os error 2)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use crate::config::AggregationConfig;
+use crate::core::{
+    lifecycle::Lifecycle,
+    state::{ListenerError, StateChange, StateListener},
+};
+use async_trait::async_trait;
+use shared_models::AggregateWindow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio::time::Duration;
+use tracing::{debug, info};
+
+// Receives every closed window; implemented by the MQTT publisher
+#[async_trait]
+pub trait AggregateSink: Send + Sync + 'static {
+    async fn publish_aggregate(
+        &self,
+        device_id: &str,
+        window: AggregateWindow,
+    ) -> Result<(), ListenerError>;
+}
+
+#[derive(Debug, Clone)]
+struct OpenWindow {
+    start: i64,
+    end: i64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+    first_timestamp: i64,
+    last_timestamp: i64,
+}
+
+impl OpenWindow {
+    fn new(start: i64, end: i64, value: f64, timestamp: i64) -> Self {
+        Self {
+            start,
+            end,
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+            last: value,
+            first_timestamp: timestamp,
+            last_timestamp: timestamp,
+        }
+    }
+
+    // Samples may arrive out of order within the window; `last` follows the newest one
+    fn add(&mut self, value: f64, timestamp: i64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.first_timestamp = self.first_timestamp.min(timestamp);
+        if timestamp >= self.last_timestamp {
+            self.last = value;
+            self.last_timestamp = timestamp;
+        }
+    }
+
+    fn close(&self) -> AggregateWindow {
+        AggregateWindow {
+            start: self.start,
+            end: self.end,
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            avg: self.sum / self.count as f64,
+            last: self.last,
+            first_timestamp: self.first_timestamp,
+            last_timestamp: self.last_timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct DeviceWindows {
+    open: Option<OpenWindow>,
+    // End of the last published window; older samples are dropped
+    closed_until: i64,
+}
+
+// Tumbling windows aligned to multiples of the window length since the epoch. A window
+// closes when a sample of a later window arrives or when its end (plus grace) has passed.
+#[derive(Debug, Clone)]
+pub struct WindowAggregator {
+    config: AggregationConfig,
+    devices: HashMap<String, DeviceWindows>,
+}
+
+impl WindowAggregator {
+    pub fn new(config: AggregationConfig) -> Self {
+        Self {
+            config,
+            devices: HashMap::new(),
+        }
+    }
+
+    // Adds a sample and returns the previous window if the sample closed it
+    pub fn observe(
+        &mut self,
+        device_id: &str,
+        value: f64,
+        timestamp: i64,
+    ) -> Option<AggregateWindow> {
+        if !self.config.aggregates(device_id) {
+            return None;
+        }
+        let window_ms = self.config.window_ms.max(1) as i64;
+        let start = timestamp.div_euclid(window_ms) * window_ms;
+        let device = self.devices.entry(device_id.to_string()).or_default();
+
+        if timestamp < device.closed_until {
+            debug!(
+                "Dropping late sample of {} at {}, window already published",
+                device_id, timestamp
+            );
+            return None;
+        }
+
+        match &mut device.open {
+            Some(open) if open.start == start => {
+                open.add(value, timestamp);
+                None
+            }
+            Some(open) if start < open.start => {
+                debug!(
+                    "Dropping late sample of {} at {}, a newer window is open",
+                    device_id, timestamp
+                );
+                None
+            }
+            _ => {
+                let closed = device.open.take().map(|open| open.close());
+                if let Some(window) = &closed {
+                    device.closed_until = window.end;
+                }
+                device.open = Some(OpenWindow::new(start, start + window_ms, value, timestamp));
+                closed
+            }
+        }
+    }
+
+    // Closes the open window of a removed device so its last samples are not lost
+    pub fn remove(&mut self, device_id: &str) -> Option<AggregateWindow> {
+        self.devices
+            .remove(device_id)
+            .and_then(|d| d.open)
+            .map(|open| open.close())
+    }
+
+    // Windows whose end plus grace lies before `now`
+    pub fn expire(&mut self, now: i64) -> Vec<(String, AggregateWindow)> {
+        let grace = self.config.grace_ms as i64;
+        let mut closed = Vec::new();
+        for (device_id, device) in &mut self.devices {
+            if device.open.as_ref().is_some_and(|w| w.end + grace <= now) {
+                let window = device.open.take().unwrap().close();
+                device.closed_until = window.end;
+                closed.push((device_id.clone(), window));
+            }
+        }
+        closed.sort_by(|a, b| a.1.start.cmp(&b.1.start).then_with(|| a.0.cmp(&b.0)));
+        closed
+    }
+
+    // Every open window, partial ones included; used on shutdown
+    pub fn drain(&mut self) -> Vec<(String, AggregateWindow)> {
+        let mut closed: Vec<(String, AggregateWindow)> = self
+            .devices
+            .drain()
+            .filter_map(|(device_id, d)| d.open.map(|open| (device_id, open.close())))
+            .collect();
+        closed.sort_by(|a, b| a.0.cmp(&b.0));
+        closed
+    }
+}
+
+// Feeds device values into the aggregator and publishes closed windows. As a Lifecycle
+// service it closes windows of quiet devices and flushes partial windows on shutdown.
+#[derive(Clone)]
+pub struct AggregationListener {
+    aggregator: Arc<Mutex<WindowAggregator>>,
+    sink: Arc<dyn AggregateSink>,
+}
+
+impl AggregationListener {
+    pub fn new(config: AggregationConfig, sink: Arc<dyn AggregateSink>) -> Self {
+        Self {
+            aggregator: Arc::new(Mutex::new(WindowAggregator::new(config))),
+            sink,
+        }
+    }
+
+    async fn publish(&self, windows: Vec<(String, AggregateWindow)>) -> Result<(), ListenerError> {
+        for (device_id, window) in windows {
+            self.sink.publish_aggregate(&device_id, window).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StateListener for AggregationListener {
+    async fn on_event(&self, event: StateChange) -> Result<(), ListenerError> {
+        let closed = {
+            let mut aggregator = self.aggregator.lock().await;
+            match &event {
+                StateChange::DeviceUpdated {
+                    id,
+                    value,
+                    timestamp,
+                } => aggregator.observe(id, *value, *timestamp),
+                StateChange::DeviceRemoved { id, .. } => aggregator.remove(id),
+                _ => None,
+            }
+        };
+
+        match (closed, event) {
+            (Some(window), StateChange::DeviceUpdated { id, .. })
+            | (Some(window), StateChange::DeviceRemoved { id, .. }) => {
+                self.sink.publish_aggregate(&id, window).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "aggregation"
+    }
+}
+
+#[async_trait]
+impl Lifecycle for AggregationListener {
+    async fn run(self, mut shutdown: broadcast::Receiver<()>) {
+        let window_ms = self.aggregator.lock().await.config.window_ms;
+        let mut interval = tokio::time::interval(Duration::from_millis(window_ms.clamp(1, 1000)));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.recv() => break,
+                _ = interval.tick() => {
+                    let now = chrono::Utc::now().timestamp_millis();
+                    let closed = self.aggregator.lock().await.expire(now);
+                    if let Err(e) = self.publish(closed).await {
+                        tracing::error!("Publishing aggregate windows failed: {}", e);
+                    }
+                }
+            }
+        }
+
+        let partial = self.aggregator.lock().await.drain();
+        info!("Flushing {} partial aggregate windows", partial.len());
+        if let Err(e) = self.publish(partial).await {
+            tracing::error!("Flushing aggregate windows failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregator(devices: Vec<String>) -> WindowAggregator {
+        WindowAggregator::new(AggregationConfig {
+            window_ms: 1000,
+            grace_ms: 100,
+            publish_raw: true,
+            devices,
+        })
+    }
+
+    #[test]
+    fn window_closes_when_a_later_sample_arrives() {
+        let mut a = aggregator(vec![]);
+
+        assert_eq!(a.observe("1", 2.0, 1_100), None);
+        assert_eq!(a.observe("1", 6.0, 1_900), None);
+        // Out of order within the window: counted, but not the last value
+        assert_eq!(a.observe("1", 1.0, 1_500), None);
+
+        let window = a.observe("1", 10.0, 2_050).unwrap();
+        assert_eq!(
+            window,
+            AggregateWindow {
+                start: 1_000,
+                end: 2_000,
+                count: 3,
+                min: 1.0,
+                max: 6.0,
+                avg: 3.0,
+                last: 6.0,
+                first_timestamp: 1_100,
+                last_timestamp: 1_900,
+            }
+        );
+
+        // Late sample for the published window is dropped
+        assert_eq!(a.observe("1", 99.0, 1_950), None);
+        assert_eq!(a.drain()[0].1.count, 1);
+    }
+
+    #[test]
+    fn quiet_devices_expire_after_grace() {
+        let mut a = aggregator(vec!["1".into()]);
+        a.observe("1", 5.0, 1_200);
+        // Not configured for aggregation
+        a.observe("2", 5.0, 1_200);
+
+        assert!(a.expire(2_050).is_empty());
+        let expired = a.expire(2_100);
+        assert_eq!(expired.len(), 1);
+        assert_eq!((expired[0].0.as_str(), expired[0].1.count), ("1", 1));
+
+        // A sample that was still in flight is dropped rather than reopening the window
+        assert_eq!(a.observe("1", 7.0, 1_999), None);
+        assert!(a.drain().is_empty());
+    }
+
+    #[test]
+    fn removing_a_device_closes_its_window() {
+        let mut a = aggregator(vec![]);
+        a.observe("1", 5.0, 1_200);
+
+        assert_eq!(a.remove("1").unwrap().last, 5.0);
+        assert_eq!(a.remove("1"), None);
+    }
+
+    struct RecordingSink(std::sync::Mutex<Vec<(String, AggregateWindow)>>);
+
+    #[async_trait]
+    impl AggregateSink for RecordingSink {
+        async fn publish_aggregate(
+            &self,
+            device_id: &str,
+            window: AggregateWindow,
+        ) -> Result<(), ListenerError> {
+            self.0.lock().unwrap().push((device_id.to_string(), window));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn listener_publishes_closed_windows() {
+        let sink = Arc::new(RecordingSink(Default::default()));
+        let listener = AggregationListener::new(
+            AggregationConfig {
+                window_ms: 1000,
+                grace_ms: 0,
+                publish_raw: false,
+                devices: vec![],
+            },
+            sink.clone(),
+        );
+        let update = |value, timestamp| StateChange::DeviceUpdated {
+            id: "1".into(),
+            value,
+            timestamp,
+        };
+
+        listener.on_event(update(1.0, 1_100)).await.unwrap();
+        listener.on_event(update(3.0, 2_100)).await.unwrap();
+        listener
+            .on_event(StateChange::DeviceRemoved {
+                id: "1".into(),
+                timestamp: 2_200,
+            })
+            .await
+            .unwrap();
+
+        let published = sink.0.lock().unwrap();
+        let starts: Vec<(&str, i64)> = published
+            .iter()
+            .map(|(id, w)| (id.as_str(), w.start))
+            .collect();
+        assert_eq!(starts, [("1", 1_000), ("1", 2_000)]);
+    }
+}
@@ -0,0 +1,649 @@
+use crate::config::AlarmRule;
+use crate::core::events::GatewayEvent;
+use crate::core::lifecycle::Lifecycle;
+use crate::core::state::StateChange;
+use async_trait::async_trait;
+use serde::Serialize;
+use shared_models::{AlarmKind, AlarmRecord, AlarmSeverity};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc::Sender};
+
+// Cleared alarms kept for the API; the full history lives in telemetry
+const CLEARED_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmState {
+    Active,
+    Acknowledged,
+    Cleared,
+}
+
+impl AlarmState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlarmState::Active => "active",
+            AlarmState::Acknowledged => "acknowledged",
+            AlarmState::Cleared => "cleared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alarm {
+    pub id: String,
+    pub device_id: String,
+    pub kind: AlarmKind,
+    pub severity: AlarmSeverity,
+    pub state: AlarmState,
+    pub value: Option<f64>,
+    pub message: String,
+    pub raised_at: i64,
+    pub acknowledged_at: Option<i64>,
+    pub acknowledged_by: Option<String>,
+    pub cleared_at: Option<i64>,
+}
+
+impl Alarm {
+    // Wire format published to telemetry
+    pub fn record(&self) -> AlarmRecord {
+        AlarmRecord {
+            id: self.id.clone(),
+            kind: self.kind,
+            severity: self.severity,
+            value: self.value,
+            message: self.message.clone(),
+            raised_at: self.raised_at,
+            acknowledged_at: self.acknowledged_at,
+            acknowledged_by: self.acknowledged_by.clone(),
+            cleared_at: self.cleared_at,
+        }
+    }
+}
+
+// Outcome of one check against the latest value
+enum Check {
+    Violated { value: Option<f64>, message: String },
+    Normal,
+    // Inside the deadband: neither raises nor clears
+    Hold,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Condition {
+    // Violation waiting for the rule's delay to pass
+    pending: Option<(i64, Option<f64>, String)>,
+    open_alarm: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tracker {
+    last_value: Option<(f64, i64)>,
+    unchanged_since: Option<i64>,
+    last_seen: Option<i64>,
+    conditions: HashMap<AlarmKind, Condition>,
+}
+
+// Evaluates the alarm rules against device changes and owns the alarm lifecycle
+// (active → acknowledged → cleared). Every transition is returned as a StateChange
+// for the event loop to dispatch.
+#[derive(Debug, Clone, Default)]
+pub struct AlarmEngine {
+    rules: HashMap<String, AlarmRule>,
+    trackers: HashMap<String, Tracker>,
+    open: Vec<Alarm>,
+    cleared: VecDeque<Alarm>,
+}
+
+impl AlarmEngine {
+    // A device listed twice keeps its first rule
+    pub fn new(rules: Vec<AlarmRule>) -> Self {
+        let mut by_device = HashMap::new();
+        for rule in rules {
+            by_device.entry(rule.device_id.clone()).or_insert(rule);
+        }
+
+        Self {
+            rules: by_device,
+            ..Self::default()
+        }
+    }
+
+    // Open alarms first (newest first), then recently cleared ones
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.open
+            .iter()
+            .rev()
+            .chain(self.cleared.iter().rev())
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, alarm_id: &str) -> Option<&Alarm> {
+        self.open
+            .iter()
+            .chain(self.cleared.iter())
+            .find(|a| a.id == alarm_id)
+    }
+
+    // Timers only matter when a rule detects stale data or delays raising
+    pub fn needs_timer(&self) -> bool {
+        self.rules
+            .values()
+            .any(|r| r.stale_ms.is_some() || r.delay_ms > 0)
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.open.len()
+    }
+
+    // Alarm transitions caused by a device change
+    pub fn on_change(&mut self, change: &StateChange) -> Vec<StateChange> {
+        match change {
+            StateChange::DeviceCreated { id, timestamp } => {
+                if self.rules.contains_key(id) {
+                    let tracker = self.trackers.entry(id.clone()).or_default();
+                    tracker.last_seen = Some(tracker.last_seen.unwrap_or(0).max(*timestamp));
+                }
+                Vec::new()
+            }
+            StateChange::DeviceUpdated {
+                id,
+                value,
+                timestamp,
+            } => self.on_value(id, *value, *timestamp),
+            StateChange::DeviceRemoved { id, timestamp } => self.forget(id, *timestamp),
+            _ => Vec::new(),
+        }
+    }
+
+    // Swaps the rules after a config reload. Devices whose rule changed or disappeared
+    // start over: their open alarms are cleared and re-raised by the new rule if needed.
+    pub fn replace_rules(&mut self, rules: Vec<AlarmRule>, timestamp: i64) -> Vec<StateChange> {
+        let next = Self::new(rules).rules;
+        let changed: Vec<String> = self
+            .rules
+            .iter()
+            .filter(|(id, rule)| next.get(*id) != Some(*rule))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        self.rules = next;
+        changed
+            .iter()
+            .flat_map(|id| self.forget(id, timestamp))
+            .collect()
+    }
+
+    // Drops what is known about a device and clears its open alarms
+    fn forget(&mut self, device_id: &str, timestamp: i64) -> Vec<StateChange> {
+        self.trackers.remove(device_id);
+        let ids: Vec<String> = self
+            .open
+            .iter()
+            .filter(|a| a.device_id == device_id)
+            .map(|a| a.id.clone())
+            .collect();
+        ids.into_iter()
+            .filter_map(|alarm_id| self.clear(&alarm_id, timestamp))
+            .collect()
+    }
+
+    fn on_value(&mut self, device_id: &str, value: f64, timestamp: i64) -> Vec<StateChange> {
+        let Some(rule) = self.rules.get(device_id).cloned() else {
+            return Vec::new();
+        };
+        let tracker = self.trackers.entry(device_id.to_string()).or_default();
+        let mut checks = Vec::new();
+
+        if let Some(limit) = rule.hi_hi {
+            checks.push((AlarmKind::HiHi, above(value, limit, rule.deadband, "hi-hi")));
+        }
+        if let Some(limit) = rule.hi {
+            checks.push((AlarmKind::Hi, above(value, limit, rule.deadband, "hi")));
+        }
+        if let Some(limit) = rule.lo {
+            checks.push((AlarmKind::Lo, below(value, limit, rule.deadband, "lo")));
+        }
+        if let Some(limit) = rule.lo_lo {
+            checks.push((AlarmKind::LoLo, below(value, limit, rule.deadband, "lo-lo")));
+        }
+
+        if let (Some(limit), Some((last, last_ts))) = (rule.rate_of_change, tracker.last_value) {
+            if timestamp > last_ts {
+                let rate = (value - last).abs() / ((timestamp - last_ts) as f64 / 1000.0);
+                let check = if rate > limit {
+                    Check::Violated {
+                        value: Some(value),
+                        message: format!("value changed by {:.3}/s, limit is {}/s", rate, limit),
+                    }
+                } else {
+                    Check::Normal
+                };
+                checks.push((AlarmKind::RateOfChange, check));
+            }
+        }
+
+        if tracker.last_value.is_none_or(|(last, _)| last != value) {
+            tracker.unchanged_since = Some(timestamp);
+        }
+        if let (Some(stuck_ms), Some(since)) = (rule.stuck_ms, tracker.unchanged_since) {
+            let check = if timestamp - since >= stuck_ms as i64 {
+                Check::Violated {
+                    value: Some(value),
+                    message: format!("value {} unchanged for {} ms", value, timestamp - since),
+                }
+            } else {
+                Check::Normal
+            };
+            checks.push((AlarmKind::Stuck, check));
+        }
+
+        // Fresh data ends a stale condition
+        if rule.stale_ms.is_some() {
+            checks.push((AlarmKind::Stale, Check::Normal));
+        }
+
+        tracker.last_value = Some((value, timestamp));
+        tracker.last_seen = Some(tracker.last_seen.unwrap_or(timestamp).max(timestamp));
+
+        checks
+            .into_iter()
+            .filter_map(|(kind, check)| self.step(device_id, kind, check, rule.delay_ms, timestamp))
+            .collect()
+    }
+
+    // Periodic check for stale data and for violations whose delay has passed
+    pub fn on_timer(&mut self, now: i64) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+        let devices: Vec<String> = self.trackers.keys().cloned().collect();
+
+        for device_id in devices {
+            let Some(rule) = self.rules.get(&device_id).cloned() else {
+                continue;
+            };
+
+            let last_seen = self.trackers.get(&device_id).and_then(|t| t.last_seen);
+            // A fresh device still gets its other delayed violations raised below
+            let stale = rule
+                .stale_ms
+                .zip(last_seen)
+                .filter(|&(stale_ms, last_seen)| now - last_seen > stale_ms as i64);
+            if let Some((_, last_seen)) = stale {
+                let check = Check::Violated {
+                    value: None,
+                    message: format!("no value for {} ms", now - last_seen),
+                };
+                changes.extend(self.step(&device_id, AlarmKind::Stale, check, rule.delay_ms, now));
+            }
+
+            let pending: Vec<(AlarmKind, Option<f64>, String)> = self.trackers[&device_id]
+                .conditions
+                .iter()
+                .filter_map(|(kind, c)| {
+                    c.pending
+                        .as_ref()
+                        .map(|(_, value, message)| (*kind, *value, message.clone()))
+                })
+                .collect();
+            for (kind, value, message) in pending {
+                let check = Check::Violated { value, message };
+                changes.extend(self.step(&device_id, kind, check, rule.delay_ms, now));
+            }
+        }
+
+        changes
+    }
+
+    // Only active alarms can be acknowledged
+    pub fn acknowledge(&mut self, alarm_id: &str, by: &str, timestamp: i64) -> Option<StateChange> {
+        let alarm = self
+            .open
+            .iter_mut()
+            .find(|a| a.id == alarm_id && a.state == AlarmState::Active)?;
+
+        alarm.state = AlarmState::Acknowledged;
+        alarm.acknowledged_at = Some(timestamp);
+        alarm.acknowledged_by = Some(by.to_string());
+        Some(StateChange::AlarmAcknowledged(alarm.clone()))
+    }
+
+    fn step(
+        &mut self,
+        device_id: &str,
+        kind: AlarmKind,
+        check: Check,
+        delay_ms: u64,
+        now: i64,
+    ) -> Option<StateChange> {
+        let condition = self
+            .trackers
+            .get_mut(device_id)?
+            .conditions
+            .entry(kind)
+            .or_default();
+
+        match check {
+            Check::Violated { value, message } => {
+                if condition.open_alarm.is_some() {
+                    return None;
+                }
+                let since = condition
+                    .pending
+                    .as_ref()
+                    .map_or(now, |(since, _, _)| *since);
+                if now - since < delay_ms as i64 {
+                    condition.pending = Some((since, value, message));
+                    return None;
+                }
+
+                let alarm = Alarm {
+                    id: format!("{}-{}-{}", device_id, kind.as_str(), now),
+                    device_id: device_id.to_string(),
+                    kind,
+                    severity: kind.severity(),
+                    state: AlarmState::Active,
+                    value,
+                    message,
+                    raised_at: now,
+                    acknowledged_at: None,
+                    acknowledged_by: None,
+                    cleared_at: None,
+                };
+                condition.pending = None;
+                condition.open_alarm = Some(alarm.id.clone());
+                self.open.push(alarm.clone());
+                Some(StateChange::AlarmRaised(alarm))
+            }
+            Check::Normal => {
+                condition.pending = None;
+                let alarm_id = condition.open_alarm.take()?;
+                self.clear(&alarm_id, now)
+            }
+            Check::Hold => {
+                condition.pending = None;
+                None
+            }
+        }
+    }
+
+    fn clear(&mut self, alarm_id: &str, timestamp: i64) -> Option<StateChange> {
+        let pos = self.open.iter().position(|a| a.id == alarm_id)?;
+        let mut alarm = self.open.remove(pos);
+        alarm.state = AlarmState::Cleared;
+        alarm.cleared_at = Some(timestamp);
+
+        self.cleared.push_back(alarm.clone());
+        if self.cleared.len() > CLEARED_HISTORY {
+            self.cleared.pop_front();
+        }
+        Some(StateChange::AlarmCleared(alarm))
+    }
+}
+
+fn above(value: f64, limit: f64, deadband: f64, name: &str) -> Check {
+    if value > limit {
+        Check::Violated {
+            value: Some(value),
+            message: format!("value {} above {} limit {}", value, name, limit),
+        }
+    } else if value <= limit - deadband {
+        Check::Normal
+    } else {
+        Check::Hold
+    }
+}
+
+fn below(value: f64, limit: f64, deadband: f64, name: &str) -> Check {
+    if value < limit {
+        Check::Violated {
+            value: Some(value),
+            message: format!("value {} below {} limit {}", value, name, limit),
+        }
+    } else if value >= limit + deadband {
+        Check::Normal
+    } else {
+        Check::Hold
+    }
+}
+
+// Feeds the event loop with timer events so stale data and delays are evaluated
+// even when no values arrive
+pub struct AlarmTimer {
+    tx: Sender<GatewayEvent>,
+    interval: Duration,
+}
+
+impl AlarmTimer {
+    pub fn new(tx: Sender<GatewayEvent>, interval_ms: u64) -> Self {
+        Self {
+            tx,
+            interval: Duration::from_millis(interval_ms),
+        }
+    }
+}
+
+#[async_trait]
+impl Lifecycle for AlarmTimer {
+    async fn run(self, mut shutdown: broadcast::Receiver<()>) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.recv() => break,
+                _ = interval.tick() => {
+                    let event = GatewayEvent::AlarmTimer {
+                        timestamp: chrono::Utc::now().timestamp_millis(),
+                    };
+                    if self.tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(rule: AlarmRule) -> AlarmEngine {
+        AlarmEngine::new(vec![AlarmRule {
+            device_id: "1".into(),
+            ..rule
+        }])
+    }
+
+    fn update(engine: &mut AlarmEngine, value: f64, timestamp: i64) -> Vec<StateChange> {
+        engine.on_change(&StateChange::DeviceUpdated {
+            id: "1".into(),
+            value,
+            timestamp,
+        })
+    }
+
+    fn kinds(changes: &[StateChange]) -> Vec<(&'static str, AlarmKind)> {
+        changes
+            .iter()
+            .map(|c| match c {
+                StateChange::AlarmRaised(a) => ("raised", a.kind),
+                StateChange::AlarmAcknowledged(a) => ("acknowledged", a.kind),
+                StateChange::AlarmCleared(a) => ("cleared", a.kind),
+                other => panic!("unexpected change {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn limits_raise_and_clear_with_hysteresis() {
+        let mut e = engine(AlarmRule {
+            hi: Some(80.0),
+            hi_hi: Some(90.0),
+            deadband: 5.0,
+            ..Default::default()
+        });
+
+        assert!(update(&mut e, 70.0, 0).is_empty());
+        assert_eq!(
+            kinds(&update(&mut e, 95.0, 1_000)),
+            vec![("raised", AlarmKind::HiHi), ("raised", AlarmKind::Hi)]
+        );
+        // Back below hi-hi but inside its deadband: nothing changes
+        assert!(update(&mut e, 88.0, 2_000).is_empty());
+        assert_eq!(
+            kinds(&update(&mut e, 84.0, 3_000)),
+            vec![("cleared", AlarmKind::HiHi)]
+        );
+        assert!(update(&mut e, 76.0, 4_000).is_empty());
+        assert_eq!(
+            kinds(&update(&mut e, 75.0, 5_000)),
+            vec![("cleared", AlarmKind::Hi)]
+        );
+        assert_eq!(e.open_count(), 0);
+    }
+
+    #[test]
+    fn delay_defers_raising_until_the_condition_held() {
+        let mut e = engine(AlarmRule {
+            lo: Some(10.0),
+            delay_ms: 5_000,
+            ..Default::default()
+        });
+
+        assert!(update(&mut e, 5.0, 0).is_empty());
+        // A short recovery resets the delay
+        assert!(update(&mut e, 12.0, 1_000).is_empty());
+        assert!(update(&mut e, 5.0, 2_000).is_empty());
+        assert!(e.on_timer(6_000).is_empty());
+        assert_eq!(kinds(&e.on_timer(7_000)), vec![("raised", AlarmKind::Lo)]);
+    }
+
+    #[test]
+    fn timer_raises_delayed_violations_of_fresh_devices() {
+        let mut e = engine(AlarmRule {
+            lo: Some(10.0),
+            delay_ms: 5_000,
+            stale_ms: Some(60_000),
+            ..Default::default()
+        });
+
+        assert!(update(&mut e, 5.0, 0).is_empty());
+        // Well within stale_ms, the delayed lo alarm is still raised by the timer
+        assert_eq!(kinds(&e.on_timer(5_000)), vec![("raised", AlarmKind::Lo)]);
+    }
+
+    #[test]
+    fn rate_of_change_and_stuck_values() {
+        let mut e = engine(AlarmRule {
+            rate_of_change: Some(10.0),
+            stuck_ms: Some(3_000),
+            ..Default::default()
+        });
+
+        update(&mut e, 0.0, 0);
+        assert_eq!(
+            kinds(&update(&mut e, 50.0, 1_000)),
+            vec![("raised", AlarmKind::RateOfChange)]
+        );
+        assert_eq!(
+            kinds(&update(&mut e, 50.0, 2_000)),
+            vec![("cleared", AlarmKind::RateOfChange)]
+        );
+        assert!(update(&mut e, 50.0, 3_000).is_empty());
+        assert_eq!(
+            kinds(&update(&mut e, 50.0, 4_000)),
+            vec![("raised", AlarmKind::Stuck)]
+        );
+        assert_eq!(
+            kinds(&update(&mut e, 51.0, 5_000)),
+            vec![("cleared", AlarmKind::Stuck)]
+        );
+    }
+
+    #[test]
+    fn stale_data_is_detected_by_the_timer() {
+        let mut e = engine(AlarmRule {
+            stale_ms: Some(10_000),
+            ..Default::default()
+        });
+
+        update(&mut e, 1.0, 0);
+        assert!(e.on_timer(10_000).is_empty());
+        assert_eq!(
+            kinds(&e.on_timer(10_001)),
+            vec![("raised", AlarmKind::Stale)]
+        );
+        assert!(e.on_timer(20_000).is_empty());
+        assert_eq!(
+            kinds(&update(&mut e, 1.0, 21_000)),
+            vec![("cleared", AlarmKind::Stale)]
+        );
+    }
+
+    #[test]
+    fn lifecycle_active_acknowledged_cleared() {
+        let mut e = engine(AlarmRule {
+            hi: Some(10.0),
+            ..Default::default()
+        });
+
+        let raised = update(&mut e, 20.0, 1_000);
+        let StateChange::AlarmRaised(alarm) = &raised[0] else {
+            panic!("expected a raised alarm");
+        };
+
+        let Some(StateChange::AlarmAcknowledged(acked)) = e.acknowledge(&alarm.id, "op", 2_000)
+        else {
+            panic!("expected an acknowledgement");
+        };
+        assert_eq!(acked.state, AlarmState::Acknowledged);
+        assert_eq!(acked.acknowledged_by.as_deref(), Some("op"));
+        // Already acknowledged
+        assert!(e.acknowledge(&alarm.id, "op", 2_500).is_none());
+
+        let cleared = e.on_change(&StateChange::DeviceRemoved {
+            id: "1".into(),
+            timestamp: 3_000,
+        });
+        let StateChange::AlarmCleared(cleared) = &cleared[0] else {
+            panic!("expected a cleared alarm");
+        };
+        assert_eq!(cleared.acknowledged_at, Some(2_000));
+        assert_eq!(cleared.cleared_at, Some(3_000));
+        assert_eq!(e.get(&alarm.id).unwrap().state, AlarmState::Cleared);
+    }
+
+    #[test]
+    fn replacing_rules_clears_alarms_of_changed_devices_only() {
+        let rule = |device_id: &str, hi| AlarmRule {
+            device_id: device_id.into(),
+            hi: Some(hi),
+            ..Default::default()
+        };
+        let mut e = AlarmEngine::new(vec![rule("1", 80.0), rule("2", 80.0)]);
+        for id in ["1", "2"] {
+            e.on_change(&StateChange::DeviceUpdated {
+                id: id.into(),
+                value: 85.0,
+                timestamp: 1_000,
+            });
+        }
+        assert_eq!(e.open_count(), 2);
+
+        let changes = e.replace_rules(vec![rule("1", 80.0), rule("2", 90.0)], 2_000);
+        assert_eq!(kinds(&changes), vec![("cleared", AlarmKind::Hi)]);
+        assert!(matches!(&changes[0], StateChange::AlarmCleared(a) if a.device_id == "2"));
+
+        // The new limit applies from the next value on
+        assert!(update(&mut e, 85.0, 3_000).is_empty());
+        assert!(e
+            .on_change(&StateChange::DeviceUpdated {
+                id: "2".into(),
+                value: 85.0,
+                timestamp: 3_000,
+            })
+            .is_empty());
+        assert_eq!(e.open_count(), 1);
+    }
+}
@@ -18,21 +18,25 @@ pub async fn initialize_devices(tx: &Sender<GatewayEvent>, config: &Config) {
             }
         }
         SourceMode::Simulation => {
-            for device_id in 1..=2 {
+            // First values follow with the source's first tick
+            for device in config.simulation.devices() {
                 let _ = tx
                     .send(GatewayEvent::DeviceCreated {
-                        id: device_id.to_string(),
-                        timestamp: chrono::Utc::now().timestamp_millis(),
-                    })
-                    .await;
-                let _ = tx
-                    .send(GatewayEvent::DeviceValueObserved {
-                        id: device_id.to_string(),
-                        value: rand::random::<f64>() * 100.0,
+                        id: device.id,
                         timestamp: chrono::Utc::now().timestamp_millis(),
                     })
                     .await;
             }
         }
     }
+
+    // Virtual devices exist from the start; their first value follows their inputs
+    for device in &config.virtual_devices {
+        let _ = tx
+            .send(GatewayEvent::DeviceCreated {
+                id: device.id.clone(),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            })
+            .await;
+    }
 }
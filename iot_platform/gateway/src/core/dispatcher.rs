@@ -1,33 +1,184 @@
+use crate::config::{DispatchConfig, DispatchMode};
+use crate::core::listener_queue::{ListenerQueue, Pushed};
+use crate::core::metrics::Metrics;
 use crate::core::state::{StateChange, StateListener};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
 
 #[derive(Clone)]
 pub struct Dispatcher {
     listeners: Vec<Arc<dyn StateListener>>,
+    metrics: Metrics,
+    // Listeners that are fed from one queue in queued mode, see `share_queue`
+    shared: Vec<String>,
+    // Queued mode: one queue per listener or shared group, and their worker tasks
+    queues: Vec<(Group, Arc<ListenerQueue>)>,
+    workers: Arc<Mutex<Vec<JoinHandle<()>>>>,
 }
 
+type Group = Arc<[Arc<dyn StateListener>]>;
+
 impl Dispatcher {
     pub fn new(listeners: Vec<Arc<dyn StateListener>>) -> Self {
-        Self { listeners }
+        Self {
+            listeners,
+            metrics: Metrics::new(),
+            shared: Vec::new(),
+            queues: Vec::new(),
+            workers: Arc::default(),
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    // The named listeners keep their relative order in queued mode by sharing one queue
+    // and task, e.g. aggregation and MQTT. The queue is configured under the last name.
+    pub fn share_queue(mut self, names: &[&str]) -> Self {
+        self.shared = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    // In `queued` mode every listener gets its own queue and task; changes reach each
+    // listener in order, but listeners no longer wait for each other
+    pub fn with_config(mut self, config: &DispatchConfig) -> Self {
+        if config.mode == DispatchMode::Sequential {
+            return self;
+        }
+        let (shared, single): (Vec<_>, Vec<_>) = self
+            .listeners
+            .iter()
+            .cloned()
+            .partition(|listener| self.shared.iter().any(|name| name == listener.name()));
+        let groups = single
+            .into_iter()
+            .map(|listener| vec![listener])
+            .chain((!shared.is_empty()).then_some(shared));
+
+        let mut workers = self.workers.lock().unwrap();
+        for group in groups {
+            let name = group
+                .last()
+                .map(|listener| listener.name())
+                .unwrap_or_default();
+            let queue = Arc::new(ListenerQueue::new(config.queue(name).clone()));
+            let group: Group = group.into();
+            workers.push(tokio::spawn(worker(
+                group.clone(),
+                queue.clone(),
+                self.metrics.clone(),
+            )));
+            self.queues.push((group, queue));
+        }
+        drop(workers);
+        self
     }
 
     pub async fn dispatch(&self, event: StateChange) {
+        if !self.queues.is_empty() {
+            return self.enqueue(event).await;
+        }
         for listener in &self.listeners {
+            let started = Instant::now();
             // await on_event - sequential execution preserves listener order
-            if let Err(e) = listener.on_event(event.clone()).await {
+            let result = listener.on_event(event.clone()).await;
+            self.metrics.observe_dispatch(
+                listener.name(),
+                started.elapsed().as_secs_f64(),
+                result.is_err(),
+            );
+
+            if let Err(e) = result {
                 // Centralized logging for all side-effect errors
-                tracing::error!("Listener failed to process event: {:?}", e);
+                tracing::error!(
+                    "Listener {} failed to process event: {:?}",
+                    listener.name(),
+                    e
+                );
             };
         }
     }
+
+    async fn enqueue(&self, event: StateChange) {
+        for (group, queue) in &self.queues {
+            let pushed = queue.push(event.clone()).await;
+            for listener in group.iter() {
+                match pushed {
+                    Pushed::Queued => {}
+                    Pushed::Dropped => self.metrics.listener_dropped(listener.name()),
+                    Pushed::Closed => warn!(
+                        "Listener {} is shut down, change not delivered",
+                        listener.name()
+                    ),
+                }
+                self.metrics
+                    .set_listener_queue_depth(listener.name(), queue.len());
+            }
+        }
+    }
+
+    // Delivers everything still queued, then stops the listener tasks. Called by the
+    // event loop once it has drained; a no-op in sequential mode.
+    pub async fn shutdown(&self) {
+        for (_, queue) in &self.queues {
+            queue.close();
+        }
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        for worker in workers {
+            let _ = worker.await;
+        }
+    }
+}
+
+// Feeds a group of listeners from their queue, each change to every listener in turn. A
+// panicking listener loses the change it was handling; the queue is picked up again by
+// a new task.
+async fn worker(group: Group, queue: Arc<ListenerQueue>, metrics: Metrics) {
+    loop {
+        let task = tokio::spawn(drain(group.clone(), queue.clone(), metrics.clone()));
+        match task.await {
+            Ok(()) => break,
+            Err(e) => error!(
+                "Listener {} task failed, restarting: {}",
+                group
+                    .iter()
+                    .map(|l| l.name())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                e
+            ),
+        }
+    }
+}
+
+async fn drain(group: Group, queue: Arc<ListenerQueue>, metrics: Metrics) {
+    while let Some((change, queued_at)) = queue.pop().await {
+        for listener in group.iter() {
+            let name = listener.name();
+            metrics.observe_listener_lag(name, queued_at.elapsed().as_secs_f64());
+            metrics.set_listener_queue_depth(name, queue.len());
+
+            let started = Instant::now();
+            let result = listener.on_event(change.clone()).await;
+            metrics.observe_dispatch(name, started.elapsed().as_secs_f64(), result.is_err());
+            if let Err(e) = result {
+                error!("Listener {} failed to process event: {:?}", name, e);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::config::{DispatchConfig, DispatchMode};
     use crate::core::state::{ListenerError, StateChange, StateListener};
-    use tokio::sync::Mutex;
+    use tokio::sync::{mpsc, Mutex, Semaphore};
 
     use std::sync::Arc;
 
@@ -72,4 +223,138 @@ mod tests {
 
         assert_eq!(*calls.lock().await, 1);
     }
+
+    #[tokio::test]
+    async fn dispatcher_records_listener_failures() {
+        let metrics = Metrics::new();
+        let dispatcher =
+            Dispatcher::new(vec![Arc::new(FailingListener)]).with_metrics(metrics.clone());
+
+        dispatcher
+            .dispatch(StateChange::DeviceCreated {
+                id: "1".to_string(),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            })
+            .await;
+
+        assert!(metrics
+            .render()
+            .contains(r#"gateway_dispatch_failures_total{listener="listener"} 1"#));
+    }
+
+    // Records the timestamps it sees, as `name:timestamp`. A gated recorder waits for
+    // one permit per change.
+    struct Recorder {
+        name: &'static str,
+        gate: Option<Arc<Semaphore>>,
+        seen: mpsc::UnboundedSender<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl StateListener for Recorder {
+        async fn on_event(&self, change: StateChange) -> Result<(), ListenerError> {
+            if let Some(gate) = &self.gate {
+                gate.acquire().await.unwrap().forget();
+            }
+            if let StateChange::DeviceCreated { timestamp, .. } = change {
+                let _ = self.seen.send(format!("{}:{}", self.name, timestamp));
+            }
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn recorder(
+        name: &'static str,
+        gate: Option<&Arc<Semaphore>>,
+        seen: &mpsc::UnboundedSender<String>,
+    ) -> Arc<dyn StateListener> {
+        Arc::new(Recorder {
+            name,
+            gate: gate.cloned(),
+            seen: seen.clone(),
+        })
+    }
+
+    async fn dispatch_created(dispatcher: &Dispatcher, count: i64) {
+        for timestamp in 1..=count {
+            dispatcher
+                .dispatch(StateChange::DeviceCreated {
+                    id: "1".into(),
+                    timestamp,
+                })
+                .await;
+        }
+    }
+
+    fn queued() -> DispatchConfig {
+        DispatchConfig {
+            mode: DispatchMode::Queued,
+            ..DispatchConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_mode_isolates_slow_listeners_and_keeps_order() {
+        let gate = Arc::new(Semaphore::new(0));
+        let (slow_tx, mut slow) = mpsc::unbounded_channel();
+        let (fast_tx, mut fast) = mpsc::unbounded_channel();
+        let metrics = Metrics::new();
+        let dispatcher = Dispatcher::new(vec![
+            recorder("slow", Some(&gate), &slow_tx),
+            recorder("fast", None, &fast_tx),
+        ])
+        .with_metrics(metrics.clone())
+        .with_config(&queued());
+
+        dispatch_created(&dispatcher, 5).await;
+        for timestamp in 1..=5 {
+            assert_eq!(fast.recv().await.unwrap(), format!("fast:{}", timestamp));
+        }
+        assert!(slow.try_recv().is_err(), "slow listener still blocked");
+
+        gate.add_permits(5);
+        dispatcher.shutdown().await;
+        for timestamp in 1..=5 {
+            assert_eq!(slow.try_recv().unwrap(), format!("slow:{}", timestamp));
+        }
+        assert!(metrics
+            .render()
+            .contains(r#"gateway_listener_lag_seconds_count{listener="slow"} 5"#));
+    }
+
+    #[tokio::test]
+    async fn shared_queue_keeps_order_across_its_listeners() {
+        let gate = Arc::new(Semaphore::new(0));
+        let (shared_tx, mut shared) = mpsc::unbounded_channel();
+        let (other_tx, mut other) = mpsc::unbounded_channel();
+        let dispatcher = Dispatcher::new(vec![
+            recorder("first", Some(&gate), &shared_tx),
+            recorder("other", None, &other_tx),
+            recorder("second", None, &shared_tx),
+        ])
+        .share_queue(&["first", "second"])
+        .with_config(&queued());
+
+        dispatch_created(&dispatcher, 3).await;
+        // Listeners outside the group are not held up by it
+        for timestamp in 1..=3 {
+            assert_eq!(other.recv().await.unwrap(), format!("other:{}", timestamp));
+        }
+        assert!(shared.try_recv().is_err(), "first listener still blocked");
+
+        gate.add_permits(3);
+        dispatcher.shutdown().await;
+        let mut seen = Vec::new();
+        while let Ok(entry) = shared.try_recv() {
+            seen.push(entry);
+        }
+        assert_eq!(
+            seen,
+            ["first:1", "second:1", "first:2", "second:2", "first:3", "second:3"]
+        );
+    }
 }
@@ -0,0 +1,107 @@
+use crate::core::{
+    dispatcher::Dispatcher,
+    events::GatewayEvent,
+    health::{self, HealthRegistry},
+    lifecycle::Lifecycle,
+    metrics::Metrics,
+    state::{GatewayState, StateChange},
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc::Receiver, Mutex};
+use tracing::{debug, info};
+
+// The single writer: applies GatewayEvents to the state in arrival order and dispatches
+// the resulting changes. On shutdown it stops accepting events and drains the ones
+// already queued, so nothing the sources produced is lost.
+pub struct EventLoop {
+    gateway_name: String,
+    rx: Receiver<GatewayEvent>,
+    state: Arc<Mutex<GatewayState>>,
+    dispatcher: Arc<Dispatcher>,
+    health: HealthRegistry,
+    metrics: Metrics,
+}
+
+impl EventLoop {
+    pub fn new(
+        gateway_name: String,
+        rx: Receiver<GatewayEvent>,
+        state: Arc<Mutex<GatewayState>>,
+        dispatcher: Arc<Dispatcher>,
+        health: HealthRegistry,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            gateway_name,
+            rx,
+            state,
+            dispatcher,
+            health,
+            metrics,
+        }
+    }
+
+    async fn process(&self, event: GatewayEvent) {
+        debug!("{}: Event loop: received {:?}", self.gateway_name, event);
+
+        let (changes, open_alarms) = {
+            let mut state = self.state.lock().await;
+            let kind = event.kind();
+            let changes = state.apply(event);
+            self.metrics.event_applied(kind, state.devices.len());
+            (changes, state.alarms.open_count())
+        };
+
+        // Device change first, then the alarm transitions it caused
+        for change in changes {
+            if let StateChange::AlarmRaised(alarm)
+            | StateChange::AlarmAcknowledged(alarm)
+            | StateChange::AlarmCleared(alarm) = &change
+            {
+                self.metrics.alarm_transition(
+                    alarm.kind.as_str(),
+                    alarm.state.as_str(),
+                    open_alarms,
+                );
+            }
+            self.dispatcher.dispatch(change).await;
+        }
+        self.health.mark_success(health::EVENT_LOOP);
+    }
+}
+
+#[async_trait]
+impl Lifecycle for EventLoop {
+    // `shutdown` is expected once the sources have stopped
+    async fn run(mut self, mut shutdown: broadcast::Receiver<()>) {
+        self.health.mark_up(health::EVENT_LOOP);
+        loop {
+            tokio::select! {
+                _ = shutdown.recv() => break,
+                event = self.rx.recv() => match event {
+                    Some(event) => self.process(event).await,
+                    None => {
+                        self.health.mark_down(health::EVENT_LOOP, "event channel closed");
+                        return;
+                    }
+                },
+            }
+        }
+
+        // Late senders (e.g. an API request still in flight) now get an error
+        self.rx.close();
+        let mut drained = 0;
+        while let Some(event) = self.rx.recv().await {
+            self.process(event).await;
+            drained += 1;
+        }
+        // Queued dispatch: wait until the listeners have handled everything
+        self.dispatcher.shutdown().await;
+        info!(
+            "Event loop stopped after draining {} queued events",
+            drained
+        );
+        self.health.mark_down(health::EVENT_LOOP, "stopped");
+    }
+}
@@ -1,3 +1,6 @@
+use crate::config::AlarmRule;
+use crate::core::virtual_devices::VirtualDevices;
+
 #[derive(Debug)]
 pub enum GatewayEvent {
     DeviceValueObserved {
@@ -13,4 +16,33 @@ pub enum GatewayEvent {
         id: String,
         timestamp: i64,
     },
+    AlarmAcknowledged {
+        alarm_id: String,
+        by: String,
+        timestamp: i64,
+    },
+    // Periodic tick for stale-data checks and alarm delays
+    AlarmTimer {
+        timestamp: i64,
+    },
+    // Sent by the config reloader; validated before it is sent
+    RulesReplaced {
+        alarm_rules: Vec<AlarmRule>,
+        virtual_devices: VirtualDevices,
+        timestamp: i64,
+    },
+}
+
+impl GatewayEvent {
+    // Stable label used for metrics and logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GatewayEvent::DeviceValueObserved { .. } => "device_value_observed",
+            GatewayEvent::DeviceCreated { .. } => "device_created",
+            GatewayEvent::DeviceRemoved { .. } => "device_removed",
+            GatewayEvent::AlarmAcknowledged { .. } => "alarm_acknowledged",
+            GatewayEvent::AlarmTimer { .. } => "alarm_timer",
+            GatewayEvent::RulesReplaced { .. } => "rules_replaced",
+        }
+    }
 }
@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+
+// Arithmetic over device values used by virtual devices:
+//   voltage * current * 0.001      device ids as identifiers
+//   {100} - {200}                  braces for ids that are not identifiers
+//   avg(#temperatures)             # expands a tag to its device ids
+//   max(a, b, 0) / abs(c)          sum, avg, min, max and abs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Device(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Arg>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Abs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Expr(Expr),
+    // Devices of a tag, resolved while parsing
+    Tag(String, Vec<String>),
+}
+
+impl Expr {
+    pub fn parse(input: &str, tags: &BTreeMap<String, Vec<String>>) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+            tags,
+        };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    // None while a referenced device has no value; tag members without a value are
+    // skipped, an empty tag has no value either
+    pub fn eval(&self, value: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            Expr::Device(id) => value(id),
+            Expr::Neg(e) => e.eval(value).map(|v| -v),
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(value)?, r.eval(value)?);
+                Some(match op {
+                    Op::Add => l + r,
+                    Op::Sub => l - r,
+                    Op::Mul => l * r,
+                    Op::Div => l / r,
+                })
+            }
+            Expr::Call(func, args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    match arg {
+                        Arg::Expr(e) => values.push(e.eval(value)?),
+                        Arg::Tag(_, ids) => values.extend(ids.iter().filter_map(|id| value(id))),
+                    }
+                }
+                if values.is_empty() {
+                    return None;
+                }
+                Some(match func {
+                    Func::Sum => values.iter().sum(),
+                    Func::Avg => values.iter().sum::<f64>() / values.len() as f64,
+                    Func::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+                    Func::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    Func::Abs => values[0].abs(),
+                })
+            }
+        }
+    }
+
+    // Every device the expression reads, tag members included
+    pub fn inputs(&self, out: &mut Vec<String>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Device(id) => out.push(id.clone()),
+            Expr::Neg(e) => e.inputs(out),
+            Expr::Binary(_, l, r) => {
+                l.inputs(out);
+                r.inputs(out);
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    match arg {
+                        Arg::Expr(e) => e.inputs(out),
+                        Arg::Tag(_, ids) => out.extend(ids.iter().cloned()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Device(String),
+    Tag(String),
+    Symbol(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                let n = number
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", number))?;
+                tokens.push(Token::Number(n));
+            }
+            '{' => {
+                chars.next();
+                let id: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if id.trim().is_empty() {
+                    return Err("empty device id in braces".into());
+                }
+                tokens.push(Token::Device(id.trim().to_string()));
+            }
+            '#' => {
+                chars.next();
+                let name = identifier(&mut chars);
+                if name.is_empty() {
+                    return Err("tag name expected after '#'".into());
+                }
+                tokens.push(Token::Tag(name));
+            }
+            '+' | '-' | '*' | '/' | '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(Token::Symbol(c));
+            }
+            c if c.is_alphabetic() || c == '_' => tokens.push(Token::Ident(identifier(&mut chars))),
+            c => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn identifier(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_alphanumeric() || c == '_') {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    name
+}
+
+// Recursive descent: expr = term (+|- term)*, term = unary (*|/ unary)*
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    tags: &'a BTreeMap<String, Vec<String>>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Device(id)) => Ok(Expr::Device(id)),
+            Some(Token::Ident(name)) if self.eat('(') => self.call(&name),
+            Some(Token::Ident(name)) => Ok(Expr::Device(name)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.expr()?;
+                if !self.eat(')') {
+                    return Err("missing ')'".into());
+                }
+                Ok(expr)
+            }
+            Some(Token::Tag(name)) => Err(format!(
+                "tag #{} can only be used as a function argument",
+                name
+            )),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let func = match name {
+            "sum" => Func::Sum,
+            "avg" => Func::Avg,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "abs" => Func::Abs,
+            _ => return Err(format!("unknown function '{}'", name)),
+        };
+
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.arg()?);
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err(format!("expected ',' or ')' in {}()", name));
+                }
+            }
+        }
+
+        match (func, args.as_slice()) {
+            (_, []) => Err(format!("{}() needs at least one argument", name)),
+            (Func::Abs, [Arg::Expr(_)]) => Ok(Expr::Call(func, args)),
+            (Func::Abs, _) => Err("abs() takes exactly one expression".into()),
+            _ => Ok(Expr::Call(func, args)),
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, String> {
+        if let Some(Token::Tag(name)) = self.peek().cloned() {
+            self.pos += 1;
+            let ids = self
+                .tags
+                .get(&name)
+                .ok_or_else(|| format!("unknown tag #{}", name))?;
+            return Ok(Arg::Tag(name, ids.clone()));
+        }
+        self.expr().map(Arg::Expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn tags() -> BTreeMap<String, Vec<String>> {
+        BTreeMap::from([(
+            "temperatures".to_string(),
+            vec!["t1".to_string(), "t2".to_string(), "t3".to_string()],
+        )])
+    }
+
+    fn eval(input: &str, values: &[(&str, f64)]) -> Option<f64> {
+        let values: HashMap<&str, f64> = values.iter().copied().collect();
+        Expr::parse(input, &tags())
+            .unwrap()
+            .eval(&|id| values.get(id).copied())
+    }
+
+    #[test]
+    fn evaluates_arithmetic_with_precedence() {
+        let values = [("voltage", 250.0), ("current", 4.0), ("100", 4.0)];
+
+        assert_eq!(eval("voltage * current * 0.001", &values), Some(1.0));
+        assert_eq!(eval("1 + 2 * 3 - -{100}", &values), Some(11.0));
+        assert_eq!(eval("(1 + 2) * 3 / {100}", &values), Some(2.25));
+        assert_eq!(eval("voltage * missing", &values), None);
+    }
+
+    #[test]
+    fn aggregates_over_tags_skip_missing_devices() {
+        let values = [("t1", 20.0), ("t3", 24.0), ("offset", -1.0)];
+
+        assert_eq!(eval("avg(#temperatures)", &values), Some(22.0));
+        assert_eq!(eval("sum(#temperatures, offset)", &values), Some(43.0));
+        assert_eq!(eval("min(#temperatures)", &values), Some(20.0));
+        assert_eq!(
+            eval("max(#temperatures) + abs(offset)", &values),
+            Some(25.0)
+        );
+        assert_eq!(eval("avg(#temperatures)", &[]), None);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        for (input, error) in [
+            ("voltage *", "unexpected end"),
+            ("(voltage", "missing ')'"),
+            ("median(a)", "unknown function"),
+            ("avg(#pressures)", "unknown tag"),
+            ("#temperatures + 1", "function argument"),
+            ("abs(a, b)", "exactly one"),
+            ("a $ b", "unexpected character"),
+        ] {
+            let err = Expr::parse(input, &tags()).unwrap_err();
+            assert!(err.contains(error), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn lists_inputs_including_tag_members() {
+        let expr = Expr::parse("a + max(#temperatures, {100})", &tags()).unwrap();
+        let mut inputs = Vec::new();
+        expr.inputs(&mut inputs);
+        assert_eq!(inputs, ["a", "t1", "t2", "t3", "100"]);
+    }
+}
@@ -0,0 +1,290 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+pub const EVENT_LOOP: &str = "event_loop";
+pub const MQTT: &str = "mqtt";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentStatus {
+    Up,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComponentHealth {
+    pub status: ComponentStatus,
+    pub critical: bool,
+    pub detail: Option<String>,
+    pub last_success: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_depth: Option<usize>,
+    #[serde(skip)]
+    stale_after_ms: Option<i64>,
+}
+
+// Task state of a supervised service, see core::supervisor
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceState {
+    Running,
+    // Waiting to be restarted after a panic or unexpected return
+    Backoff,
+    // Restart budget exhausted or not restartable
+    Failed,
+    Stopped,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServiceHealth {
+    pub state: ServiceState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub since: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HealthReport {
+    pub status: ComponentStatus,
+    pub timestamp: i64,
+    pub components: BTreeMap<String, ComponentHealth>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServiceHealth>,
+}
+
+// Shared, cheaply clonable registry that components push their status into
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    components: Arc<RwLock<BTreeMap<String, ComponentHealth>>>,
+    services: Arc<RwLock<BTreeMap<String, ServiceHealth>>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Components start as Down until they report their first success
+    pub fn register(&self, name: &str, critical: bool) {
+        self.components.write().unwrap().insert(
+            name.to_string(),
+            ComponentHealth {
+                status: ComponentStatus::Down,
+                critical,
+                detail: Some("starting".into()),
+                last_success: None,
+                queue_depth: None,
+                stale_after_ms: None,
+            },
+        );
+    }
+
+    // For components that no longer run, e.g. the previous source after a mode change
+    pub fn unregister(&self, name: &str) {
+        self.components.write().unwrap().remove(name);
+    }
+
+    // Marks the component degraded when no success was reported within `ms`
+    pub fn set_stale_after(&self, name: &str, ms: u64) {
+        self.update(name, |c| c.stale_after_ms = Some(ms as i64));
+    }
+
+    pub fn mark_success(&self, name: &str) {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.update(name, |c| {
+            c.status = ComponentStatus::Up;
+            c.detail = None;
+            c.last_success = Some(now);
+        });
+    }
+
+    // For successes that do not prove the connection, e.g. a publish handed to the MQTT
+    // client: lifts a degraded status, but a component that is down stays down
+    pub fn mark_recovered(&self, name: &str) {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.update(name, |c| {
+            if c.status != ComponentStatus::Down {
+                c.status = ComponentStatus::Up;
+                c.detail = None;
+            }
+            c.last_success = Some(now);
+        });
+    }
+
+    pub fn mark_up(&self, name: &str) {
+        self.update(name, |c| {
+            c.status = ComponentStatus::Up;
+            c.detail = None;
+        });
+    }
+
+    pub fn mark_degraded(&self, name: &str, detail: impl Into<String>) {
+        let detail = detail.into();
+        self.update(name, |c| {
+            c.status = ComponentStatus::Degraded;
+            c.detail = Some(detail);
+        });
+    }
+
+    pub fn mark_down(&self, name: &str, detail: impl Into<String>) {
+        let detail = detail.into();
+        self.update(name, |c| {
+            c.status = ComponentStatus::Down;
+            c.detail = Some(detail);
+        });
+    }
+
+    pub fn set_queue_depth(&self, name: &str, depth: usize) {
+        self.update(name, |c| c.queue_depth = Some(depth));
+    }
+
+    pub fn set_service(&self, name: &str, service: ServiceHealth) {
+        self.services
+            .write()
+            .unwrap()
+            .insert(name.to_string(), service);
+    }
+
+    pub fn services(&self) -> BTreeMap<String, ServiceHealth> {
+        self.services.read().unwrap().clone()
+    }
+
+    // Liveness only depends on the event loop: without it nothing progresses
+    pub fn liveness(&self) -> HealthReport {
+        let now = chrono::Utc::now().timestamp_millis();
+        let components: BTreeMap<_, _> = self
+            .snapshot(now)
+            .into_iter()
+            .filter(|(name, _)| name == EVENT_LOOP)
+            .collect();
+
+        let status = match components.get(EVENT_LOOP) {
+            Some(c) if c.status == ComponentStatus::Down => ComponentStatus::Down,
+            _ => ComponentStatus::Up,
+        };
+
+        HealthReport {
+            status,
+            timestamp: now,
+            components,
+            services: BTreeMap::new(),
+        }
+    }
+
+    // Not ready while any critical component is down; non-critical ones only degrade.
+    // A service waiting for a restart or given up degrades readiness as well.
+    pub fn readiness(&self) -> HealthReport {
+        let now = chrono::Utc::now().timestamp_millis();
+        let components = self.snapshot(now);
+        let services = self.services();
+
+        let status = components
+            .values()
+            .map(|c| match (c.status, c.critical) {
+                (ComponentStatus::Down, true) => ComponentStatus::Down,
+                (ComponentStatus::Up, _) => ComponentStatus::Up,
+                _ => ComponentStatus::Degraded,
+            })
+            .chain(services.values().map(|s| match s.state {
+                ServiceState::Backoff | ServiceState::Failed => ComponentStatus::Degraded,
+                ServiceState::Running | ServiceState::Stopped => ComponentStatus::Up,
+            }))
+            .fold(ComponentStatus::Up, worst);
+
+        HealthReport {
+            status,
+            timestamp: now,
+            components,
+            services,
+        }
+    }
+
+    fn snapshot(&self, now: i64) -> BTreeMap<String, ComponentHealth> {
+        let mut components = self.components.read().unwrap().clone();
+
+        for c in components.values_mut() {
+            let stale = match (c.stale_after_ms, c.last_success) {
+                (Some(limit), Some(last)) => now - last > limit,
+                _ => false,
+            };
+
+            if stale && c.status == ComponentStatus::Up {
+                c.status = ComponentStatus::Degraded;
+                c.detail = Some("no successful update within expected interval".into());
+            }
+        }
+
+        components
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut ComponentHealth)) {
+        if let Some(c) = self.components.write().unwrap().get_mut(name) {
+            f(c);
+        }
+    }
+}
+
+fn worst(a: ComponentStatus, b: ComponentStatus) -> ComponentStatus {
+    match (a, b) {
+        (ComponentStatus::Down, _) | (_, ComponentStatus::Down) => ComponentStatus::Down,
+        (ComponentStatus::Degraded, _) | (_, ComponentStatus::Degraded) => {
+            ComponentStatus::Degraded
+        }
+        _ => ComponentStatus::Up,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readiness_fails_when_critical_component_is_down() {
+        let health = HealthRegistry::new();
+        health.register(EVENT_LOOP, true);
+        health.register(MQTT, true);
+        health.mark_up(EVENT_LOOP);
+
+        assert_eq!(health.readiness().status, ComponentStatus::Down);
+
+        health.mark_up(MQTT);
+        assert_eq!(health.readiness().status, ComponentStatus::Up);
+    }
+
+    #[test]
+    fn non_critical_component_only_degrades_readiness() {
+        let health = HealthRegistry::new();
+        health.register(EVENT_LOOP, true);
+        health.register("source.simulation", false);
+        health.mark_up(EVENT_LOOP);
+
+        assert_eq!(health.readiness().status, ComponentStatus::Degraded);
+    }
+
+    #[test]
+    fn stale_component_is_reported_degraded() {
+        let health = HealthRegistry::new();
+        health.register("source.modbus", false);
+        health.set_stale_after("source.modbus", 0);
+        health.mark_success("source.modbus");
+
+        let later = chrono::Utc::now().timestamp_millis() + 10;
+        let snapshot = health.snapshot(later);
+
+        assert_eq!(snapshot["source.modbus"].status, ComponentStatus::Degraded);
+    }
+
+    #[test]
+    fn liveness_ignores_sources() {
+        let health = HealthRegistry::new();
+        health.register(EVENT_LOOP, true);
+        health.register("source.modbus", false);
+        health.mark_up(EVENT_LOOP);
+
+        let report = health.liveness();
+        assert_eq!(report.status, ComponentStatus::Up);
+        assert_eq!(report.components.len(), 1);
+    }
+}
@@ -4,6 +4,7 @@ use tokio::sync::broadcast;
 // trait for services that are started and shall be stopped
 #[async_trait]
 pub trait Lifecycle: Send + 'static {
-    // starts service and runs until receiving shutdown signal
+    // starts service and runs until receiving shutdown signal; the work has to happen
+    // inside run, returning earlier counts as a failure and the Supervisor restarts it
     async fn run(self, shutdown: broadcast::Receiver<()>);
 }
@@ -0,0 +1,186 @@
+use crate::config::{ListenerQueueConfig, OverflowPolicy};
+use crate::core::state::StateChange;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::Notify;
+
+#[derive(Debug, PartialEq)]
+pub enum Pushed {
+    Queued,
+    // The queue was full; one change (the oldest or the new one) was dropped
+    Dropped,
+    Closed,
+}
+
+// Bounded FIFO between the dispatcher and one listener task. Unlike an mpsc channel it
+// can drop its oldest entry, for listeners where only recent changes matter.
+pub struct ListenerQueue {
+    inner: Mutex<Inner>,
+    config: ListenerQueueConfig,
+    not_empty: Notify,
+    not_full: Notify,
+}
+
+struct Inner {
+    // With the time they were queued, for the lag metric
+    changes: VecDeque<(StateChange, Instant)>,
+    closed: bool,
+}
+
+impl ListenerQueue {
+    pub fn new(config: ListenerQueueConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                changes: VecDeque::with_capacity(config.capacity.min(1024)),
+                closed: false,
+            }),
+            config,
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+        }
+    }
+
+    // There is a single producer (the event loop), so a stored permit of `not_full`
+    // cannot be taken by anyone else
+    pub async fn push(&self, change: StateChange) -> Pushed {
+        loop {
+            if let Some(pushed) = self.try_push(&change) {
+                return pushed;
+            }
+            self.not_full.notified().await;
+        }
+    }
+
+    // None while full under the block policy
+    fn try_push(&self, change: &StateChange) -> Option<Pushed> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return Some(Pushed::Closed);
+        }
+        let full = inner.changes.len() >= self.config.capacity.max(1);
+        let pushed = match (full, self.config.overflow) {
+            (false, _) => Pushed::Queued,
+            (true, OverflowPolicy::Block) => return None,
+            (true, OverflowPolicy::DropNewest) => return Some(Pushed::Dropped),
+            (true, OverflowPolicy::DropOldest) => {
+                inner.changes.pop_front();
+                Pushed::Dropped
+            }
+        };
+        inner.changes.push_back((change.clone(), Instant::now()));
+        drop(inner);
+        self.not_empty.notify_one();
+        Some(pushed)
+    }
+
+    // The next change and when it was queued; None once closed and empty
+    pub async fn pop(&self) -> Option<(StateChange, Instant)> {
+        loop {
+            if let Some(entry) = self.try_pop() {
+                return entry;
+            }
+            self.not_empty.notified().await;
+        }
+    }
+
+    // None while empty and open
+    fn try_pop(&self) -> Option<Option<(StateChange, Instant)>> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.changes.pop_front() {
+            Some(entry) => {
+                drop(inner);
+                self.not_full.notify_one();
+                Some(Some(entry))
+            }
+            None if inner.closed => Some(None),
+            None => None,
+        }
+    }
+
+    // Rejects new changes; the ones already queued are still handed out by `pop`
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.not_empty.notify_one();
+        self.not_full.notify_one();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::time::{timeout, Duration};
+
+    fn change(n: i64) -> StateChange {
+        StateChange::DeviceUpdated {
+            id: "1".into(),
+            value: n as f64,
+            timestamp: n,
+        }
+    }
+
+    fn queue(overflow: OverflowPolicy) -> ListenerQueue {
+        ListenerQueue::new(ListenerQueueConfig {
+            capacity: 2,
+            overflow,
+        })
+    }
+
+    async fn drain(queue: &ListenerQueue) -> Vec<i64> {
+        queue.close();
+        let mut timestamps = Vec::new();
+        while let Some((change, _)) = queue.pop().await {
+            if let StateChange::DeviceUpdated { timestamp, .. } = change {
+                timestamps.push(timestamp);
+            }
+        }
+        timestamps
+    }
+
+    #[tokio::test]
+    async fn drop_policies_keep_the_oldest_or_newest_changes() {
+        let oldest = queue(OverflowPolicy::DropOldest);
+        let newest = queue(OverflowPolicy::DropNewest);
+        for n in 1..=4 {
+            let expected = if n <= 2 {
+                Pushed::Queued
+            } else {
+                Pushed::Dropped
+            };
+            assert_eq!(oldest.push(change(n)).await, expected);
+            assert_eq!(newest.push(change(n)).await, expected);
+        }
+
+        assert_eq!(drain(&oldest).await, [3, 4]);
+        assert_eq!(drain(&newest).await, [1, 2]);
+        assert_eq!(oldest.push(change(5)).await, Pushed::Closed);
+    }
+
+    #[tokio::test]
+    async fn block_policy_waits_for_the_consumer() {
+        let queue = Arc::new(queue(OverflowPolicy::Block));
+        queue.push(change(1)).await;
+        queue.push(change(2)).await;
+        assert!(
+            timeout(Duration::from_millis(20), queue.push(change(3)))
+                .await
+                .is_err(),
+            "a full queue blocks"
+        );
+
+        let consumer = queue.clone();
+        let popped = tokio::spawn(async move { consumer.pop().await.map(|(c, _)| c) });
+        assert_eq!(queue.push(change(3)).await, Pushed::Queued);
+        assert_eq!(popped.await.unwrap(), Some(change(1)));
+        assert_eq!(drain(&queue).await, [2, 3]);
+    }
+}
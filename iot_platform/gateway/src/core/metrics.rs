@@ -0,0 +1,356 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::sync::Arc;
+
+// Prometheus metrics shared by the event loop, dispatcher, adapters and API
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    events_applied: IntCounterVec,
+    devices: IntGauge,
+    dispatch_duration: HistogramVec,
+    dispatch_failures: IntCounterVec,
+    modbus_poll_duration: HistogramVec,
+    modbus_poll_errors: IntCounterVec,
+    mqtt_enqueued: IntCounterVec,
+    mqtt_acknowledged: IntCounter,
+    mqtt_queue_depth: IntGauge,
+    http_request_duration: HistogramVec,
+    alarm_transitions: IntCounterVec,
+    alarms_open: IntGauge,
+    config_reloads: IntCounterVec,
+    service_restarts: IntCounterVec,
+    listener_queue_depth: IntGaugeVec,
+    listener_lag: HistogramVec,
+    listener_dropped: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("gateway".into()), None).expect("valid metrics namespace");
+
+        let events_applied = IntCounterVec::new(
+            Opts::new("events_applied_total", "Events applied by GatewayState"),
+            &["event"],
+        )
+        .unwrap();
+        let devices = IntGauge::new("devices", "Devices currently held in GatewayState").unwrap();
+        let dispatch_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "dispatch_duration_seconds",
+                "Time a listener takes to handle a state change",
+            )
+            .buckets(vec![
+                0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0,
+            ]),
+            &["listener"],
+        )
+        .unwrap();
+        let dispatch_failures = IntCounterVec::new(
+            Opts::new("dispatch_failures_total", "Listener errors during dispatch"),
+            &["listener"],
+        )
+        .unwrap();
+        let modbus_poll_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "modbus_poll_duration_seconds",
+                "Duration of a Modbus register read per mapping",
+            )
+            .buckets(vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+            ]),
+            &["device_id", "address"],
+        )
+        .unwrap();
+        let modbus_poll_errors = IntCounterVec::new(
+            Opts::new(
+                "modbus_poll_errors_total",
+                "Failed Modbus reads per mapping",
+            ),
+            &["device_id", "address"],
+        )
+        .unwrap();
+        // Handing a message to the client only queues it locally; the broker confirms
+        // QoS 1 publishes with a PUBACK
+        let mqtt_enqueued = IntCounterVec::new(
+            Opts::new(
+                "mqtt_enqueued_total",
+                "Messages handed to the MQTT client by result",
+            ),
+            &["result"],
+        )
+        .unwrap();
+        let mqtt_acknowledged = IntCounter::new(
+            "mqtt_acknowledged_total",
+            "Publishes acknowledged by the broker (PUBACK)",
+        )
+        .unwrap();
+        let mqtt_queue_depth = IntGauge::new(
+            "mqtt_queue_depth",
+            "Messages waiting in the MQTT publisher queue",
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "REST API request latency"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+
+        let alarm_transitions = IntCounterVec::new(
+            Opts::new(
+                "alarm_transitions_total",
+                "Alarm lifecycle transitions by alarm kind and new state",
+            ),
+            &["kind", "state"],
+        )
+        .unwrap();
+        let alarms_open = IntGauge::new("alarms_open", "Active and acknowledged alarms").unwrap();
+        let config_reloads = IntCounterVec::new(
+            Opts::new(
+                "config_reloads_total",
+                "Config reloads by trigger and result",
+            ),
+            &["trigger", "result"],
+        )
+        .unwrap();
+        let listener_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "listener_queue_depth",
+                "State changes waiting in a listener queue (queued dispatch)",
+            ),
+            &["listener"],
+        )
+        .unwrap();
+        let listener_lag = HistogramVec::new(
+            HistogramOpts::new(
+                "listener_lag_seconds",
+                "Time a state change waits in a listener queue before it is handled",
+            )
+            .buckets(vec![
+                0.0001, 0.001, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0,
+            ]),
+            &["listener"],
+        )
+        .unwrap();
+        let listener_dropped = IntCounterVec::new(
+            Opts::new(
+                "listener_dropped_total",
+                "State changes dropped because a listener queue was full",
+            ),
+            &["listener"],
+        )
+        .unwrap();
+        let service_restarts = IntCounterVec::new(
+            Opts::new(
+                "service_restarts_total",
+                "Restarts of supervised services after a panic or unexpected return",
+            ),
+            &["service"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(events_applied.clone())).unwrap();
+        registry.register(Box::new(devices.clone())).unwrap();
+        registry
+            .register(Box::new(dispatch_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(dispatch_failures.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(modbus_poll_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(modbus_poll_errors.clone()))
+            .unwrap();
+        registry.register(Box::new(mqtt_enqueued.clone())).unwrap();
+        registry
+            .register(Box::new(mqtt_acknowledged.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(mqtt_queue_depth.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(alarm_transitions.clone()))
+            .unwrap();
+        registry.register(Box::new(alarms_open.clone())).unwrap();
+        registry.register(Box::new(config_reloads.clone())).unwrap();
+        registry
+            .register(Box::new(service_restarts.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(listener_queue_depth.clone()))
+            .unwrap();
+        registry.register(Box::new(listener_lag.clone())).unwrap();
+        registry
+            .register(Box::new(listener_dropped.clone()))
+            .unwrap();
+
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                events_applied,
+                devices,
+                dispatch_duration,
+                dispatch_failures,
+                modbus_poll_duration,
+                modbus_poll_errors,
+                mqtt_enqueued,
+                mqtt_acknowledged,
+                mqtt_queue_depth,
+                http_request_duration,
+                alarm_transitions,
+                alarms_open,
+                config_reloads,
+                service_restarts,
+                listener_queue_depth,
+                listener_lag,
+                listener_dropped,
+            }),
+        }
+    }
+
+    pub fn event_applied(&self, event: &str, device_count: usize) {
+        self.inner.events_applied.with_label_values(&[event]).inc();
+        self.inner.devices.set(device_count as i64);
+    }
+
+    pub fn observe_dispatch(&self, listener: &str, seconds: f64, failed: bool) {
+        self.inner
+            .dispatch_duration
+            .with_label_values(&[listener])
+            .observe(seconds);
+        if failed {
+            self.inner
+                .dispatch_failures
+                .with_label_values(&[listener])
+                .inc();
+        }
+    }
+
+    pub fn observe_modbus_poll(&self, device_id: &str, address: u16, seconds: f64, failed: bool) {
+        let address = address.to_string();
+        let labels = [device_id, address.as_str()];
+        self.inner
+            .modbus_poll_duration
+            .with_label_values(&labels)
+            .observe(seconds);
+        if failed {
+            self.inner
+                .modbus_poll_errors
+                .with_label_values(&labels)
+                .inc();
+        }
+    }
+
+    pub fn mqtt_enqueued(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.inner.mqtt_enqueued.with_label_values(&[result]).inc();
+    }
+
+    pub fn mqtt_acknowledged(&self) {
+        self.inner.mqtt_acknowledged.inc();
+    }
+
+    pub fn set_mqtt_queue_depth(&self, depth: usize) {
+        self.inner.mqtt_queue_depth.set(depth as i64);
+    }
+
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.inner
+            .http_request_duration
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(seconds);
+    }
+
+    pub fn alarm_transition(&self, kind: &str, state: &str, open: usize) {
+        self.inner
+            .alarm_transitions
+            .with_label_values(&[kind, state])
+            .inc();
+        self.inner.alarms_open.set(open as i64);
+    }
+
+    pub fn config_reload(&self, trigger: &str, success: bool) {
+        let result = if success { "applied" } else { "rejected" };
+        self.inner
+            .config_reloads
+            .with_label_values(&[trigger, result])
+            .inc();
+    }
+
+    pub fn set_listener_queue_depth(&self, listener: &str, depth: usize) {
+        self.inner
+            .listener_queue_depth
+            .with_label_values(&[listener])
+            .set(depth as i64);
+    }
+
+    pub fn observe_listener_lag(&self, listener: &str, seconds: f64) {
+        self.inner
+            .listener_lag
+            .with_label_values(&[listener])
+            .observe(seconds);
+    }
+
+    pub fn listener_dropped(&self, listener: &str) {
+        self.inner
+            .listener_dropped
+            .with_label_values(&[listener])
+            .inc();
+    }
+
+    pub fn service_restarted(&self, service: &str) {
+        self.inner
+            .service_restarts
+            .with_label_values(&[service])
+            .inc();
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.inner.registry.gather(), &mut buffer)
+            .expect("text encoding of metrics cannot fail");
+        String::from_utf8(buffer).expect("metrics are valid UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_contains_namespaced_metrics() {
+        let metrics = Metrics::new();
+        metrics.event_applied("device_updated", 3);
+        metrics.observe_dispatch("mqtt", 0.002, true);
+        metrics.mqtt_enqueued(false);
+        metrics.mqtt_acknowledged();
+
+        let text = metrics.render();
+
+        assert!(text.contains(r#"gateway_events_applied_total{event="device_updated"} 1"#));
+        assert!(text.contains("gateway_devices 3"));
+        assert!(text.contains(r#"gateway_dispatch_failures_total{listener="mqtt"} 1"#));
+        assert!(text.contains(r#"gateway_mqtt_enqueued_total{result="failure"} 1"#));
+        assert!(text.contains("gateway_mqtt_acknowledged_total 1"));
+    }
+}
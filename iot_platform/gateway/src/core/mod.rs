@@ -1,8 +1,18 @@
+pub mod aggregation;
+pub mod alarms;
 pub mod bootstrap;
 pub mod device;
 pub mod dispatcher;
+pub mod event_loop;
 pub mod events;
+pub mod expression;
+pub mod health;
 pub mod lifecycle;
+pub mod listener_queue;
+pub mod metrics;
+pub mod reload;
 pub mod state;
 #[cfg(test)]
 mod state_tests;
+pub mod supervisor;
+pub mod virtual_devices;
@@ -0,0 +1,358 @@
+use crate::config::{Config, ConfigSource, SourceMode};
+use crate::core::{
+    alarms::AlarmEngine, events::GatewayEvent, lifecycle::Lifecycle, metrics::Metrics,
+    virtual_devices::VirtualDevices,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::time::SystemTime;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+// Starts and stops the data source selected by `mode`; implemented in adapters::sources
+pub trait SourceControl: Send + Sync + 'static {
+    // Stops the running source, if any, before starting the configured one
+    fn start(&mut self, config: &Config);
+    fn stop(&mut self);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReloadTrigger {
+    Signal,
+    FileChanged,
+    Api,
+}
+
+impl ReloadTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReloadTrigger::Signal => "signal",
+            ReloadTrigger::FileChanged => "file",
+            ReloadTrigger::Api => "api",
+        }
+    }
+}
+
+// What a reload changes in the running gateway
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigDiff {
+    pub source_restarted: bool,
+    pub devices_added: Vec<String>,
+    pub devices_removed: Vec<String>,
+    // Alarm rules, virtual devices or tags changed
+    pub rules_replaced: bool,
+    // Changed sections that are only read at startup
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigDiff {
+    pub fn between(old: &Config, new: &Config) -> Self {
+        let source_restarted = Self::source_changed(old, new);
+
+        let old_ids = old.device_ids();
+        let new_ids = new.device_ids();
+        let devices_added = new_ids
+            .iter()
+            .filter(|id| !old_ids.contains(id))
+            .cloned()
+            .collect();
+        let devices_removed = old_ids
+            .iter()
+            .filter(|id| !new_ids.contains(id))
+            .cloned()
+            .collect();
+
+        Self {
+            source_restarted,
+            devices_added,
+            devices_removed,
+            rules_replaced: old.alarms.rules != new.alarms.rules
+                || old.virtual_devices != new.virtual_devices
+                || old.tags != new.tags,
+            restart_required: Self::pending_restart(old, new),
+        }
+    }
+
+    // Compared against the config the process started with, so a section stays listed
+    // across later reloads until the gateway is restarted
+    // Mode or the section of the selected source differ
+    pub fn source_changed(old: &Config, new: &Config) -> bool {
+        old.mode != new.mode
+            || match new.mode {
+                SourceMode::Modbus => old.modbus != new.modbus,
+                SourceMode::Simulation => old.simulation != new.simulation,
+            }
+    }
+
+    pub fn pending_restart(started: &Config, new: &Config) -> Vec<String> {
+        let needs_timer =
+            |config: &Config| AlarmEngine::new(config.alarms.rules.clone()).needs_timer();
+        [
+            ("gateway_id", started.gateway_id != new.gateway_id),
+            ("gateway_name", started.gateway_name != new.gateway_name),
+            ("api", started.api != new.api),
+            ("auth", started.auth != new.auth),
+            ("mqtt", started.mqtt != new.mqtt),
+            ("aggregation", started.aggregation != new.aggregation),
+            (
+                "alarms.check_interval_ms",
+                started.alarms.check_interval_ms != new.alarms.check_interval_ms,
+            ),
+            // The alarm timer only runs when the startup rules needed it
+            (
+                "alarms.rules (stale_ms / delay_ms need the alarm timer)",
+                !needs_timer(started) && needs_timer(new),
+            ),
+            ("reload", started.reload != new.reload),
+            ("supervisor", started.supervisor != new.supervisor),
+            ("dispatch", started.dispatch != new.dispatch),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(section, _)| section.to_string())
+        .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.source_restarted
+            && self.devices_added.is_empty()
+            && self.devices_removed.is_empty()
+            && !self.rules_replaced
+    }
+}
+
+pub struct ReloadRequest {
+    trigger: ReloadTrigger,
+    reply: oneshot::Sender<Result<ConfigDiff, String>>,
+}
+
+// Requests a reload from the running ConfigReloader (API, signal handler)
+#[derive(Clone)]
+pub struct ReloadHandle {
+    tx: mpsc::Sender<ReloadRequest>,
+}
+
+impl ReloadHandle {
+    pub async fn reload(&self, trigger: ReloadTrigger) -> Result<ConfigDiff, String> {
+        let (reply, result) = oneshot::channel();
+        self.tx
+            .send(ReloadRequest { trigger, reply })
+            .await
+            .map_err(|_| "config reloader is not running".to_string())?;
+        result
+            .await
+            .map_err(|_| "config reloader stopped during the reload".to_string())?
+    }
+}
+
+// Re-reads the config layers on request or when a file changes and applies the difference
+// to the running gateway: devices through GatewayEvents, alarm rules and virtual
+// devices through RulesReplaced, and a source restart when its section changed.
+// Invalid configs are rejected and the running config stays in place.
+pub struct ConfigReloader {
+    source: ConfigSource,
+    started: Config,
+    current: Config,
+    // What the running source was started with; ahead of `current` when a reload
+    // restarted the source but failed to send the device changes
+    source_config: Config,
+    modified: Option<SystemTime>,
+    tx: mpsc::Sender<GatewayEvent>,
+    sources: Box<dyn SourceControl>,
+    metrics: Metrics,
+    requests: mpsc::Receiver<ReloadRequest>,
+}
+
+impl ConfigReloader {
+    // `config` is the running config; the source in `sources` is already started with it
+    pub fn new(
+        source: ConfigSource,
+        config: Config,
+        tx: mpsc::Sender<GatewayEvent>,
+        sources: Box<dyn SourceControl>,
+        metrics: Metrics,
+    ) -> (Self, ReloadHandle) {
+        let (requests_tx, requests) = mpsc::channel(4);
+        let reloader = Self {
+            modified: source.modified(),
+            source,
+            started: config.clone(),
+            source_config: config.clone(),
+            current: config,
+            tx,
+            sources,
+            metrics,
+            requests,
+        };
+        (reloader, ReloadHandle { tx: requests_tx })
+    }
+
+    async fn reload(&mut self, trigger: ReloadTrigger) -> Result<ConfigDiff, String> {
+        info!(
+            "Reloading config from {} ({})",
+            self.source.path.display(),
+            trigger.as_str()
+        );
+        let result = self.apply().await;
+        self.metrics.config_reload(trigger.as_str(), result.is_ok());
+
+        match &result {
+            Ok(diff) if diff.is_empty() => info!("Config reloaded, nothing to apply"),
+            Ok(diff) => info!("Config reloaded: {:?}", diff),
+            Err(e) => error!("Config rejected, keeping the running config: {}", e),
+        }
+        if let Ok(diff) = &result {
+            for section in &diff.restart_required {
+                warn!(
+                    "Config section {} changed, takes effect after a restart",
+                    section
+                );
+            }
+        }
+        result
+    }
+
+    async fn apply(&mut self) -> Result<ConfigDiff, String> {
+        // Remembered before reading so a rejected file is retried only once it changes again
+        self.modified = self.source.modified();
+        let new = self.source.load().map_err(|e| e.to_string())?;
+        let virtual_devices = VirtualDevices::new(&new.virtual_devices, &new.tags)?;
+
+        let diff = ConfigDiff {
+            source_restarted: ConfigDiff::source_changed(&self.source_config, &new),
+            restart_required: ConfigDiff::pending_restart(&self.started, &new),
+            ..ConfigDiff::between(&self.current, &new)
+        };
+        let timestamp = chrono::Utc::now().timestamp_millis();
+
+        // The old source is stopped first so it cannot recreate removed devices
+        if diff.source_restarted {
+            self.sources.start(&new);
+            self.source_config = new.clone();
+        }
+        // Rules before devices: a removed virtual device is then removed like any other
+        if diff.rules_replaced {
+            self.send(GatewayEvent::RulesReplaced {
+                alarm_rules: new.alarms.rules.clone(),
+                virtual_devices,
+                timestamp,
+            })
+            .await?;
+        }
+        for id in &diff.devices_removed {
+            self.send(GatewayEvent::DeviceRemoved {
+                id: id.clone(),
+                timestamp,
+            })
+            .await?;
+        }
+        for id in &diff.devices_added {
+            self.send(GatewayEvent::DeviceCreated {
+                id: id.clone(),
+                timestamp,
+            })
+            .await?;
+        }
+
+        self.current = new;
+        Ok(diff)
+    }
+
+    async fn send(&self, event: GatewayEvent) -> Result<(), String> {
+        self.tx
+            .send(event)
+            .await
+            .map_err(|_| "event loop is not running".to_string())
+    }
+}
+
+#[async_trait]
+impl Lifecycle for ConfigReloader {
+    async fn run(mut self, mut shutdown: broadcast::Receiver<()>) {
+        let watch = self.started.reload.watch;
+        let mut poll = tokio::time::interval(Duration::from_millis(
+            self.started.reload.poll_interval_ms.max(100),
+        ));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.recv() => break,
+                Some(request) = self.requests.recv() => {
+                    let result = self.reload(request.trigger).await;
+                    let _ = request.reply.send(result);
+                }
+                _ = poll.tick(), if watch => {
+                    if self.source.modified() != self.modified {
+                        let _ = self.reload(ReloadTrigger::FileChanged).await;
+                    }
+                }
+            }
+        }
+
+        self.sources.stop();
+        info!("Config reloader stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AlarmRule, RegisterMapping, VirtualDeviceConfig};
+
+    fn register(device_id: &str, address: u16) -> RegisterMapping {
+        RegisterMapping {
+            address,
+            count: 1,
+            device_id: device_id.into(),
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn diff_lists_devices_and_what_needs_a_restart() {
+        let mut old = Config {
+            mode: SourceMode::Modbus,
+            ..Config::default()
+        };
+        old.modbus.registers = vec![register("1", 0), register("2", 5)];
+
+        let mut new = old.clone();
+        new.modbus.registers = vec![register("2", 5), register("3", 7)];
+        new.virtual_devices.push(VirtualDeviceConfig {
+            id: "sum".into(),
+            expression: "{2} + {3}".into(),
+        });
+        new.api.port = 9090;
+
+        let diff = ConfigDiff::between(&old, &new);
+        assert!(diff.source_restarted);
+        assert!(diff.rules_replaced);
+        assert_eq!(diff.devices_added, ["3", "sum"]);
+        assert_eq!(diff.devices_removed, ["1"]);
+        assert_eq!(diff.restart_required, ["api"]);
+
+        assert!(ConfigDiff::between(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn inactive_source_sections_and_timer_needs() {
+        let old = Config::default();
+        let mut new = old.clone();
+        // Simulation mode: Modbus registers are not in use
+        new.modbus.registers.clear();
+        new.alarms.rules.push(AlarmRule {
+            device_id: "1".into(),
+            stale_ms: Some(5000),
+            ..Default::default()
+        });
+
+        let diff = ConfigDiff::between(&old, &new);
+        assert!(!diff.source_restarted);
+        assert!(diff.devices_added.is_empty() && diff.devices_removed.is_empty());
+        assert_eq!(
+            diff.restart_required,
+            ["alarms.rules (stale_ms / delay_ms need the alarm timer)"]
+        );
+    }
+}
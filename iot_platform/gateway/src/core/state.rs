@@ -1,7 +1,16 @@
-use crate::core::{device::Device, events::GatewayEvent};
+use crate::config::AlarmRule;
+use crate::core::{
+    alarms::{Alarm, AlarmEngine},
+    device::Device,
+    events::GatewayEvent,
+    health::HealthRegistry,
+    metrics::Metrics,
+    reload::ReloadHandle,
+    virtual_devices::VirtualDevices,
+};
 use std::sync::Arc;
 use tokio::sync::{mpsc::Sender, Mutex};
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StateChange {
@@ -18,12 +27,20 @@ pub enum StateChange {
         id: String,
         timestamp: i64,
     },
+    AlarmRaised(Alarm),
+    AlarmAcknowledged(Alarm),
+    AlarmCleared(Alarm),
 }
 
 #[async_trait::async_trait]
 pub trait StateListener: Send + Sync + 'static {
     // Methods return Result to allow for centralized error reporting
     async fn on_event(&self, event: StateChange) -> Result<(), ListenerError>;
+
+    // Used as label in dispatch metrics
+    fn name(&self) -> &str {
+        "listener"
+    }
 }
 
 use std::fmt;
@@ -31,11 +48,107 @@ use std::fmt;
 #[derive(Debug, Default, Clone)]
 pub struct GatewayState {
     pub devices: Vec<Device>,
+    pub alarms: AlarmEngine,
+    pub virtual_devices: VirtualDevices,
 }
 
 impl GatewayState {
     pub fn new() -> Self {
-        Self { devices: vec![] }
+        Self::default()
+    }
+
+    pub fn with_alarms(rules: Vec<AlarmRule>) -> Self {
+        Self {
+            alarms: AlarmEngine::new(rules),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_virtual_devices(mut self, virtual_devices: VirtualDevices) -> Self {
+        self.virtual_devices = virtual_devices;
+        self
+    }
+
+    // Applies an event and returns every resulting change in dispatch order: the device
+    // change first, then the alarm transitions it caused, then the virtual devices it
+    // updated (each followed by its own alarm transitions)
+    pub fn apply(&mut self, ev: GatewayEvent) -> Vec<StateChange> {
+        match ev {
+            GatewayEvent::AlarmAcknowledged {
+                alarm_id,
+                by,
+                timestamp,
+            } => self
+                .alarms
+                .acknowledge(&alarm_id, &by, timestamp)
+                .into_iter()
+                .collect(),
+            GatewayEvent::AlarmTimer { timestamp } => self.alarms.on_timer(timestamp),
+            // New virtual devices get their first value once an input changes
+            GatewayEvent::RulesReplaced {
+                alarm_rules,
+                virtual_devices,
+                timestamp,
+            } => {
+                self.virtual_devices = virtual_devices;
+                self.alarms.replace_rules(alarm_rules, timestamp)
+            }
+            GatewayEvent::DeviceValueObserved { ref id, .. }
+            | GatewayEvent::DeviceRemoved { ref id, .. }
+                if self.virtual_devices.contains(id) =>
+            {
+                warn!(
+                    "Ignoring {} for virtual device {}, its value is computed",
+                    ev.kind(),
+                    id
+                );
+                Vec::new()
+            }
+            ev => {
+                let Some(change) = self.apply_event(ev) else {
+                    return Vec::new();
+                };
+                let mut changes = Vec::new();
+                let inputs = match &change {
+                    StateChange::DeviceUpdated { id, timestamp, .. }
+                    | StateChange::DeviceRemoved { id, timestamp } => {
+                        Some((id.clone(), *timestamp))
+                    }
+                    _ => None,
+                };
+                self.push_with_alarms(change, &mut changes);
+
+                if let Some((id, timestamp)) = inputs {
+                    for (id, value) in self.recompute_virtual(&id) {
+                        let ev = GatewayEvent::DeviceValueObserved {
+                            id,
+                            value,
+                            timestamp,
+                        };
+                        if let Some(change) = self.apply_event(ev) {
+                            self.push_with_alarms(change, &mut changes);
+                        }
+                    }
+                }
+                changes
+            }
+        }
+    }
+
+    fn push_with_alarms(&mut self, change: StateChange, changes: &mut Vec<StateChange>) {
+        let alarms = self.alarms.on_change(&change);
+        changes.push(change);
+        changes.extend(alarms);
+    }
+
+    fn recompute_virtual(&self, changed: &str) -> Vec<(String, f64)> {
+        let current = |id: &str| {
+            self.devices
+                .iter()
+                .find(|d| d.id == id)
+                .and_then(|d| d.value)
+        };
+        self.virtual_devices.recompute(changed, &current)
     }
 
     pub fn apply_event(&mut self, ev: GatewayEvent) -> Option<StateChange> {
@@ -81,7 +194,7 @@ impl GatewayState {
                 if let Some(device) = dev {
                     device.timestamp = timestamp;
 
-                    return Some(StateChange::DeviceCreated { id, timestamp });
+                    Some(StateChange::DeviceCreated { id, timestamp })
                 } else {
                     self.devices.push(Device {
                         id: id.clone(),
@@ -89,12 +202,16 @@ impl GatewayState {
                         timestamp,
                     });
 
-                    return Some(StateChange::DeviceCreated {
+                    Some(StateChange::DeviceCreated {
                         id: id.clone(),
                         timestamp,
-                    });
+                    })
                 }
             }
+            // Alarm and rule events only concern the alarm engine, see apply
+            GatewayEvent::AlarmAcknowledged { .. }
+            | GatewayEvent::AlarmTimer { .. }
+            | GatewayEvent::RulesReplaced { .. } => None,
         }
     }
 }
@@ -103,6 +220,10 @@ impl GatewayState {
 pub struct AppState {
     pub tx: Sender<GatewayEvent>,
     pub state: Arc<Mutex<GatewayState>>,
+    pub health: HealthRegistry,
+    pub metrics: Metrics,
+    // None when the gateway runs without a config reloader (tests)
+    pub reload: Option<ReloadHandle>,
 }
 
 #[derive(Debug)]
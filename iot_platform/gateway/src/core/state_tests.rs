@@ -1,9 +1,12 @@
 #[cfg(test)]
 mod tests {
 
+    use crate::config::{AlarmRule, VirtualDeviceConfig};
     use crate::core::events::GatewayEvent;
     use crate::core::state::{GatewayState, StateChange};
+    use crate::core::virtual_devices::VirtualDevices;
     use chrono::Utc;
+    use std::collections::BTreeMap;
 
     #[tokio::test]
     async fn test_device_created_applies_timestamp() {
@@ -86,4 +89,49 @@ mod tests {
         );
         assert!(state.devices.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_virtual_device_follows_its_inputs() {
+        let virtual_devices = VirtualDevices::new(
+            &[VirtualDeviceConfig {
+                id: "power".to_string(),
+                expression: "voltage * current".to_string(),
+            }],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        let mut state = GatewayState::with_alarms(vec![AlarmRule {
+            device_id: "power".to_string(),
+            hi: Some(500.0),
+            ..Default::default()
+        }])
+        .with_virtual_devices(virtual_devices);
+
+        let observe = |id: &str, value: f64, timestamp: i64| GatewayEvent::DeviceValueObserved {
+            id: id.to_string(),
+            value,
+            timestamp,
+        };
+
+        // No value until every input has one
+        assert_eq!(state.apply(observe("voltage", 230.0, 1)).len(), 1);
+
+        let changes = state.apply(observe("current", 3.0, 2));
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes[1],
+            StateChange::DeviceUpdated {
+                id: "power".to_string(),
+                value: 690.0,
+                timestamp: 2
+            }
+        );
+        // Alarms apply to virtual devices like to any other device
+        assert!(matches!(&changes[2], StateChange::AlarmRaised(a) if a.device_id == "power"));
+
+        // Values for a virtual device from outside are ignored
+        assert!(state.apply(observe("power", 1.0, 3)).is_empty());
+        let power = state.devices.iter().find(|d| d.id == "power").unwrap();
+        assert_eq!(power.value, Some(690.0));
+    }
 }
@@ -0,0 +1,396 @@
+use crate::config::{RestartPolicy, SupervisorConfig};
+use crate::core::{
+    health::{HealthRegistry, ServiceHealth, ServiceState},
+    lifecycle::Lifecycle,
+    metrics::Metrics,
+};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// Supervising task per service name
+type Tasks = Vec<(String, JoinHandle<()>)>;
+
+// Owns the tasks of all Lifecycle services. A service that panics or returns before its
+// stop signal is rebuilt and restarted according to its RestartPolicy, and its state is
+// published to the health registry (`services` in /health/ready).
+#[derive(Clone)]
+pub struct Supervisor {
+    config: Arc<SupervisorConfig>,
+    health: HealthRegistry,
+    metrics: Metrics,
+    tasks: Arc<Mutex<Tasks>>,
+    // Latest spawn per name, so a replaced service (e.g. the source after a reload)
+    // cannot overwrite the state of its successor when it stops
+    generations: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig, health: HealthRegistry, metrics: Metrics) -> Self {
+        Self {
+            config: Arc::new(config),
+            health,
+            metrics,
+            tasks: Arc::default(),
+            generations: Arc::default(),
+        }
+    }
+
+    // A supervisor for the next shutdown phase: same policies, health and metrics, but
+    // its own tasks, so `shutdown` waits only for the services spawned on it
+    pub fn stage(&self) -> Self {
+        Self {
+            tasks: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    // Runs the service built by `factory` until `stop` fires; every restart builds a new one
+    pub fn spawn<T, F>(&self, name: &str, stop: broadcast::Receiver<()>, mut factory: F)
+    where
+        T: Lifecycle,
+        F: FnMut() -> T + Send + 'static,
+    {
+        let policy = self.config.policy(name).clone();
+        self.supervise(name, policy, stop, move || Some(factory()));
+    }
+
+    // For services that own state handed out at startup (channels, handles) and cannot
+    // be rebuilt: a panic or early return is reported as failed instead of restarted
+    pub fn spawn_once<T: Lifecycle>(&self, name: &str, stop: broadcast::Receiver<()>, service: T) {
+        let policy = RestartPolicy {
+            max_restarts: 0,
+            ..self.config.policy(name).clone()
+        };
+        let mut service = Some(service);
+        self.supervise(name, policy, stop, move || service.take());
+    }
+
+    // Waits until every service has stopped after the stop signals were sent. Services
+    // still running after `shutdown_timeout_ms` are aborted.
+    pub async fn shutdown(&self) {
+        let deadline = Instant::now() + Duration::from_millis(self.config.shutdown_timeout_ms);
+        let mut stopped = Vec::new();
+        let mut aborted = Vec::new();
+        loop {
+            // Services spawned while waiting (a source restarted by a reload) are
+            // picked up in the next round
+            let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+            if tasks.is_empty() {
+                break;
+            }
+            for (name, mut task) in tasks {
+                if tokio::time::timeout_at(deadline, &mut task).await.is_ok() {
+                    stopped.push(name);
+                } else {
+                    task.abort();
+                    aborted.push(name);
+                }
+            }
+        }
+
+        if aborted.is_empty() && !stopped.is_empty() {
+            info!("Services stopped: {}", stopped.join(", "));
+        } else if !aborted.is_empty() {
+            warn!(
+                "Services did not stop within {} ms and were aborted: {}",
+                self.config.shutdown_timeout_ms,
+                aborted.join(", ")
+            );
+        }
+    }
+
+    fn supervise<T, F>(
+        &self,
+        name: &str,
+        policy: RestartPolicy,
+        stop: broadcast::Receiver<()>,
+        factory: F,
+    ) where
+        T: Lifecycle,
+        F: FnMut() -> Option<T> + Send + 'static,
+    {
+        let generation = {
+            let mut generations = self.generations.lock().unwrap();
+            let generation = generations.get(name).map_or(0, |g| g + 1);
+            generations.insert(name.to_string(), generation);
+            generation
+        };
+        let service = Service {
+            name: name.to_string(),
+            generation,
+            supervisor: self.clone(),
+            restarts: 0,
+            last_error: None,
+        };
+        let task = tokio::spawn(service.run(policy, stop, factory));
+
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|(_, t)| !t.is_finished());
+        tasks.push((name.to_string(), task));
+    }
+}
+
+// Supervision state of one spawned service
+struct Service {
+    name: String,
+    generation: u64,
+    supervisor: Supervisor,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+impl Service {
+    async fn run<T, F>(
+        mut self,
+        policy: RestartPolicy,
+        mut stop: broadcast::Receiver<()>,
+        mut factory: F,
+    ) where
+        T: Lifecycle,
+        F: FnMut() -> Option<T>,
+    {
+        // Restarts since the last run that lasted `reset_after_ms`
+        let mut consecutive = 0;
+
+        loop {
+            // Subscribed before checking, so a stop signal reaches this loop or the new run
+            let service_stop = stop.resubscribe();
+            if stopped(&mut stop) {
+                break;
+            }
+            let Some(service) = factory() else {
+                self.report(ServiceState::Failed);
+                return;
+            };
+
+            self.report(ServiceState::Running);
+            let started = Instant::now();
+            // Aborted with this task, e.g. when shutdown times out
+            let mut task = AbortOnDrop(tokio::spawn(service.run(service_stop)));
+            let error = tokio::select! {
+                result = &mut task.0 => match result {
+                    Ok(()) if stopped(&mut stop) => break,
+                    Ok(()) => "returned before shutdown".to_string(),
+                    Err(e) if e.is_panic() => format!("panicked: {}", panic_message(e.into_panic())),
+                    Err(e) => e.to_string(),
+                },
+                _ = stop.recv() => {
+                    let _ = (&mut task.0).await;
+                    break;
+                }
+            };
+            self.last_error = Some(error.clone());
+
+            if started.elapsed() >= Duration::from_millis(policy.reset_after_ms) {
+                consecutive = 0;
+            }
+            if consecutive >= policy.max_restarts {
+                error!(
+                    "Service {} {}, giving up after {} restarts",
+                    self.name, error, consecutive
+                );
+                self.report(ServiceState::Failed);
+                return;
+            }
+
+            let delay = backoff(&policy, consecutive);
+            consecutive += 1;
+            warn!(
+                "Service {} {}, restarting in {} ms",
+                self.name,
+                error,
+                delay.as_millis()
+            );
+            self.report(ServiceState::Backoff);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = stop.recv() => break,
+            }
+            self.restarts += 1;
+            self.supervisor.metrics.service_restarted(&self.name);
+        }
+
+        self.report(ServiceState::Stopped);
+    }
+
+    fn report(&self, state: ServiceState) {
+        let generations = self.supervisor.generations.lock().unwrap();
+        if generations.get(&self.name) != Some(&self.generation) {
+            return;
+        }
+        self.supervisor.health.set_service(
+            &self.name,
+            ServiceHealth {
+                state,
+                restarts: self.restarts,
+                last_error: self.last_error.clone(),
+                since: chrono::Utc::now().timestamp_millis(),
+            },
+        );
+    }
+}
+
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+// A closed or lagged channel counts as stopped, like `shutdown.recv()` in the services
+fn stopped(stop: &mut broadcast::Receiver<()>) -> bool {
+    !matches!(stop.try_recv(), Err(TryRecvError::Empty))
+}
+
+// Doubles per consecutive restart, capped at `max_backoff_ms`
+fn backoff(policy: &RestartPolicy, consecutive: u32) -> Duration {
+    let factor = 2u64.saturating_pow(consecutive);
+    Duration::from_millis(
+        policy
+            .initial_backoff_ms
+            .saturating_mul(factor)
+            .min(policy.max_backoff_ms),
+    )
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "unknown panic".to_string(), |m| m.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::health::ComponentStatus;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Panics on its first `failures` runs, then runs until stopped
+    struct Flaky {
+        runs: Arc<AtomicU32>,
+        failures: u32,
+    }
+
+    #[async_trait]
+    impl Lifecycle for Flaky {
+        async fn run(self, mut shutdown: broadcast::Receiver<()>) {
+            if self.runs.fetch_add(1, Ordering::SeqCst) < self.failures {
+                panic!("flaky service failed");
+            }
+            let _ = shutdown.recv().await;
+        }
+    }
+
+    fn supervisor(max_restarts: u32) -> (Supervisor, HealthRegistry) {
+        let health = HealthRegistry::new();
+        let config = SupervisorConfig {
+            shutdown_timeout_ms: 1000,
+            restart: RestartPolicy {
+                initial_backoff_ms: 1,
+                max_backoff_ms: 5,
+                max_restarts,
+                reset_after_ms: 60_000,
+            },
+            ..SupervisorConfig::default()
+        };
+        (
+            Supervisor::new(config, health.clone(), Metrics::new()),
+            health,
+        )
+    }
+
+    async fn wait_for(
+        health: &HealthRegistry,
+        name: &str,
+        done: impl Fn(&ServiceHealth) -> bool,
+    ) -> ServiceHealth {
+        for _ in 0..200 {
+            if let Some(service) = health.services().get(name).filter(|s| done(s)) {
+                return service.clone();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("{} never got there: {:?}", name, health.services());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let policy = RestartPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            ..RestartPolicy::default()
+        };
+        let delays: Vec<u128> = (0..6).map(|n| backoff(&policy, n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(backoff(&policy, 200).as_millis(), 1000);
+    }
+
+    #[tokio::test]
+    async fn panicking_service_is_restarted_and_stops_on_shutdown() {
+        let (supervisor, health) = supervisor(5);
+        let (stop, _) = broadcast::channel(1);
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        supervisor.spawn("flaky", stop.subscribe(), move || Flaky {
+            runs: counter.clone(),
+            failures: 2,
+        });
+
+        let service = wait_for(&health, "flaky", |s| {
+            s.state == ServiceState::Running && s.restarts == 2
+        })
+        .await;
+        assert_eq!(
+            service.last_error.as_deref(),
+            Some("panicked: flaky service failed")
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        stop.send(()).unwrap();
+        supervisor.shutdown().await;
+        assert_eq!(health.services()["flaky"].state, ServiceState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn service_fails_when_restarts_are_exhausted() {
+        let (supervisor, health) = supervisor(1);
+        let (stop, _) = broadcast::channel(1);
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        supervisor.spawn("broken", stop.subscribe(), move || Flaky {
+            runs: counter.clone(),
+            failures: u32::MAX,
+        });
+        // Not restartable at all
+        supervisor.spawn_once(
+            "once",
+            stop.subscribe(),
+            Flaky {
+                runs: Arc::new(AtomicU32::new(0)),
+                failures: 1,
+            },
+        );
+
+        let broken = wait_for(&health, "broken", |s| s.state == ServiceState::Failed).await;
+        assert_eq!(broken.restarts, 1);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let once = wait_for(&health, "once", |s| s.state == ServiceState::Failed).await;
+        assert_eq!(once.restarts, 0);
+        assert_eq!(health.readiness().status, ComponentStatus::Degraded);
+
+        // Failed services hold no receivers any more
+        let _ = stop.send(());
+        supervisor.shutdown().await;
+        assert_eq!(health.services()["broken"].state, ServiceState::Failed);
+    }
+}
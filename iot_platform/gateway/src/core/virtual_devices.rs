@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::config::VirtualDeviceConfig;
+use crate::core::expression::Expr;
+
+#[derive(Debug, Clone)]
+struct VirtualDevice {
+    id: String,
+    expression: Expr,
+    inputs: HashSet<String>,
+}
+
+// Devices whose value is computed from other devices. Recomputed inside the event loop
+// whenever one of their inputs changes; virtual devices may read other virtual devices.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualDevices {
+    // Ordered so every device comes after the virtual devices it reads
+    devices: Vec<VirtualDevice>,
+}
+
+impl VirtualDevices {
+    // Refuses duplicate ids, invalid expressions and cycles between virtual devices
+    pub fn new(
+        configs: &[VirtualDeviceConfig],
+        tags: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self, String> {
+        let mut pending: Vec<VirtualDevice> = Vec::new();
+        for config in configs {
+            if pending.iter().any(|d| d.id == config.id) {
+                return Err(format!("virtual device {} is defined twice", config.id));
+            }
+            let expression = Expr::parse(&config.expression, tags)
+                .map_err(|e| format!("virtual device {}: {}", config.id, e))?;
+            let mut inputs = Vec::new();
+            expression.inputs(&mut inputs);
+            pending.push(VirtualDevice {
+                id: config.id.clone(),
+                expression,
+                inputs: inputs.into_iter().collect(),
+            });
+        }
+
+        let ids: HashSet<String> = pending.iter().map(|d| d.id.clone()).collect();
+        let mut devices = Vec::new();
+        let mut resolved = HashSet::new();
+        while !pending.is_empty() {
+            let (ready, blocked): (Vec<_>, Vec<_>) = pending.into_iter().partition(|d| {
+                d.inputs
+                    .iter()
+                    .all(|i| !ids.contains(i) || resolved.contains(i))
+            });
+            if ready.is_empty() {
+                let mut cycle: Vec<&str> = blocked.iter().map(|d| d.id.as_str()).collect();
+                cycle.sort();
+                return Err(format!(
+                    "virtual devices depend on each other: {}",
+                    cycle.join(", ")
+                ));
+            }
+            resolved.extend(ready.iter().map(|d| d.id.clone()));
+            devices.extend(ready);
+            pending = blocked;
+        }
+
+        Ok(Self { devices })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.devices.iter().any(|d| d.id == id)
+    }
+
+    // New values of the virtual devices affected by a change of `changed`, in dependency
+    // order. Devices whose value is unavailable or not finite are left out.
+    pub fn recompute(
+        &self,
+        changed: &str,
+        current: &dyn Fn(&str) -> Option<f64>,
+    ) -> Vec<(String, f64)> {
+        let mut dirty = HashSet::from([changed.to_string()]);
+        let mut computed: HashMap<String, f64> = HashMap::new();
+        let mut updates = Vec::new();
+
+        for device in &self.devices {
+            if device.inputs.is_disjoint(&dirty) {
+                continue;
+            }
+            let value = device
+                .expression
+                .eval(&|id| computed.get(id).copied().or_else(|| current(id)));
+            if let Some(value) = value.filter(|v| v.is_finite()) {
+                computed.insert(device.id.clone(), value);
+                dirty.insert(device.id.clone());
+                updates.push((device.id.clone(), value));
+            }
+        }
+
+        updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(id: &str, expression: &str) -> VirtualDeviceConfig {
+        VirtualDeviceConfig {
+            id: id.into(),
+            expression: expression.into(),
+        }
+    }
+
+    #[test]
+    fn recomputes_dependents_in_order() {
+        // Declared before its input to check the ordering
+        let devices = VirtualDevices::new(
+            &[
+                config("energy_cost", "power * 0.3"),
+                config("power", "voltage * current * 0.001"),
+                config("unrelated", "pressure + 1"),
+            ],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        let values = HashMap::from([("voltage", 250.0), ("current", 4.0)]);
+
+        let updates = devices.recompute("current", &|id| values.get(id).copied());
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0], ("power".to_string(), 1.0));
+        assert_eq!(updates[1].0, "energy_cost");
+        assert!((updates[1].1 - 0.3).abs() < 1e-9);
+
+        // Division by zero is not published
+        let devices = VirtualDevices::new(&[config("ratio", "a / b")], &BTreeMap::new()).unwrap();
+        let values = HashMap::from([("a", 1.0), ("b", 0.0)]);
+        assert!(devices
+            .recompute("b", &|id| values.get(id).copied())
+            .is_empty());
+    }
+
+    #[test]
+    fn refuses_cycles_and_duplicates() {
+        let err = VirtualDevices::new(
+            &[config("a", "b + 1"), config("b", "a + 1"), config("c", "x")],
+            &BTreeMap::new(),
+        )
+        .unwrap_err();
+        assert!(err.contains("depend on each other: a, b"), "{}", err);
+
+        let err = VirtualDevices::new(&[config("a", "a * 2")], &BTreeMap::new()).unwrap_err();
+        assert!(err.contains("a"), "{}", err);
+
+        let err = VirtualDevices::new(&[config("a", "x"), config("a", "y")], &BTreeMap::new())
+            .unwrap_err();
+        assert!(err.contains("defined twice"), "{}", err);
+    }
+}
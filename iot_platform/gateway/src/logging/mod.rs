@@ -1,6 +1,6 @@
 use crate::core::state::{ListenerError, StateChange, StateListener};
 use async_trait::async_trait;
-use tracing::info;
+use tracing::{info, warn};
 
 pub struct ConsoleLogger {
     device_name: String,
@@ -34,8 +34,33 @@ impl StateListener for ConsoleLogger {
             StateChange::DeviceRemoved { id, .. } => {
                 info!("{}: Device {id} was removed", self.device_name);
             }
+            StateChange::AlarmRaised(alarm) => {
+                warn!(
+                    "{}: Alarm {} ({}) raised for device {}: {}",
+                    self.device_name,
+                    alarm.id,
+                    alarm.severity.as_str(),
+                    alarm.device_id,
+                    alarm.message
+                );
+            }
+            StateChange::AlarmAcknowledged(alarm) => {
+                info!(
+                    "{}: Alarm {} acknowledged by {}",
+                    self.device_name,
+                    alarm.id,
+                    alarm.acknowledged_by.as_deref().unwrap_or("unknown")
+                );
+            }
+            StateChange::AlarmCleared(alarm) => {
+                info!("{}: Alarm {} cleared", self.device_name, alarm.id);
+            }
         }
 
         Ok(())
     }
+
+    fn name(&self) -> &str {
+        "console_logger"
+    }
 }
@@ -1,33 +1,51 @@
-use axum::{
-    routing::{post, put},
-    Router,
-};
 use std::sync::Arc;
 use tokio::{net::TcpListener, sync::Mutex};
 
-mod adapters;
-mod config;
-mod core;
-mod logging;
-
-use crate::core::{
+use gateway::core::{
     events::GatewayEvent,
+    health::{self, HealthRegistry},
+    metrics::Metrics,
     state::{AppState, GatewayState, StateListener},
 };
-use crate::{
-    adapters::api::{create_device, delete_device, get_devices, health_check, update_device},
-    adapters::{
-        modbus::ModbusPoller, mqtt::MqttPublisher, simulation::SimulationPoller,
-        spawn_service::spawn_service,
-    },
-    config::{Config, SourceMode},
+use gateway::{
+    adapters::api::{self, auth::Authenticator},
+    adapters::{mqtt::MqttPublisher, sources::Sources},
+    cli::{self, Cli, Command},
+    config::{ConfigSource, ENV_PREFIX},
+    core::aggregation::AggregationListener,
+    core::alarms::AlarmTimer,
     core::bootstrap::initialize_devices,
     core::dispatcher::Dispatcher,
+    core::event_loop::EventLoop,
+    core::reload::{ConfigReloader, ReloadTrigger, SourceControl},
+    core::supervisor::Supervisor,
+    core::virtual_devices::VirtualDevices,
 };
-use tracing::{debug, error, info, warn};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    let source = ConfigSource::resolve(cli.config, cli.overrides);
+
+    let code = match cli.command {
+        Command::Run => return run(source).await,
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            0
+        }
+        Command::CheckConfig => cli::check_config(&source),
+        Command::PrintDefaultConfig => cli::print_default_config(),
+        Command::ScanModbus(options) => cli::scan_modbus(&source, options).await,
+        Command::DumpState(options) => cli::dump_state(&source, options).await,
+    };
+    std::process::exit(code);
+}
+
+async fn run(source: ConfigSource) -> Result<(), Box<dyn std::error::Error>> {
     // -------------------------
     // INITIALIZE TRACING / LOGGING
     // -------------------------
@@ -40,7 +58,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // -------------------------
     // LOAD CONFIG
     // -------------------------
-    let config = Config::load_or_default();
+    // Typos and invalid values stop startup instead of falling back to defaults
+    let config = match source.load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    info!(
+        "Loaded {} with {} fragments (overrides via {}*)",
+        source.path.display(),
+        source.fragments().len(),
+        ENV_PREFIX
+    );
     info!("Full config:\n{}", toml::to_string_pretty(&config).unwrap());
     let gateway_name = config.gateway_name.clone();
 
@@ -52,24 +83,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // -------------------------
     // EVENT CHANNEL & SHARED STATE
     // -------------------------
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<GatewayEvent>(32);
-    let shared_state = Arc::new(Mutex::new(GatewayState::new()));
+    let (tx, rx) = tokio::sync::mpsc::channel::<GatewayEvent>(32);
+    // Expressions were validated with the config
+    let virtual_devices = VirtualDevices::new(&config.virtual_devices, &config.tags)?;
+    let gateway_state = GatewayState::with_alarms(config.alarms.rules.clone())
+        .with_virtual_devices(virtual_devices);
+    let alarm_timer = gateway_state.alarms.needs_timer();
+    let shared_state = Arc::new(Mutex::new(gateway_state));
+
+    // -------------------------
+    // HEALTH REGISTRY
+    // -------------------------
+    let health = HealthRegistry::new();
+    health.register(health::EVENT_LOOP, true);
+    health.register(health::MQTT, true);
+
+    // -------------------------
+    // METRICS
+    // -------------------------
+    let metrics = Metrics::new();
+
+    // -------------------------
+    // SUPERVISOR
+    // -------------------------
+    // Restarts failed services and reports them under `services` in /health/ready
+    let supervisor = Supervisor::new(config.supervisor.clone(), health.clone(), metrics.clone());
 
     // -------------------------
     // LISTENERS SETUP
     // -------------------------
-    let mut listeners: Vec<Arc<dyn StateListener>> =
-        vec![Arc::new(logging::ConsoleLogger::new(&gateway_name))];
+    let mut listeners: Vec<Arc<dyn StateListener>> = vec![Arc::new(
+        gateway::logging::ConsoleLogger::new(&gateway_name),
+    )];
 
     let mqtt_service = match MqttPublisher::new(
         config.mqtt.clone(),
         config.gateway_id.clone(),
         config.gateway_name.clone(),
+        health.clone(),
+        metrics.clone(),
     )
     .await
     {
-        Ok(p) => Some(Arc::new(p)),
+        Ok(p) => Some(Arc::new(p.with_aggregation(config.aggregation.clone()))),
         Err(err) => {
+            health.mark_down(health::MQTT, format!("connection failed: {}", err));
             warn!(
                 "{}: {} - MQTT connection failed, running without MQTT: {}",
                 gateway_name, config.mqtt.device_name, err
@@ -78,11 +136,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // Windows go out through the MQTT publisher, so aggregation needs a connection. It
+    // is registered first so a removed device's last window precedes the removal; in
+    // queued dispatch both share a queue to keep that order.
+    let mut aggregation = None;
     if let Some(mqtt) = &mqtt_service {
+        if let Some(config) = &config.aggregation {
+            let listener = AggregationListener::new(config.clone(), mqtt.clone());
+            listeners.push(Arc::new(listener.clone()));
+            aggregation = Some(listener);
+        }
         listeners.push(mqtt.clone());
     }
 
-    let dispatcher = Arc::new(Dispatcher::new(listeners));
+    let dispatcher = Arc::new(
+        Dispatcher::new(listeners)
+            .with_metrics(metrics.clone())
+            .share_queue(&["aggregation", "mqtt"])
+            .with_config(&config.dispatch),
+    );
 
     // -------------------------
     // INITIALIZE DEVICES
@@ -93,43 +165,78 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // START DATA SOURCES (MODBUS / SIMULATION)
     // -------------------------
     info!("Starting gateway with mode: {:?}", config.mode);
-    match config.mode {
-        SourceMode::Modbus => {
-            let modbus = ModbusPoller::new(config.modbus.clone(), tx.clone());
-            spawn_service(modbus, shutdown_tx.subscribe());
-        }
-        SourceMode::Simulation => {
-            let sim = SimulationPoller::new(config.simulation.clone(), tx.clone());
-            spawn_service(sim, shutdown_tx.subscribe());
-        }
-    }
+    let mut sources = Sources::new(
+        tx.clone(),
+        health.clone(),
+        metrics.clone(),
+        supervisor.clone(),
+    );
+    sources.start(&config);
 
     // -------------------------
-    // EVENT LOOP
+    // CONFIG RELOAD (SIGHUP, FILE CHANGE, ADMIN API)
     // -------------------------
-    let event_state = shared_state.clone();
-    let event_dispatcher = dispatcher.clone();
-    let gateway_name_loop = gateway_name.clone();
+    // The reloader owns the source from here on and stops it on shutdown
+    let (reloader, reload_handle) = ConfigReloader::new(
+        source,
+        config.clone(),
+        tx.clone(),
+        Box::new(sources),
+        metrics.clone(),
+    );
+    // Holds the reload request channel, so it is not rebuilt after a failure
+    supervisor.spawn_once("reloader", shutdown_tx.subscribe(), reloader);
 
-    tokio::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            debug!("{}: Event loop: received {:?}", gateway_name_loop, event);
-
-            let state_change = {
-                let mut state = event_state.lock().await;
-
-                match state.apply_event(event) {
-                    Some(sc) => sc,
-                    None => {
-                        error!("{}: Failed to apply event", gateway_name_loop);
-                        continue;
-                    }
+    #[cfg(unix)]
+    {
+        let reload_handle = reload_handle.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    warn!("Cannot listen for SIGHUP, reload via API only: {}", e);
+                    return;
                 }
             };
+            while hangup.recv().await.is_some() {
+                // Outcome is logged by the reloader
+                let _ = reload_handle.reload(ReloadTrigger::Signal).await;
+            }
+        });
+    }
 
-            event_dispatcher.dispatch(state_change).await;
-        }
-    });
+    // Fed by the event loop, so it flushes its partial windows only after the drain
+    let outputs = supervisor.stage();
+    let (outputs_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    if let Some(aggregation) = aggregation {
+        outputs.spawn("aggregation", outputs_tx.subscribe(), move || {
+            aggregation.clone()
+        });
+    }
+
+    if alarm_timer {
+        let (timer_tx, interval_ms) = (tx.clone(), config.alarms.check_interval_ms);
+        supervisor.spawn("alarm_timer", shutdown_tx.subscribe(), move || {
+            AlarmTimer::new(timer_tx.clone(), interval_ms)
+        });
+    }
+
+    // -------------------------
+    // EVENT LOOP
+    // -------------------------
+    // Stopped after the sources, so the events they queued are still applied
+    let pipeline = supervisor.stage();
+    let (drain_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    let event_loop = EventLoop::new(
+        gateway_name.clone(),
+        rx,
+        shared_state.clone(),
+        dispatcher.clone(),
+        health.clone(),
+        metrics.clone(),
+    );
+    pipeline.spawn_once("event_loop", drain_tx.subscribe(), event_loop);
 
     // -------------------------
     // APP STATE FOR ROUTES
@@ -137,18 +244,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app_state = AppState {
         tx: tx.clone(),
         state: shared_state.clone(),
+        health: health.clone(),
+        metrics: metrics.clone(),
+        reload: Some(reload_handle),
+    };
+    let app = api::router(app_state, Authenticator::new(&config.auth));
+
+    // Fail fast on unreadable certificates instead of serving plain HTTP by accident
+    let tls_config = match &config.api.tls {
+        Some(tls) => Some(api::tls::load_server_config(tls)?),
+        None => None,
     };
-    let app = Router::new()
-        .route("/devices", post(create_device).get(get_devices))
-        .route("/devices/{id}", put(update_device).delete(delete_device))
-        .route("/health", axum::routing::get(health_check))
-        .with_state(app_state);
 
     let addr = format!("{}:{}", config.api.host, config.api.port);
     let listener = TcpListener::bind(&addr).await.unwrap();
     info!(
-        "{}: {} - HTTP server listening on {}",
-        gateway_name, config.api.device_name, addr
+        "{}: {} - {} server listening on {}",
+        gateway_name,
+        config.api.device_name,
+        if tls_config.is_some() {
+            "HTTPS"
+        } else {
+            "HTTP"
+        },
+        addr
     );
 
     // -------------------------
@@ -156,23 +275,69 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // -------------------------
     let shutdown_signal = shutdown_tx.clone();
     tokio::spawn(async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to listen for Ctrl+C");
-        info!("Shutdown signal received (Ctrl+C)");
+        let signal = terminate_signal().await;
+        info!("Shutdown signal received ({})", signal);
         let _ = shutdown_signal.send(());
     });
-    info!("Press Ctrl+C to shutdown gracefully");
-
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            let mut shutdown_rx = shutdown_tx.subscribe();
-            shutdown_rx.recv().await.ok();
-            info!("HTTP server shutting down");
-        })
-        .await
-        .unwrap();
+    info!("Press Ctrl+C or send SIGTERM to shutdown gracefully");
+
+    match tls_config {
+        Some(server_config) => {
+            api::tls::serve(
+                listener.into_std()?,
+                app,
+                server_config,
+                shutdown_tx.subscribe(),
+            )
+            .await?;
+        }
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    let mut shutdown_rx = shutdown_tx.subscribe();
+                    shutdown_rx.recv().await.ok();
+                    info!("HTTP server shutting down");
+                })
+                .await
+                .unwrap();
+        }
+    }
+
+    // Phase 1: no new events. The reloader stops the source on shutdown, so this also
+    // waits for the source.
+    supervisor.shutdown().await;
+
+    // Phase 2: apply and dispatch what the sources queued
+    let _ = drain_tx.send(());
+    pipeline.shutdown().await;
+
+    // Phase 3: listeners with their own tasks publish what they still hold
+    let _ = outputs_tx.send(());
+    outputs.shutdown().await;
+
+    // Phase 4: publish the MQTT queue and the offline status
+    if let Some(mqtt) = &mqtt_service {
+        mqtt.shutdown().await;
+    }
 
     info!("Gateway stopped completely");
     Ok(())
 }
+
+// Ctrl+C, or SIGTERM as sent by container runtimes
+async fn terminate_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => return "Ctrl+C",
+                _ = terminate.recv() => return "SIGTERM",
+            }
+        }
+    }
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for Ctrl+C");
+    "Ctrl+C"
+}
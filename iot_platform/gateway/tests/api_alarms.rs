@@ -0,0 +1,139 @@
+use axum::{body::Body, http::Request, http::StatusCode, Router};
+use gateway::adapters::api::{self, auth::Authenticator};
+use gateway::config::{AlarmRule, ApiKeyConfig, AuthConfig, Role};
+use gateway::core::events::GatewayEvent;
+use gateway::core::health::HealthRegistry;
+use gateway::core::metrics::Metrics;
+use gateway::core::state::{AppState, GatewayState, StateChange};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tower::ServiceExt;
+
+// Router over a state with one raised "temp" hi alarm, plus the event receiver the API sends to
+async fn alarm_router() -> (
+    Router,
+    Arc<Mutex<GatewayState>>,
+    mpsc::Receiver<GatewayEvent>,
+) {
+    let mut state = GatewayState::with_alarms(vec![AlarmRule {
+        device_id: "temp".into(),
+        hi: Some(30.0),
+        ..Default::default()
+    }]);
+    state.apply(GatewayEvent::DeviceCreated {
+        id: "temp".into(),
+        timestamp: 1_000,
+    });
+    let changes = state.apply(GatewayEvent::DeviceValueObserved {
+        id: "temp".into(),
+        value: 31.0,
+        timestamp: 2_000,
+    });
+    assert!(matches!(changes[1], StateChange::AlarmRaised(_)));
+
+    let state = Arc::new(Mutex::new(state));
+    let (tx, rx) = mpsc::channel(10);
+    let key = |name: &str, role| ApiKeyConfig {
+        name: name.into(),
+        token: format!("{name}-token"),
+        role,
+    };
+    let auth = Authenticator::new(&AuthConfig {
+        enabled: true,
+        keys: vec![key("viewer", Role::Viewer), key("operator", Role::Operator)],
+    });
+    let router = api::router(
+        AppState {
+            tx,
+            state: state.clone(),
+            health: HealthRegistry::new(),
+            metrics: Metrics::new(),
+            reload: None,
+        },
+        auth,
+    );
+    (router, state, rx)
+}
+
+fn request(method: &str, uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn alarms(router: &Router, uri: &str) -> Value {
+    let response = router
+        .clone()
+        .oneshot(request("GET", uri, "viewer-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn operator_acknowledges_active_alarm() {
+    let (router, state, mut rx) = alarm_router().await;
+
+    let active = alarms(&router, "/alarms?state=active").await;
+    assert_eq!(active[0]["kind"], "hi");
+    let id = active[0]["id"].as_str().unwrap().to_string();
+    let uri = format!("/alarms/{id}/acknowledge");
+
+    // Viewers can list but not acknowledge
+    let response = router
+        .clone()
+        .oneshot(request("POST", &uri, "viewer-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router
+        .clone()
+        .oneshot(request("POST", &uri, "operator-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // The event loop applies the acknowledgement
+    let event = rx.try_recv().unwrap();
+    assert!(matches!(
+        &event,
+        GatewayEvent::AlarmAcknowledged { by, .. } if by == "operator"
+    ));
+    state.lock().await.apply(event);
+
+    let acknowledged = alarms(&router, "/alarms?state=acknowledged").await;
+    assert_eq!(acknowledged[0]["acknowledged_by"], "operator");
+    assert!(alarms(&router, "/alarms?state=active").await[0].is_null());
+
+    // A second acknowledgement conflicts
+    let response = router
+        .clone()
+        .oneshot(request("POST", &uri, "operator-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn unknown_alarm_is_not_found() {
+    let (router, _state, _rx) = alarm_router().await;
+
+    let response = router
+        .oneshot(request(
+            "POST",
+            "/alarms/nope/acknowledge",
+            "operator-token",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,150 @@
+use axum::{body::Body, http::Request, http::StatusCode, Router};
+use gateway::adapters::api::{self, auth::Authenticator};
+use gateway::config::{ApiKeyConfig, AuthConfig, Role};
+use gateway::core::health::HealthRegistry;
+use gateway::core::metrics::Metrics;
+use gateway::core::state::{AppState, GatewayState};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+type EventReceiver = tokio::sync::mpsc::Receiver<gateway::core::events::GatewayEvent>;
+
+// Helper to create a router with one key per role (receiver must stay alive for sends to succeed)
+fn create_auth_router() -> (Router, EventReceiver) {
+    let state = Arc::new(Mutex::new(GatewayState::new()));
+    let (tx, rx) = tokio::sync::mpsc::channel::<gateway::core::events::GatewayEvent>(10);
+
+    let key = |name: &str, role| ApiKeyConfig {
+        name: name.into(),
+        token: format!("{name}-token"),
+        role,
+    };
+    let auth = Authenticator::new(&AuthConfig {
+        enabled: true,
+        keys: vec![
+            key("viewer", Role::Viewer),
+            key("operator", Role::Operator),
+            key("admin", Role::Admin),
+        ],
+    });
+
+    (
+        api::router(
+            AppState {
+                tx,
+                state,
+                health: HealthRegistry::new(),
+                metrics: Metrics::new(),
+                reload: None,
+            },
+            auth,
+        ),
+        rx,
+    )
+}
+
+fn request(method: &str, uri: &str, token: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn health_is_public() {
+    let (router, _rx) = create_auth_router();
+
+    let response = router
+        .oneshot(request("GET", "/health", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn missing_or_invalid_token_is_unauthorized() {
+    let (router, _rx) = create_auth_router();
+
+    let response = router
+        .clone()
+        .oneshot(request("GET", "/devices", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let response = router
+        .oneshot(request("GET", "/devices", Some("wrong")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn viewer_can_read_but_not_mutate() {
+    let (router, _rx) = create_auth_router();
+
+    let response = router
+        .clone()
+        .oneshot(request("GET", "/devices", Some("viewer-token")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let create = Request::builder()
+        .method("POST")
+        .uri("/devices")
+        .header("authorization", "Bearer viewer-token")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"id":"1","value":1.0}"#))
+        .unwrap();
+    let response = router.oneshot(create).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn only_admin_can_delete() {
+    let (router, _rx) = create_auth_router();
+
+    let response = router
+        .clone()
+        .oneshot(request("DELETE", "/devices/1", Some("operator-token")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // API key header is accepted as an alternative to Bearer
+    let delete = Request::builder()
+        .method("DELETE")
+        .uri("/devices/1")
+        .header("x-api-key", "admin-token")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(delete).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn operator_can_create_and_update() {
+    let (router, _rx) = create_auth_router();
+
+    for method in ["POST", "PUT"] {
+        let uri = if method == "POST" {
+            "/devices"
+        } else {
+            "/devices/1"
+        };
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", "Bearer operator-token")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"id":"1","value":1.0}"#))
+            .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
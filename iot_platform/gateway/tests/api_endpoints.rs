@@ -1,6 +1,10 @@
 use axum::body;
 use axum::{body::Body, http::Request, http::StatusCode, Router};
-use gateway::adapters::api::{create_device, delete_device, get_devices, update_device};
+use gateway::adapters::api::{
+    self, auth::Authenticator, create_device, delete_device, get_devices, update_device,
+};
+use gateway::core::health::{self, HealthRegistry};
+use gateway::core::metrics::Metrics;
 use gateway::core::state::{AppState, GatewayState};
 use serde_json::Value;
 use std::sync::Arc;
@@ -23,7 +27,13 @@ fn create_test_router(
     state: Arc<Mutex<GatewayState>>,
     tx: tokio::sync::mpsc::Sender<gateway::core::events::GatewayEvent>,
 ) -> Router {
-    let app_state = AppState { tx, state };
+    let app_state = AppState {
+        tx,
+        state,
+        health: HealthRegistry::new(),
+        metrics: Metrics::new(),
+        reload: None,
+    };
     Router::new()
         .route(
             "/devices",
@@ -47,6 +57,9 @@ async fn api_endpoints_work() {
     let app_state = AppState {
         tx,
         state: state.clone(),
+        health: HealthRegistry::new(),
+        metrics: Metrics::new(),
+        reload: None,
     };
 
     // Build router
@@ -279,3 +292,97 @@ async fn error_empty_body_in_create() {
         assert_eq!(state_guard.devices.len(), 0);
     }
 }
+
+#[tokio::test]
+async fn health_ready_reflects_critical_components() {
+    let state = Arc::new(Mutex::new(GatewayState::new()));
+    let (tx, _rx) = tokio::sync::mpsc::channel::<gateway::core::events::GatewayEvent>(10);
+    let health = HealthRegistry::new();
+    health.register(health::EVENT_LOOP, true);
+    health.register(health::MQTT, true);
+    health.register("source.modbus", false);
+    health.mark_up(health::EVENT_LOOP);
+
+    let router = api::router(
+        AppState {
+            tx,
+            state,
+            health: health.clone(),
+            metrics: Metrics::new(),
+            reload: None,
+        },
+        Authenticator::disabled(),
+    );
+
+    let get = |uri: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // MQTT has not connected yet: alive but not ready
+    let response = router.clone().oneshot(get("/health/live")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().oneshot(get("/health/ready")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body_bytes = body::to_bytes(response.into_body(), 1024 * 1024)
+        .await
+        .unwrap();
+    let report: Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(report["status"], "down");
+    assert_eq!(report["components"]["mqtt"]["status"], "down");
+
+    // Once MQTT connects the gateway becomes ready; a reconnecting source only degrades it
+    health.mark_up(health::MQTT);
+    health.mark_down("source.modbus", "reconnecting");
+    let response = router.oneshot(get("/health/ready")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body_bytes = body::to_bytes(response.into_body(), 1024 * 1024)
+        .await
+        .unwrap();
+    let report: Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(report["status"], "degraded");
+}
+
+#[tokio::test]
+async fn metrics_endpoint_exposes_api_latency_per_route() {
+    let state = Arc::new(Mutex::new(GatewayState::new()));
+    let (tx, _rx) = tokio::sync::mpsc::channel::<gateway::core::events::GatewayEvent>(10);
+    let router = api::router(
+        AppState {
+            tx,
+            state,
+            health: HealthRegistry::new(),
+            metrics: Metrics::new(),
+            reload: None,
+        },
+        Authenticator::disabled(),
+    );
+
+    let request = Request::builder()
+        .method("DELETE")
+        .uri("/devices/7")
+        .body(Body::empty())
+        .unwrap();
+    router.clone().oneshot(request).await.unwrap();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("/metrics")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = body::to_bytes(response.into_body(), 1024 * 1024)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body_bytes.to_vec()).unwrap();
+    // Route template is used as label, not the concrete device id
+    assert!(text.contains(
+        r#"gateway_http_request_duration_seconds_count{method="DELETE",route="/devices/{id}",status="204"} 1"#
+    ));
+}
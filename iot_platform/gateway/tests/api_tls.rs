@@ -0,0 +1,187 @@
+use gateway::adapters::api::{self, auth::Authenticator, tls};
+use gateway::config::TlsConfig;
+use gateway::core::health::HealthRegistry;
+use gateway::core::metrics::Metrics;
+use gateway::core::state::{AppState, GatewayState};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+    ClientConfig, RootCertStore,
+};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::{broadcast, Mutex},
+};
+use tokio_rustls::TlsConnector;
+
+// Self-signed CA plus a server and a client certificate issued by it
+struct TestPki {
+    dir: PathBuf,
+    ca_der: CertificateDer<'static>,
+    client_cert: CertificateDer<'static>,
+    client_key: Vec<u8>,
+}
+
+impl TestPki {
+    fn generate(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("gateway-tls-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let mut server_params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let server_cert = server_params
+            .signed_by(&server_key, &ca_cert, &ca_key)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(vec!["plc-client".to_string()]).unwrap();
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client_cert = client_params
+            .signed_by(&client_key, &ca_cert, &ca_key)
+            .unwrap();
+
+        std::fs::write(dir.join("ca.pem"), ca_cert.pem()).unwrap();
+        std::fs::write(dir.join("server.pem"), server_cert.pem()).unwrap();
+        std::fs::write(dir.join("server.key"), server_key.serialize_pem()).unwrap();
+
+        Self {
+            dir,
+            ca_der: ca_cert.der().clone(),
+            client_cert: client_cert.der().clone(),
+            client_key: client_key.serialize_der(),
+        }
+    }
+
+    fn tls_config(&self, mutual: bool) -> TlsConfig {
+        let path = |file: &str| self.dir.join(file).to_string_lossy().into_owned();
+        TlsConfig {
+            cert_path: path("server.pem"),
+            key_path: path("server.key"),
+            client_ca_path: mutual.then(|| path("ca.pem")),
+        }
+    }
+
+    fn client(&self, with_cert: bool) -> TlsConnector {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca_der.clone()).unwrap();
+
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+
+        let config = if with_cert {
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.client_key.clone()));
+            builder
+                .with_client_auth_cert(vec![self.client_cert.clone()], key)
+                .unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        TlsConnector::from(Arc::new(config))
+    }
+}
+
+impl Drop for TestPki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Helper to start the API over TLS on an ephemeral port
+async fn start_server(config: &TlsConfig) -> (SocketAddr, broadcast::Sender<()>) {
+    let (tx, _rx) = tokio::sync::mpsc::channel::<gateway::core::events::GatewayEvent>(10);
+    let app_state = AppState {
+        tx,
+        state: Arc::new(Mutex::new(GatewayState::new())),
+        health: HealthRegistry::new(),
+        metrics: Metrics::new(),
+        reload: None,
+    };
+    let app = api::router(app_state, Authenticator::disabled());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_config = tls::load_server_config(config).unwrap();
+
+    let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+    tokio::spawn(tls::serve(listener, app, server_config, shutdown_rx));
+
+    (addr, shutdown_tx)
+}
+
+// Sends a plain HTTP/1.1 request over TLS and returns the raw response
+async fn get_health(addr: SocketAddr, connector: TlsConnector) -> std::io::Result<String> {
+    let tcp = TcpStream::connect(addr).await?;
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await?;
+
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test]
+async fn https_serves_health_with_self_signed_certificate() {
+    let pki = TestPki::generate("plain");
+    let (addr, shutdown) = start_server(&pki.tls_config(false)).await;
+
+    let response = get_health(addr, pki.client(false)).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.contains(r#""status":"up""#), "{response}");
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn mutual_tls_accepts_client_certificate_from_ca() {
+    let pki = TestPki::generate("mtls-ok");
+    let (addr, shutdown) = start_server(&pki.tls_config(true)).await;
+
+    let response = get_health(addr, pki.client(true)).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn mutual_tls_rejects_client_without_certificate() {
+    let pki = TestPki::generate("mtls-reject");
+    let (addr, shutdown) = start_server(&pki.tls_config(true)).await;
+
+    // With TLS 1.3 the rejection may surface on read instead of during the handshake
+    let result = get_health(addr, pki.client(false)).await;
+    assert!(
+        result.is_err() || !result.unwrap().contains("200 OK"),
+        "request without client certificate must not succeed"
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[test]
+fn missing_certificate_file_is_reported() {
+    let config = TlsConfig {
+        cert_path: "/nonexistent/server.pem".into(),
+        key_path: "/nonexistent/server.key".into(),
+        client_ca_path: None,
+    };
+
+    let err = tls::load_server_config(&config).unwrap_err();
+    assert!(err.to_string().contains("/nonexistent/server.pem"));
+}
@@ -0,0 +1,194 @@
+use gateway::adapters::api::{self, auth::Authenticator};
+use gateway::adapters::modbus::scan::{scan, Reading, ScanRequest};
+use gateway::cli::{self, DumpOptions, ScanOptions};
+use gateway::config::{ApiKeyConfig, AuthConfig, Config, ConfigSource, Role};
+use gateway::core::events::GatewayEvent;
+use gateway::core::health::{self, HealthRegistry};
+use gateway::core::metrics::Metrics;
+use gateway::core::state::{AppState, GatewayState};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+
+fn temp_config(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gateway-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn check_config_reports_valid_and_invalid_files() {
+    let default = toml::to_string_pretty(&Config::default()).unwrap();
+    let valid = temp_config("valid", &default);
+    let invalid = temp_config(
+        "invalid",
+        &default.replace("[mqtt]", "[mqtt]\nbrokr = \"x\""),
+    );
+
+    assert_eq!(cli::check_config(&ConfigSource::file(&valid)), 0);
+    assert_eq!(cli::check_config(&ConfigSource::file(&invalid)), 1);
+    assert_eq!(
+        cli::check_config(&ConfigSource::file(valid.with_file_name("missing.toml"))),
+        1
+    );
+
+    for path in [valid, invalid] {
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
+
+// Minimal Modbus TCP server answering "read holding registers" with address * 10
+async fn modbus_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // MBAP header (7 bytes) and PDU: function, address, quantity
+        let mut frame = [0u8; 12];
+        while stream.read_exact(&mut frame).await.is_ok() {
+            let address = u16::from_be_bytes([frame[8], frame[9]]);
+            let count = u16::from_be_bytes([frame[10], frame[11]]);
+
+            let mut response = Vec::new();
+            response.extend_from_slice(&frame[0..4]);
+            response.extend_from_slice(&(3 + 2 * count).to_be_bytes());
+            response.extend_from_slice(&[frame[6], 0x03, (2 * count) as u8]);
+            for register in address..address + count {
+                response.extend_from_slice(&(register * 10).to_be_bytes());
+            }
+            stream.write_all(&response).await.unwrap();
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn scan_modbus_reads_a_device_without_a_config() {
+    let addr = modbus_server().await;
+    let request = ScanRequest {
+        host: addr.ip().to_string(),
+        port: addr.port(),
+        slaves: 1..=1,
+        addresses: 3..=4,
+        count: 1,
+        timeout_ms: 1000,
+    };
+
+    let results = scan(&request).await.unwrap();
+    let decoded: Vec<_> = results
+        .iter()
+        .map(|r| match &r.reading {
+            Reading::Value { decoded, .. } => Some(*decoded),
+            Reading::Failed(_) => None,
+        })
+        .collect();
+    assert_eq!(decoded, [Some(30), Some(40)]);
+
+    // No config file: the connection comes from the options alone
+    let source = ConfigSource::file("/nonexistent/gateway.toml");
+    let addr = modbus_server().await;
+    let options = ScanOptions {
+        host: Some(addr.ip().to_string()),
+        port: Some(addr.port()),
+        ..ScanOptions::default()
+    };
+    assert_eq!(cli::scan_modbus(&source, options).await, 0);
+
+    // Nothing listens on the port of a dropped listener
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let options = ScanOptions {
+        host: Some(closed.ip().to_string()),
+        port: Some(closed.port()),
+        ..ScanOptions::default()
+    };
+    assert_eq!(cli::scan_modbus(&source, options).await, 1);
+}
+
+// Serves the real API router over plain HTTP with one device and an operator key
+async fn api_server() -> String {
+    let mut state = GatewayState::new();
+    state.apply(GatewayEvent::DeviceCreated {
+        id: "temp".into(),
+        timestamp: 1_000,
+    });
+    let (tx, _rx) = mpsc::channel(10);
+    let health = HealthRegistry::new();
+    health.register(health::MQTT, true);
+    let auth = Authenticator::new(&AuthConfig {
+        enabled: true,
+        keys: vec![ApiKeyConfig {
+            name: "ops".into(),
+            token: "ops-token".into(),
+            role: Role::Operator,
+        }],
+    });
+    let router = api::router(
+        AppState {
+            tx,
+            state: Arc::new(Mutex::new(state)),
+            health,
+            metrics: Metrics::new(),
+            reload: None,
+        },
+        auth,
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn dump_state_reads_devices_alarms_and_readiness() {
+    let url = api_server().await;
+
+    let timeout = Duration::from_secs(5);
+    let state = cli::fetch_state(&url, Some("ops-token"), timeout)
+        .await
+        .unwrap();
+    assert_eq!(state["devices"][0]["id"], "temp", "{state}");
+    assert!(state["alarms"].is_array(), "{state}");
+    // Not ready yet, the 503 report is still part of the dump
+    assert_eq!(state["health"]["status"], "down", "{state}");
+
+    let err = cli::fetch_state(&url, None, timeout).await.unwrap_err();
+    assert!(err.contains("/devices: HTTP 401"), "{err}");
+
+    let source = ConfigSource::file("/nonexistent/gateway.toml");
+    let dump = |token: Option<&str>| DumpOptions {
+        url: Some(url.clone()),
+        token: token.map(str::to_string),
+        ..DumpOptions::default()
+    };
+    assert_eq!(cli::dump_state(&source, dump(Some("ops-token"))).await, 0);
+    assert_eq!(cli::dump_state(&source, dump(None)).await, 1);
+}
+
+#[tokio::test]
+async fn dump_state_gives_up_on_a_silent_server() {
+    // Accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            open.push(stream);
+        }
+    });
+
+    let err = cli::fetch_state(&url, None, Duration::from_millis(100))
+        .await
+        .unwrap_err();
+    assert!(err.contains("no response within 100 ms"), "{err}");
+}
@@ -0,0 +1,186 @@
+use gateway::config::{AlarmRule, Config, ConfigSource, SourceMode, VirtualDeviceConfig};
+use gateway::core::events::GatewayEvent;
+use gateway::core::lifecycle::Lifecycle;
+use gateway::core::metrics::Metrics;
+use gateway::core::reload::{ConfigReloader, ReloadHandle, ReloadTrigger, SourceControl};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+// Records which source mode was started or stopped
+#[derive(Clone, Default)]
+struct FakeSources {
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl SourceControl for FakeSources {
+    fn start(&mut self, config: &Config) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("start {:?}", config.mode));
+    }
+
+    fn stop(&mut self) {
+        self.calls.lock().unwrap().push("stop".into());
+    }
+}
+
+struct Harness {
+    path: PathBuf,
+    handle: ReloadHandle,
+    events: mpsc::Receiver<GatewayEvent>,
+    sources: FakeSources,
+    shutdown: broadcast::Sender<()>,
+}
+
+impl Harness {
+    fn write(&self, config: &Config) {
+        std::fs::write(&self.path, toml::to_string(config).unwrap()).unwrap();
+    }
+
+    fn drain(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            events.push(match event {
+                GatewayEvent::DeviceCreated { id, .. } => format!("created {}", id),
+                GatewayEvent::DeviceRemoved { id, .. } => format!("removed {}", id),
+                other => other.kind().to_string(),
+            });
+        }
+        events
+    }
+}
+
+fn start(name: &str, config: &Config) -> Harness {
+    // Own directory so no config.d fragments are picked up
+    let dir = std::env::temp_dir().join(format!("gateway-reload-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    std::fs::write(&path, toml::to_string(config).unwrap()).unwrap();
+
+    let (tx, events) = mpsc::channel(32);
+    let sources = FakeSources::default();
+    let (reloader, handle) = ConfigReloader::new(
+        ConfigSource::file(&path),
+        config.clone(),
+        tx,
+        Box::new(sources.clone()),
+        Metrics::new(),
+    );
+    let (shutdown, _) = broadcast::channel(1);
+    tokio::spawn(reloader.run(shutdown.subscribe()));
+
+    Harness {
+        path,
+        handle,
+        events,
+        sources,
+        shutdown,
+    }
+}
+
+fn modbus_config(devices: &[&str]) -> Config {
+    let mut config = Config {
+        mode: SourceMode::Modbus,
+        ..Config::default()
+    };
+    // Reloads are requested explicitly in these tests
+    config.reload.watch = false;
+    let template = config.modbus.registers[0].clone();
+    config.modbus.registers = devices
+        .iter()
+        .enumerate()
+        .map(|(i, id)| gateway::config::RegisterMapping {
+            address: i as u16,
+            device_id: id.to_string(),
+            ..template.clone()
+        })
+        .collect();
+    config
+}
+
+#[tokio::test]
+async fn reload_restarts_the_source_and_syncs_devices() {
+    let old = modbus_config(&["1", "2"]);
+    let mut h = start("apply", &old);
+
+    let mut new = modbus_config(&["2", "3"]);
+    new.alarms.rules.push(AlarmRule {
+        device_id: "3".into(),
+        hi: Some(50.0),
+        ..Default::default()
+    });
+    new.virtual_devices.push(VirtualDeviceConfig {
+        id: "total".into(),
+        expression: "{2} + {3}".into(),
+    });
+    new.api.port = 9999;
+    h.write(&new);
+
+    let diff = h.handle.reload(ReloadTrigger::Api).await.unwrap();
+    assert!(diff.source_restarted && diff.rules_replaced);
+    assert_eq!(diff.restart_required, ["api"]);
+    assert_eq!(
+        h.drain(),
+        ["rules_replaced", "removed 1", "created 3", "created total"]
+    );
+    assert_eq!(*h.sources.calls.lock().unwrap(), ["start Modbus"]);
+
+    // Same file again: nothing to do, the api port still waits for a restart
+    let diff = h.handle.reload(ReloadTrigger::Signal).await.unwrap();
+    assert!(diff.is_empty());
+    assert_eq!(diff.restart_required, ["api"]);
+    assert!(h.drain().is_empty());
+
+    h.shutdown.send(()).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(*h.sources.calls.lock().unwrap(), ["start Modbus", "stop"]);
+    let _ = std::fs::remove_dir_all(h.path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn invalid_config_is_rejected_without_changes() {
+    let old = modbus_config(&["1"]);
+    let mut h = start("reject", &old);
+
+    std::fs::write(&h.path, "mode = ").unwrap();
+    assert!(h.handle.reload(ReloadTrigger::Api).await.is_err());
+
+    let mut new = modbus_config(&["1", "2"]);
+    new.virtual_devices.push(VirtualDeviceConfig {
+        id: "broken".into(),
+        expression: "avg(#missing)".into(),
+    });
+    h.write(&new);
+    let err = h.handle.reload(ReloadTrigger::Api).await.unwrap_err();
+    assert!(err.contains("unknown tag"), "{}", err);
+
+    assert!(h.drain().is_empty());
+    assert!(h.sources.calls.lock().unwrap().is_empty());
+
+    // Once fixed, the diff is taken against the config that is still running
+    new.virtual_devices.clear();
+    h.write(&new);
+    h.handle.reload(ReloadTrigger::Api).await.unwrap();
+    assert_eq!(h.drain(), ["created 2"]);
+    let _ = std::fs::remove_dir_all(h.path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn failed_reload_does_not_restart_the_source_twice() {
+    let old = modbus_config(&["1"]);
+    let mut h = start("partial", &old);
+    // The event loop is gone, device changes cannot be sent
+    h.events.close();
+
+    h.write(&modbus_config(&["1", "2"]));
+    let err = h.handle.reload(ReloadTrigger::Api).await.unwrap_err();
+    assert!(err.contains("event loop"), "{}", err);
+    assert_eq!(*h.sources.calls.lock().unwrap(), ["start Modbus"]);
+
+    // The device sync is retried, the source already runs the new config
+    assert!(h.handle.reload(ReloadTrigger::Api).await.is_err());
+    assert_eq!(*h.sources.calls.lock().unwrap(), ["start Modbus"]);
+    let _ = std::fs::remove_dir_all(h.path.parent().unwrap());
+}
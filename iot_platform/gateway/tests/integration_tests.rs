@@ -145,6 +145,10 @@ impl StateListener for TestListener {
                 s.apply_event(GatewayEvent::DeviceRemoved { id, timestamp })
                     .unwrap();
             }
+            // Alarms are derived state, the mirror only tracks devices
+            StateChange::AlarmRaised(_)
+            | StateChange::AlarmAcknowledged(_)
+            | StateChange::AlarmCleared(_) => {}
         }
         Ok(())
     }
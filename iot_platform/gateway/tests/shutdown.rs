@@ -0,0 +1,79 @@
+use gateway::config::SupervisorConfig;
+use gateway::core::{
+    dispatcher::Dispatcher,
+    event_loop::EventLoop,
+    events::GatewayEvent,
+    health::{self, ComponentStatus, HealthRegistry, ServiceState},
+    metrics::Metrics,
+    state::{GatewayState, ListenerError, StateChange, StateListener},
+    supervisor::Supervisor,
+};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+struct Recorder {
+    changes: Arc<Mutex<Vec<StateChange>>>,
+}
+
+#[async_trait::async_trait]
+impl StateListener for Recorder {
+    async fn on_event(&self, change: StateChange) -> Result<(), ListenerError> {
+        // Slow enough that events are still queued when the stop signal arrives
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        self.changes.lock().await.push(change);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn event_loop_drains_queued_events_on_shutdown() {
+    let health = HealthRegistry::new();
+    health.register(health::EVENT_LOOP, true);
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let dispatcher = Dispatcher::new(vec![Arc::new(Recorder {
+        changes: changes.clone(),
+    })]);
+    let state = Arc::new(Mutex::new(GatewayState::new()));
+    let supervisor = Supervisor::new(SupervisorConfig::default(), health.clone(), Metrics::new());
+
+    let (tx, rx) = mpsc::channel(32);
+    for id in 0..10 {
+        tx.send(GatewayEvent::DeviceCreated {
+            id: id.to_string(),
+            timestamp: 1,
+        })
+        .await
+        .unwrap();
+    }
+
+    let (stop, _) = broadcast::channel(1);
+    let event_loop = EventLoop::new(
+        "test".into(),
+        rx,
+        state.clone(),
+        Arc::new(dispatcher),
+        health.clone(),
+        Metrics::new(),
+    );
+    supervisor.spawn_once("event_loop", stop.subscribe(), event_loop);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    stop.send(()).unwrap();
+    supervisor.shutdown().await;
+
+    assert_eq!(changes.lock().await.len(), 10);
+    assert_eq!(state.lock().await.devices.len(), 10);
+    assert!(tx
+        .send(GatewayEvent::DeviceCreated {
+            id: "late".into(),
+            timestamp: 2,
+        })
+        .await
+        .is_err());
+
+    let report = health.readiness();
+    assert_eq!(
+        report.components[health::EVENT_LOOP].status,
+        ComponentStatus::Down
+    );
+    assert_eq!(report.services["event_loop"].state, ServiceState::Stopped);
+}
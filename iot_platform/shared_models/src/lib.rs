@@ -32,9 +32,151 @@ pub struct DeviceRemovedPayload {
     pub meta: Metadata,
 }
 
+// Statistics of one device over a tumbling window [start, end); timestamps are the
+// first and last sample inside the window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateWindow {
+    pub start: i64,
+    pub end: i64,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAggregatePayload {
+    pub ctx: DeviceContext,
+    pub window: AggregateWindow,
+    pub meta: Metadata,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmKind {
+    HiHi,
+    Hi,
+    Lo,
+    LoLo,
+    RateOfChange,
+    Stuck,
+    Stale,
+}
+
+impl AlarmKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlarmKind::HiHi => "hi_hi",
+            AlarmKind::Hi => "hi",
+            AlarmKind::Lo => "lo",
+            AlarmKind::LoLo => "lo_lo",
+            AlarmKind::RateOfChange => "rate_of_change",
+            AlarmKind::Stuck => "stuck",
+            AlarmKind::Stale => "stale",
+        }
+    }
+
+    // Limit alarms beyond the outer thresholds are critical, everything else a warning
+    pub fn severity(&self) -> AlarmSeverity {
+        match self {
+            AlarmKind::HiHi | AlarmKind::LoLo => AlarmSeverity::Critical,
+            _ => AlarmSeverity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmSeverity {
+    Warning,
+    Critical,
+}
+
+impl AlarmSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlarmSeverity::Warning => "warning",
+            AlarmSeverity::Critical => "critical",
+        }
+    }
+}
+
+// Full state of one alarm occurrence; every lifecycle event carries all of it, so the
+// latest state can be rebuilt from events arriving in any order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmRecord {
+    pub id: String,
+    pub kind: AlarmKind,
+    pub severity: AlarmSeverity,
+    pub value: Option<f64>,
+    pub message: String,
+    pub raised_at: i64,
+    pub acknowledged_at: Option<i64>,
+    pub acknowledged_by: Option<String>,
+    pub cleared_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlarmPayload {
+    pub ctx: DeviceContext,
+    pub alarm: AlarmRecord,
+    pub meta: Metadata,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewayStatus {
+    Online,
+    Offline,
+}
+
+// Retained on `{gateway_name}/status`. The offline status is published on shutdown and
+// registered as last will, which carries the time of the connect instead
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayStatusPayload {
+    pub gateway_id: String,
+    pub gateway_name: String,
+    pub status: GatewayStatus,
+    pub meta: Metadata,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum TelemetryMessage {
     DeviceCreated(DeviceCreatedPayload),
     DeviceValueObserved(DeviceValueObservedPayload),
     DeviceRemoved(DeviceRemovedPayload),
+    DeviceValuesAggregated(DeviceAggregatePayload),
+    AlarmRaised(AlarmPayload),
+    AlarmAcknowledged(AlarmPayload),
+    AlarmCleared(AlarmPayload),
+}
+
+impl TelemetryMessage {
+    // Topic suffix the message is published under (created / value / removed)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TelemetryMessage::DeviceCreated(_) => "created",
+            TelemetryMessage::DeviceValueObserved(_) => "value",
+            TelemetryMessage::DeviceRemoved(_) => "removed",
+            TelemetryMessage::DeviceValuesAggregated(_) => "aggregate",
+            TelemetryMessage::AlarmRaised(_) => "alarm_raised",
+            TelemetryMessage::AlarmAcknowledged(_) => "alarm_acknowledged",
+            TelemetryMessage::AlarmCleared(_) => "alarm_cleared",
+        }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            TelemetryMessage::DeviceCreated(p) => p.meta.timestamp,
+            TelemetryMessage::DeviceValueObserved(p) => p.meta.timestamp,
+            TelemetryMessage::DeviceRemoved(p) => p.meta.timestamp,
+            TelemetryMessage::DeviceValuesAggregated(p) => p.meta.timestamp,
+            TelemetryMessage::AlarmRaised(p)
+            | TelemetryMessage::AlarmAcknowledged(p)
+            | TelemetryMessage::AlarmCleared(p) => p.meta.timestamp,
+        }
+    }
 }
@@ -0,0 +1,5 @@
+// Re-embed the migrations whenever a file in telemetry/migrations or migrations_sqlite changes
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=migrations_sqlite");
+}
@@ -1,3 +1,4 @@
+-- Initial schema (formerly db/init/init.sql); IF NOT EXISTS keeps it safe on databases created by the init script
 -- gateways table
 CREATE TABLE IF NOT EXISTS gateways (
     id TEXT PRIMARY KEY,
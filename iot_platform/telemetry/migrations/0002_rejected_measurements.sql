@@ -0,0 +1,14 @@
+-- Readings refused by processor validation, kept with the reason for inspection;
+-- no foreign key so a rejection is never lost to a missing device
+CREATE TABLE IF NOT EXISTS rejected_measurements (
+    id BIGSERIAL PRIMARY KEY,
+    gateway_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    "timestamp" TIMESTAMP NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    reason TEXT NOT NULL,
+    rejected_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc')
+);
+
+CREATE INDEX IF NOT EXISTS rejected_measurements_device_idx
+    ON rejected_measurements (gateway_id, device_id, "timestamp");
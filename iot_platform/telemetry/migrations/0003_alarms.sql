@@ -0,0 +1,21 @@
+-- Alarm occurrences raised by gateways; one row per alarm, updated as it is
+-- acknowledged and cleared
+CREATE TABLE IF NOT EXISTS alarms (
+    gateway_id TEXT NOT NULL,
+    id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    value DOUBLE PRECISION,
+    message TEXT NOT NULL,
+    raised_at TIMESTAMP NOT NULL,
+    acknowledged_at TIMESTAMP,
+    acknowledged_by TEXT,
+    cleared_at TIMESTAMP,
+    PRIMARY KEY (gateway_id, id),
+    FOREIGN KEY (gateway_id, device_id)
+        REFERENCES devices(gateway_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS alarms_open_idx ON alarms (raised_at DESC) WHERE cleared_at IS NULL;
+CREATE INDEX IF NOT EXISTS alarms_device_idx ON alarms (gateway_id, device_id, raised_at DESC);
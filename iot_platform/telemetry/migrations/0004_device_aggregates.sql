@@ -0,0 +1,18 @@
+-- Tumbling-window statistics computed by gateways (edge aggregation); one row per
+-- device and window, replaced when a window is delivered again
+CREATE TABLE IF NOT EXISTS device_aggregates (
+    gateway_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    window_start TIMESTAMP NOT NULL,
+    window_end TIMESTAMP NOT NULL,
+    count BIGINT NOT NULL,
+    min DOUBLE PRECISION NOT NULL,
+    max DOUBLE PRECISION NOT NULL,
+    avg DOUBLE PRECISION NOT NULL,
+    last DOUBLE PRECISION NOT NULL,
+    first_timestamp TIMESTAMP NOT NULL,
+    last_timestamp TIMESTAMP NOT NULL,
+    PRIMARY KEY (gateway_id, device_id, window_start),
+    FOREIGN KEY (gateway_id, device_id)
+        REFERENCES devices(gateway_id, id)
+);
@@ -0,0 +1,13 @@
+-- SQLite variant of migrations/0002_rejected_measurements.sql; NaN values are stored as NULL
+CREATE TABLE IF NOT EXISTS rejected_measurements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    gateway_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    "timestamp" INTEGER NOT NULL,
+    value REAL,
+    reason TEXT NOT NULL,
+    rejected_at INTEGER NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER))
+);
+
+CREATE INDEX IF NOT EXISTS rejected_measurements_device_idx
+    ON rejected_measurements (gateway_id, device_id, "timestamp");
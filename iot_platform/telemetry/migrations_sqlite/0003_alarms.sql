@@ -0,0 +1,20 @@
+-- SQLite variant of migrations/0003_alarms.sql
+CREATE TABLE IF NOT EXISTS alarms (
+    gateway_id TEXT NOT NULL,
+    id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    value REAL,
+    message TEXT NOT NULL,
+    raised_at INTEGER NOT NULL,
+    acknowledged_at INTEGER,
+    acknowledged_by TEXT,
+    cleared_at INTEGER,
+    PRIMARY KEY (gateway_id, id),
+    FOREIGN KEY (gateway_id, device_id)
+        REFERENCES devices(gateway_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS alarms_open_idx ON alarms (raised_at DESC) WHERE cleared_at IS NULL;
+CREATE INDEX IF NOT EXISTS alarms_device_idx ON alarms (gateway_id, device_id, raised_at DESC);
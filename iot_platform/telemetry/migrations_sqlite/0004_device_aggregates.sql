@@ -0,0 +1,17 @@
+-- SQLite variant of migrations/0004_device_aggregates.sql
+CREATE TABLE IF NOT EXISTS device_aggregates (
+    gateway_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    window_start INTEGER NOT NULL,
+    window_end INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    min REAL NOT NULL,
+    max REAL NOT NULL,
+    avg REAL NOT NULL,
+    last REAL NOT NULL,
+    first_timestamp INTEGER NOT NULL,
+    last_timestamp INTEGER NOT NULL,
+    PRIMARY KEY (gateway_id, device_id, window_start),
+    FOREIGN KEY (gateway_id, device_id)
+        REFERENCES devices(gateway_id, id)
+) WITHOUT ROWID;
@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use crate::{
+    core::ports::telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    domain::intents::Intent,
+};
+
+const HEADER: &str = "gateway_id,device_id,timestamp,value\n";
+
+// File name and rows of one append
+type Append = (String, String);
+
+// Appends measurements to one CSV file per UTC day (device_values-YYYY-MM-DD.csv),
+// named after the measurement timestamp so late data lands in the day it belongs to
+pub struct CsvArchiver {
+    pub dir: PathBuf,
+    // Appends of a batch that failed on a later file; its retry skips them instead of
+    // writing their rows twice. Identical rows from another batch would be duplicates anyway.
+    appended: Mutex<BTreeSet<Append>>,
+}
+
+impl CsvArchiver {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        CsvArchiver {
+            dir: dir.into(),
+            appended: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    fn file_name(timestamp: i64) -> String {
+        let day = DateTime::from_timestamp_millis(timestamp)
+            .map(|ts| ts.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "invalid".to_string());
+        format!("device_values-{}.csv", day)
+    }
+
+    // A failed write is truncated away so the file never ends in a partial row
+    async fn append(&self, file_name: &str, rows: &str) -> std::io::Result<()> {
+        let path = self.dir.join(file_name);
+        let new_file = !fs::try_exists(&path).await?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let len = file.metadata().await?.len();
+
+        let written = async {
+            if new_file {
+                file.write_all(HEADER.as_bytes()).await?;
+            }
+            file.write_all(rows.as_bytes()).await?;
+            file.flush().await
+        }
+        .await;
+        if written.is_err() {
+            let _ = file.set_len(len).await;
+        }
+        written
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for CsvArchiver {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        let mut files: BTreeMap<String, String> = BTreeMap::new();
+
+        for intent in batch.iter().flatten() {
+            if let Intent::RecordMeasurement {
+                device_id,
+                gateway_id,
+                value: Some(value),
+                timestamp,
+                ..
+            } = intent
+            {
+                files
+                    .entry(Self::file_name(*timestamp))
+                    .or_default()
+                    .push_str(&format!(
+                        "{},{},{},{}\n",
+                        csv_field(gateway_id),
+                        csv_field(device_id),
+                        timestamp,
+                        value
+                    ));
+            }
+        }
+
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut appended = self.appended.lock().await;
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| StorageError::Transient(format!("csv archive: {}", e)))?;
+
+        let mut written = BTreeSet::new();
+        let mut result = Ok(());
+        for append in files {
+            if !appended.contains(&append) {
+                let (file_name, rows) = &append;
+                if let Err(e) = self.append(file_name, rows).await {
+                    result = Err(StorageError::Transient(format!(
+                        "csv archive {}: {}",
+                        file_name, e
+                    )));
+                    break;
+                }
+            }
+            written.insert(append);
+        }
+
+        // Only the batch that just failed is retried, older entries are not needed anymore
+        *appended = if result.is_err() {
+            written
+        } else {
+            BTreeSet::new()
+        };
+        result
+    }
+}
+
+// RFC 4180 quoting for fields containing separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::intents::DuplicatePolicy;
+
+    #[tokio::test]
+    async fn appends_rows_to_daily_files() {
+        let dir = std::env::temp_dir().join(format!("telemetry-csv-{}", std::process::id()));
+        let archiver = CsvArchiver::new(&dir);
+
+        let measurement = |device_id: &str, timestamp, value| Intent::RecordMeasurement {
+            device_id: device_id.into(),
+            gateway_id: "gw1".into(),
+            value: Some(value),
+            timestamp,
+            on_duplicate: DuplicatePolicy::KeepLast,
+        };
+
+        archiver
+            .execute(vec![measurement("temp", 1_767_225_600_000, 21.5)])
+            .await
+            .unwrap();
+        archiver
+            .execute_batch(&[
+                vec![measurement("a,\"b\"", 1_767_225_601_000, 1.0)],
+                vec![measurement("temp", 1_767_312_000_000, 2.0)],
+            ])
+            .await
+            .unwrap();
+
+        let first = std::fs::read_to_string(dir.join("device_values-2026-01-01.csv")).unwrap();
+        let second = std::fs::read_to_string(dir.join("device_values-2026-01-02.csv")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            first,
+            "gateway_id,device_id,timestamp,value\n\
+             gw1,temp,1767225600000,21.5\n\
+             gw1,\"a,\"\"b\"\"\",1767225601000,1\n"
+        );
+        assert_eq!(second.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_a_failed_file_does_not_repeat_earlier_files() {
+        let dir = std::env::temp_dir().join(format!("telemetry-csv-retry-{}", std::process::id()));
+        let archiver = CsvArchiver::new(&dir);
+        let batch = [vec![
+            Intent::RecordMeasurement {
+                device_id: "temp".into(),
+                gateway_id: "gw1".into(),
+                value: Some(1.0),
+                timestamp: 1_767_225_600_000,
+                on_duplicate: DuplicatePolicy::KeepLast,
+            },
+            Intent::RecordMeasurement {
+                device_id: "temp".into(),
+                gateway_id: "gw1".into(),
+                value: Some(2.0),
+                timestamp: 1_767_312_000_000,
+                on_duplicate: DuplicatePolicy::KeepLast,
+            },
+        ]];
+
+        // A directory in place of the second day's file makes its append fail
+        let blocked = dir.join("device_values-2026-01-02.csv");
+        std::fs::create_dir_all(&blocked).unwrap();
+        let err = archiver.execute_batch(&batch).await.unwrap_err();
+        assert!(err.is_transient(), "{err}");
+
+        std::fs::remove_dir(&blocked).unwrap();
+        archiver.execute_batch(&batch).await.unwrap();
+
+        let first = std::fs::read_to_string(dir.join("device_values-2026-01-01.csv")).unwrap();
+        let second = std::fs::read_to_string(&blocked).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first.lines().count(), 2, "{first}");
+        assert_eq!(second.lines().count(), 2, "{second}");
+    }
+}
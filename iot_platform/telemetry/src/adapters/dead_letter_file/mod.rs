@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+use tracing::warn;
+
+use crate::{core::ports::dead_letter_port::DeadLetterPort, domain::dead_letter::DeadLetter};
+
+// Append-only JSON Lines file; kept outside the database so letters survive a DB outage
+pub struct FileDeadLetterStore {
+    pub path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileDeadLetterStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileDeadLetterStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl DeadLetterPort for FileDeadLetterStore {
+    async fn store(
+        &self,
+        letter: DeadLetter,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut line = serde_json::to_vec(&letter)?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let (letters, _) = self.read(&self.path).await?;
+        Ok(letters)
+    }
+
+    // The file is renamed before reading so letters written by a running service
+    // in the meantime go to a fresh file instead of being lost. A claimed file left by
+    // an interrupted replay is kept and the new letters are added to it.
+    async fn claim(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let claimed = self.claimed_path();
+
+        if fs::try_exists(&claimed).await? {
+            warn!(
+                "Resuming interrupted dead letter replay from {}",
+                claimed.display()
+            );
+            let pending = match fs::read(&self.path).await {
+                Ok(pending) => pending,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            };
+            if !pending.is_empty() {
+                let mut file = OpenOptions::new().append(true).open(&claimed).await?;
+                file.write_all(&pending).await?;
+                file.flush().await?;
+                fs::remove_file(&self.path).await?;
+            }
+        } else {
+            match fs::rename(&self.path, &claimed).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let (letters, _) = self.read(&claimed).await?;
+        Ok(letters)
+    }
+
+    async fn release(
+        &self,
+        unprocessed: Vec<DeadLetter>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let claimed = self.claimed_path();
+
+        // Lines that cannot be parsed are put back untouched for manual inspection
+        let (_, unreadable) = self.read(&claimed).await?;
+        let mut lines = Vec::new();
+        for line in unreadable {
+            lines.extend_from_slice(line.as_bytes());
+            lines.push(b'\n');
+        }
+        for letter in &unprocessed {
+            lines.extend(serde_json::to_vec(letter)?);
+            lines.push(b'\n');
+        }
+
+        if !lines.is_empty() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            file.write_all(&lines).await?;
+            file.flush().await?;
+        }
+
+        match fs::remove_file(&claimed).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FileDeadLetterStore {
+    fn claimed_path(&self) -> PathBuf {
+        self.path.with_extension("replaying")
+    }
+
+    async fn read(
+        &self,
+        path: &PathBuf,
+    ) -> Result<(Vec<DeadLetter>, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
+        let content = match fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((Vec::new(), Vec::new()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut letters = Vec::new();
+        let mut unreadable = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(letter) => letters.push(letter),
+                Err(e) => {
+                    warn!(
+                        "Skipping unreadable dead letter at {}:{}: {}",
+                        path.display(),
+                        n + 1,
+                        e
+                    );
+                    unreadable.push(line.to_string());
+                }
+            }
+        }
+
+        Ok((letters, unreadable))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::dead_letter::{FailureStage, RawMessage};
+
+    #[tokio::test]
+    async fn store_list_and_claim_round_trip() {
+        let path = std::env::temp_dir().join(format!("dead-letters-{}.jsonl", std::process::id()));
+        let store = FileDeadLetterStore::new(&path);
+        let raw = RawMessage {
+            topic: "gw/devices/1/value".into(),
+            payload: b"{not json".to_vec(),
+        };
+
+        store
+            .store(DeadLetter::new(&raw, FailureStage::Parse, "bad".into(), 1))
+            .await
+            .unwrap();
+        store
+            .store(DeadLetter::new(
+                &raw,
+                FailureStage::Storage,
+                "down".into(),
+                5,
+            ))
+            .await
+            .unwrap();
+
+        let letters = store.list().await.unwrap();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[1].stage, FailureStage::Storage);
+        assert_eq!(letters[0].raw().payload, raw.payload);
+
+        assert_eq!(store.claim().await.unwrap().len(), 2);
+        store.release(Vec::new()).await.unwrap();
+        assert!(store.list().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn interrupted_replay_is_claimed_again() {
+        let path = std::env::temp_dir().join(format!(
+            "dead-letters-interrupted-{}.jsonl",
+            std::process::id()
+        ));
+        let store = FileDeadLetterStore::new(&path);
+        let letter = |topic: &str| {
+            let raw = RawMessage {
+                topic: topic.into(),
+                payload: b"{".to_vec(),
+            };
+            DeadLetter::new(&raw, FailureStage::Parse, "eof".into(), 1)
+        };
+
+        store.store(letter("a")).await.unwrap();
+        store.store(letter("b")).await.unwrap();
+        // Replay stops without releasing; a new letter arrives meanwhile
+        assert_eq!(store.claim().await.unwrap().len(), 2);
+        store.store(letter("c")).await.unwrap();
+
+        let claimed = store.claim().await.unwrap();
+        let topics: Vec<_> = claimed.iter().map(|l| l.topic.as_str()).collect();
+        assert_eq!(topics, ["a", "b", "c"]);
+        assert!(store.list().await.unwrap().is_empty());
+
+        // The letters not handled go back to the store
+        store.release(claimed[1..].to_vec()).await.unwrap();
+        let topics: Vec<_> = store
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|l| l.topic)
+            .collect();
+        assert_eq!(topics, ["b", "c"]);
+        assert!(!path.with_extension("replaying").exists());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+
+use crate::{
+    config::RetryConfig,
+    core::{
+        metrics::Metrics,
+        ports::telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    },
+    domain::intents::Intent,
+};
+
+// A secondary storage fed by FanOutStorage
+pub struct Sink {
+    pub name: &'static str,
+    pub storage: Box<dyn TelemetryStoragePort>,
+}
+
+struct SinkQueue {
+    name: &'static str,
+    tx: mpsc::Sender<Vec<Vec<Intent>>>,
+}
+
+// Writes to the primary storage and, once that succeeded, hands the batch to every
+// secondary sink. Each sink has its own queue and worker that retries on its own, so a
+// slow or failing sink never blocks ingestion or the other sinks; when its queue is
+// full the batch is dropped for that sink only.
+pub struct FanOutStorage {
+    primary: Box<dyn TelemetryStoragePort>,
+    sinks: Vec<SinkQueue>,
+    metrics: Metrics,
+}
+
+impl FanOutStorage {
+    // The workers stop once the FanOutStorage is dropped and their queues are drained
+    pub fn spawn(
+        primary: Box<dyn TelemetryStoragePort>,
+        sinks: Vec<Sink>,
+        queue_size: usize,
+        retry: RetryConfig,
+        metrics: Metrics,
+    ) -> (Self, Vec<JoinHandle<()>>) {
+        let mut queues = Vec::with_capacity(sinks.len());
+        let mut workers = Vec::with_capacity(sinks.len());
+
+        for sink in sinks {
+            let (tx, rx) = mpsc::channel(queue_size);
+            queues.push(SinkQueue {
+                name: sink.name,
+                tx,
+            });
+            workers.push(tokio::spawn(run_sink(sink, rx, retry, metrics.clone())));
+        }
+
+        (
+            FanOutStorage {
+                primary,
+                sinks: queues,
+                metrics,
+            },
+            workers,
+        )
+    }
+
+    fn forward(&self, batch: &[Vec<Intent>]) {
+        for sink in &self.sinks {
+            match sink.tx.try_send(batch.to_vec()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    warn!(
+                        sink = sink.name,
+                        "Sink queue full, batch dropped for this sink"
+                    );
+                    self.metrics.sink_dropped(sink.name, "queue_full");
+                }
+                Err(TrySendError::Closed(_)) => {
+                    self.metrics.sink_dropped(sink.name, "closed");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for FanOutStorage {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    // Only committed batches are forwarded, so retries of the primary do not reach the sinks twice
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        self.primary.execute_batch(batch).await?;
+        self.forward(batch);
+        Ok(())
+    }
+}
+
+async fn run_sink(
+    sink: Sink,
+    mut rx: mpsc::Receiver<Vec<Vec<Intent>>>,
+    retry: RetryConfig,
+    metrics: Metrics,
+) {
+    let max_backoff = Duration::from_millis(retry.max_backoff_ms);
+
+    while let Some(batch) = rx.recv().await {
+        let mut backoff = Duration::from_millis(retry.initial_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            match sink.storage.execute_batch(&batch).await {
+                Ok(()) => {
+                    metrics.sink_written(sink.name, true);
+                    break;
+                }
+                Err(e) if e.is_transient() && attempt < retry.max_attempts => {
+                    warn!(sink = sink.name, error = %e, attempt, "Sink write failed, retrying in {:?}", backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(max_backoff);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    error!(sink = sink.name, error = %e, attempt, "Sink write failed, batch dropped");
+                    metrics.sink_written(sink.name, false);
+                    metrics.sink_dropped(sink.name, "failed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::memory_storage::MemoryStorage;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    // Fails with a transient error the first `failures` times, then counts written batches
+    struct FlakySink {
+        failures: AtomicU32,
+        written: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl TelemetryStoragePort for FlakySink {
+        async fn execute(&self, _intents: Vec<Intent>) -> Result<(), StorageError> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(StorageError::Transient("sink unavailable".into()));
+            }
+            self.written.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct BrokenSink;
+
+    #[async_trait]
+    impl TelemetryStoragePort for BrokenSink {
+        async fn execute(&self, _intents: Vec<Intent>) -> Result<(), StorageError> {
+            Err(StorageError::Permanent("rejected".into()))
+        }
+    }
+
+    fn retry() -> RetryConfig {
+        RetryConfig {
+            max_attempts: 3,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+        }
+    }
+
+    fn gateway(id: &str) -> Vec<Intent> {
+        vec![Intent::EnsureGatewayExists {
+            gateway_id: id.into(),
+            gateway_name: id.into(),
+        }]
+    }
+
+    #[tokio::test]
+    async fn failing_sink_does_not_affect_primary_or_other_sinks() {
+        let written = Arc::new(AtomicU32::new(0));
+        let sinks = vec![
+            Sink {
+                name: "broken",
+                storage: Box::new(BrokenSink),
+            },
+            Sink {
+                name: "flaky",
+                storage: Box::new(FlakySink {
+                    failures: AtomicU32::new(2),
+                    written: written.clone(),
+                }),
+            },
+        ];
+        let metrics = Metrics::new();
+        let (storage, workers) = FanOutStorage::spawn(
+            Box::new(MemoryStorage::new()),
+            sinks,
+            10,
+            retry(),
+            metrics.clone(),
+        );
+
+        storage.execute(gateway("gw1")).await.unwrap();
+        drop(storage);
+        for worker in workers {
+            worker.await.unwrap();
+        }
+
+        assert_eq!(written.load(Ordering::SeqCst), 1);
+        let text = metrics.render();
+        assert!(text.contains(r#"telemetry_sink_dropped_total{reason="failed",sink="broken"} 1"#));
+        assert!(text.contains(r#"telemetry_sink_writes_total{result="success",sink="flaky"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn primary_failure_is_returned_and_not_forwarded() {
+        let written = Arc::new(AtomicU32::new(0));
+        let sinks = vec![Sink {
+            name: "flaky",
+            storage: Box::new(FlakySink {
+                failures: AtomicU32::new(0),
+                written: written.clone(),
+            }),
+        }];
+        let (storage, workers) =
+            FanOutStorage::spawn(Box::new(BrokenSink), sinks, 10, retry(), Metrics::new());
+
+        assert!(storage.execute(gateway("gw1")).await.is_err());
+        drop(storage);
+        for worker in workers {
+            worker.await.unwrap();
+        }
+
+        assert_eq!(written.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,104 @@
+pub mod query;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+    routing::get,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::info;
+
+use crate::{
+    config::HttpConfig,
+    core::{health::Health, metrics::Metrics, ports::telemetry_query_port::TelemetryQueryPort},
+};
+
+const DB_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct HttpState {
+    pub metrics: Metrics,
+    pub health: Health,
+    pub query: Arc<dyn TelemetryQueryPort>,
+    pub max_query_limit: usize,
+}
+
+pub fn router(state: HttpState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/metrics", get(metrics))
+        .route("/api/gateways", get(query::list_gateways))
+        .route("/api/devices", get(query::list_devices))
+        .route(
+            "/api/gateways/{gateway_id}/devices",
+            get(query::list_gateway_devices),
+        )
+        .route(
+            "/api/gateways/{gateway_id}/devices/{device_id}/measurements",
+            get(query::measurements),
+        )
+        .route(
+            "/api/gateways/{gateway_id}/devices/{device_id}/windows",
+            get(query::windows),
+        )
+        .route(
+            "/api/gateways/{gateway_id}/devices/{device_id}/aggregate",
+            get(query::aggregate),
+        )
+        .route("/api/alarms", get(query::alarms))
+        .with_state(state)
+}
+
+// Runs the HTTP server (health, readiness, metrics and the query API)
+pub async fn serve(
+    config: HttpConfig,
+    state: HttpState,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr = format!("{}:{}", config.host, config.port);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!("HTTP server listening on {}", addr);
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            shutdown.recv().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+// Liveness: the process is running and able to answer
+async fn health() -> Json<Value> {
+    Json(serde_json::json!({ "status": "up" }))
+}
+
+// Readiness: connected to the broker and the database is reachable. The database is
+// probed on every call, so an idle service notices both an outage and the recovery.
+async fn ready(State(state): State<HttpState>) -> impl IntoResponse {
+    let reachable = matches!(
+        tokio::time::timeout(DB_PROBE_TIMEOUT, state.query.ping()).await,
+        Ok(Ok(()))
+    );
+    state.health.set_db_available(reachable);
+
+    let status = if state.health.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(state.health.report()))
+}
+
+async fn metrics(State(state): State<HttpState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
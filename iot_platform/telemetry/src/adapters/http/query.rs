@@ -0,0 +1,229 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use shared_models::AggregateWindow;
+use tracing::error;
+
+use super::HttpState;
+use crate::domain::queries::{
+    AggregateBucket, AggregateQuery, AlarmQuery, AlarmState, DeviceRecord, GatewayRecord,
+    Measurement, MeasurementQuery, StoredAlarm,
+};
+
+const DEFAULT_RANGE_MS: i64 = 60 * 60 * 1000;
+const DEFAULT_LIMIT: usize = 1000;
+const DEFAULT_BUCKET_MS: i64 = 60 * 1000;
+// Later bounds are clamped, the storage adapters cannot represent them
+const MAX_TIMESTAMP_MS: i64 = chrono::DateTime::<chrono::Utc>::MAX_UTC.timestamp_millis();
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceFilter {
+    pub gateway_id: Option<String>,
+}
+
+// Epoch milliseconds; defaults to the last hour
+#[derive(Debug, Deserialize)]
+pub struct RangeParams {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<usize>,
+    pub bucket_ms: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlarmParams {
+    pub gateway_id: Option<String>,
+    pub device_id: Option<String>,
+    pub state: Option<String>,
+    pub limit: Option<usize>,
+}
+
+pub async fn list_gateways(State(state): State<HttpState>) -> ApiResult<Vec<GatewayRecord>> {
+    state
+        .query
+        .list_gateways()
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+pub async fn list_devices(
+    State(state): State<HttpState>,
+    Query(filter): Query<DeviceFilter>,
+) -> ApiResult<Vec<DeviceRecord>> {
+    state
+        .query
+        .list_devices(filter.gateway_id.as_deref())
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+pub async fn list_gateway_devices(
+    State(state): State<HttpState>,
+    Path(gateway_id): Path<String>,
+) -> ApiResult<Vec<DeviceRecord>> {
+    state
+        .query
+        .list_devices(Some(&gateway_id))
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+pub async fn measurements(
+    State(state): State<HttpState>,
+    Path((gateway_id, device_id)): Path<(String, String)>,
+    Query(params): Query<RangeParams>,
+) -> ApiResult<Vec<Measurement>> {
+    let (from, to) = time_range(&params)?;
+    let limit = limit(params.limit, state.max_query_limit)?;
+
+    let query = MeasurementQuery {
+        gateway_id,
+        device_id,
+        from,
+        to,
+        limit,
+    };
+
+    state
+        .query
+        .measurements(&query)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+// Windows aggregated by the gateway, as opposed to buckets computed from raw values
+pub async fn windows(
+    State(state): State<HttpState>,
+    Path((gateway_id, device_id)): Path<(String, String)>,
+    Query(params): Query<RangeParams>,
+) -> ApiResult<Vec<AggregateWindow>> {
+    let (from, to) = time_range(&params)?;
+    let limit = limit(params.limit, state.max_query_limit)?;
+
+    let query = MeasurementQuery {
+        gateway_id,
+        device_id,
+        from,
+        to,
+        limit,
+    };
+
+    state
+        .query
+        .windows(&query)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+pub async fn aggregate(
+    State(state): State<HttpState>,
+    Path((gateway_id, device_id)): Path<(String, String)>,
+    Query(params): Query<RangeParams>,
+) -> ApiResult<Vec<AggregateBucket>> {
+    let (from, to) = time_range(&params)?;
+    let limit = limit(params.limit, state.max_query_limit)?;
+
+    let bucket_ms = params.bucket_ms.unwrap_or(DEFAULT_BUCKET_MS);
+    if bucket_ms < 1000 {
+        return Err(bad_request("bucket_ms must be at least 1000"));
+    }
+
+    // Rejects ranges that would be silently truncated by the limit. Buckets are aligned
+    // to the epoch, so an unaligned `from` lies inside the first one.
+    let first_bucket = from - from.rem_euclid(bucket_ms);
+    let buckets = (to - first_bucket - 1) / bucket_ms + 1;
+    if buckets > limit as i64 {
+        return Err(bad_request(format!(
+            "range covers {} buckets, more than the limit of {}; use a larger bucket_ms",
+            buckets, limit
+        )));
+    }
+
+    let query = AggregateQuery {
+        gateway_id,
+        device_id,
+        from,
+        to,
+        bucket_ms,
+        limit,
+    };
+
+    state
+        .query
+        .aggregate(&query)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+// Alarm history reported by the gateways; acknowledging happens on the gateway API
+pub async fn alarms(
+    State(state): State<HttpState>,
+    Query(params): Query<AlarmParams>,
+) -> ApiResult<Vec<StoredAlarm>> {
+    let alarm_state = match params.state.as_deref() {
+        None => None,
+        Some(s) => Some(
+            AlarmState::parse(s)
+                .ok_or_else(|| bad_request("state must be active, acknowledged or cleared"))?,
+        ),
+    };
+
+    let query = AlarmQuery {
+        gateway_id: params.gateway_id,
+        device_id: params.device_id,
+        state: alarm_state,
+        limit: limit(params.limit, state.max_query_limit)?,
+    };
+
+    state.query.alarms(&query).await.map(Json).map_err(internal)
+}
+
+fn time_range(params: &RangeParams) -> Result<(i64, i64), (StatusCode, String)> {
+    let to = params
+        .to
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+        .min(MAX_TIMESTAMP_MS);
+    let from = params
+        .from
+        .unwrap_or_else(|| to.saturating_sub(DEFAULT_RANGE_MS).max(0));
+
+    if from < 0 {
+        return Err(bad_request("from must not be negative"));
+    }
+    if from >= to {
+        return Err(bad_request("from must be before to"));
+    }
+
+    Ok((from, to))
+}
+
+fn limit(requested: Option<usize>, max: usize) -> Result<usize, (StatusCode, String)> {
+    match requested.unwrap_or(DEFAULT_LIMIT.min(max)) {
+        0 => Err(bad_request("limit must be positive")),
+        limit if limit > max => Err(bad_request(format!("limit must not exceed {}", max))),
+        limit => Ok(limit),
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+fn internal(e: Box<dyn std::error::Error + Send + Sync>) -> (StatusCode, String) {
+    error!("Query failed: {:?}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "query failed".to_string(),
+    )
+}
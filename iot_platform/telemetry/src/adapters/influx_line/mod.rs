@@ -0,0 +1,237 @@
+use async_trait::async_trait;
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    Request,
+    body::Bytes,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use tracing::debug;
+
+use crate::{
+    config::InfluxConfig,
+    core::ports::telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    domain::intents::Intent,
+};
+
+// Writes measurements to an InfluxDB-compatible HTTP endpoint in line protocol;
+// device and gateway lifecycle intents have no counterpart there and are skipped
+pub struct InfluxLineWriter {
+    config: InfluxConfig,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl InfluxLineWriter {
+    pub fn new(config: InfluxConfig) -> Self {
+        InfluxLineWriter {
+            config,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        }
+    }
+
+    async fn post(&self, body: String) -> Result<(), StorageError> {
+        let mut request =
+            Request::post(&self.config.url).header(CONTENT_TYPE, "text/plain; charset=utf-8");
+        if let Some(token) = &self.config.token {
+            request = request.header(AUTHORIZATION, format!("Token {}", token));
+        }
+        let request = request
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| StorageError::Permanent(format!("influx request: {}", e)))?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| StorageError::Transient(format!("influx write: {}", e)))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map(|b| String::from_utf8_lossy(&b.to_bytes()).into_owned())
+            .unwrap_or_default();
+        let message = format!("influx write: {} {}", status, body.trim());
+
+        // Rate limiting and server errors may pass, a rejected payload will not
+        if status.is_server_error() || status.as_u16() == 429 {
+            Err(StorageError::Transient(message))
+        } else {
+            Err(StorageError::Permanent(message))
+        }
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for InfluxLineWriter {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    // One request per batch; points with the same series and timestamp overwrite each other
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        let body = to_line_protocol(&self.config.measurement, batch.iter().flatten());
+        if body.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Writing {} points to InfluxDB", body.lines().count());
+        self.post(body).await
+    }
+}
+
+// `<measurement>,gateway_id=..,device_id=.. value=<f64> <epoch ms>`, one line per measurement;
+// values Influx cannot represent (missing, NaN, infinite) are left out
+pub fn to_line_protocol<'a>(
+    measurement: &str,
+    intents: impl IntoIterator<Item = &'a Intent>,
+) -> String {
+    let mut body = String::new();
+
+    for intent in intents {
+        if let Intent::RecordMeasurement {
+            device_id,
+            gateway_id,
+            value: Some(value),
+            timestamp,
+            ..
+        } = intent
+            && value.is_finite()
+        {
+            body.push_str(&format!(
+                "{},gateway_id={},device_id={} value={:?} {}\n",
+                escape(measurement, ", "),
+                escape(gateway_id, ",= "),
+                escape(device_id, ",= "),
+                value,
+                timestamp
+            ));
+        }
+    }
+
+    body
+}
+
+fn escape(value: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::intents::DuplicatePolicy;
+    use axum::{Router, http::StatusCode, routing::post};
+    use std::sync::{Arc, Mutex};
+
+    fn measurement(device_id: &str, value: Option<f64>) -> Intent {
+        Intent::RecordMeasurement {
+            device_id: device_id.into(),
+            gateway_id: "gw 1".into(),
+            value,
+            timestamp: 1_700_000_000_000,
+            on_duplicate: DuplicatePolicy::KeepLast,
+        }
+    }
+
+    #[test]
+    fn renders_measurements_and_escapes_tags() {
+        let intents = [
+            Intent::EnsureGatewayExists {
+                gateway_id: "gw 1".into(),
+                gateway_name: "Gateway".into(),
+            },
+            measurement("temp,a=b", Some(21.0)),
+            measurement("temp", None),
+            measurement("temp", Some(f64::NAN)),
+        ];
+
+        let body = to_line_protocol("device values", &intents);
+
+        assert_eq!(
+            body,
+            "device\\ values,gateway_id=gw\\ 1,device_id=temp\\,a\\=b value=21.0 1700000000000\n"
+        );
+    }
+
+    // Accepts the first write with a token, then answers with the given error status
+    async fn fake_influx(error: StatusCode) -> (String, Arc<Mutex<Vec<String>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let state = received.clone();
+        let app = Router::new().route(
+            "/api/v2/write",
+            post(
+                move |headers: axum::http::HeaderMap, body: String| async move {
+                    let mut received = state.lock().unwrap();
+                    if !received.is_empty() {
+                        return error;
+                    }
+                    assert_eq!(headers[AUTHORIZATION], "Token secret");
+                    received.push(body);
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/api/v2/write?bucket=telemetry&precision=ms",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, received)
+    }
+
+    fn influx_writer(url: String) -> InfluxLineWriter {
+        InfluxLineWriter::new(InfluxConfig {
+            url,
+            token: Some("secret".into()),
+            measurement: "device_values".into(),
+        })
+    }
+
+    #[tokio::test]
+    async fn posts_batch_and_classifies_errors() {
+        let (url, received) = fake_influx(StatusCode::SERVICE_UNAVAILABLE).await;
+        let writer = influx_writer(url);
+
+        writer
+            .execute(vec![measurement("temp", Some(1.5))])
+            .await
+            .unwrap();
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let err = writer
+            .execute(vec![measurement("temp", Some(2.5))])
+            .await
+            .unwrap_err();
+        assert!(err.is_transient(), "{err}");
+
+        let (url, _) = fake_influx(StatusCode::BAD_REQUEST).await;
+        let writer = influx_writer(url);
+        writer
+            .execute(vec![measurement("temp", Some(1.0))])
+            .await
+            .unwrap();
+        let err = writer
+            .execute(vec![measurement("temp", Some(2.0))])
+            .await
+            .unwrap_err();
+        assert!(!err.is_transient(), "{err}");
+    }
+}
@@ -0,0 +1,545 @@
+use async_trait::async_trait;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    core::ports::{
+        telemetry_query_port::TelemetryQueryPort,
+        telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    },
+    domain::{
+        intents::Intent,
+        queries::{
+            AggregateBucket, AggregateQuery, AlarmQuery, AlarmState, DeviceRecord, GatewayRecord,
+            Measurement, MeasurementQuery, StoredAlarm,
+        },
+    },
+};
+use shared_models::{AggregateWindow, AlarmRecord};
+
+type DeviceKey = (String, String);
+// Gateway id and alarm id, mapped to the device and the merged record
+type AlarmKey = (String, String);
+type Alarms = BTreeMap<AlarmKey, (String, AlarmRecord)>;
+// Edge windows per device by window start
+type Windows = BTreeMap<DeviceKey, BTreeMap<i64, AggregateWindow>>;
+
+#[derive(Debug, Clone, Default)]
+struct DeviceRow {
+    name: Option<String>,
+    created_at: Option<i64>,
+    removed_at: Option<i64>,
+    last_seen: Option<i64>,
+}
+
+// A reading refused by the processor, see Intent::RejectMeasurement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub gateway_id: String,
+    pub device_id: String,
+    pub timestamp: i64,
+    pub value: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    gateways: BTreeMap<String, String>,
+    devices: BTreeMap<DeviceKey, DeviceRow>,
+    values: BTreeMap<DeviceKey, BTreeMap<i64, f64>>,
+    rejections: Vec<Rejection>,
+    alarms: Alarms,
+    windows: Windows,
+}
+
+// Previous state of every row a batch overwrote, None for rows it created
+#[derive(Default)]
+struct UndoLog {
+    gateways: Vec<(String, Option<String>)>,
+    devices: Vec<(DeviceKey, Option<DeviceRow>)>,
+    alarms: Vec<(AlarmKey, Option<(String, AlarmRecord)>)>,
+    windows: Vec<(DeviceKey, i64, Option<AggregateWindow>)>,
+}
+
+impl UndoLog {
+    fn save_device(
+        &mut self,
+        devices: &BTreeMap<DeviceKey, DeviceRow>,
+        gateway_id: &str,
+        device_id: &str,
+    ) {
+        let key = (gateway_id.to_string(), device_id.to_string());
+        let previous = devices.get(&key).cloned();
+        self.devices.push((key, previous));
+    }
+
+    // Newest entries first, so a row changed twice ends up in its state before the batch
+    fn rollback(self, tables: &mut Tables) {
+        for (key, previous) in self.gateways.into_iter().rev() {
+            restore(&mut tables.gateways, key, previous);
+        }
+        for (key, previous) in self.devices.into_iter().rev() {
+            restore(&mut tables.devices, key, previous);
+        }
+        for (key, previous) in self.alarms.into_iter().rev() {
+            restore(&mut tables.alarms, key, previous);
+        }
+        for (key, start, previous) in self.windows.into_iter().rev() {
+            if let Some(device_windows) = tables.windows.get_mut(&key) {
+                restore(device_windows, start, previous);
+                if device_windows.is_empty() {
+                    tables.windows.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+fn restore<K: Ord, V>(table: &mut BTreeMap<K, V>, key: K, previous: Option<V>) {
+    match previous {
+        Some(value) => table.insert(key, value),
+        None => table.remove(&key),
+    };
+}
+
+// Keeps everything in process memory with the same semantics as the SQL adapters,
+// including foreign keys; meant for tests and demos. Clones share the same data.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Rejected readings in the order they were stored
+    pub fn rejections(&self) -> Vec<Rejection> {
+        self.tables.lock().unwrap().rejections.clone()
+    }
+
+    // Measurements and rejections are only added once every other intent succeeded,
+    // so the undo log covers the tables changed by `apply`
+    fn write(
+        tables: &mut Tables,
+        undo: &mut UndoLog,
+        batch: &[Vec<Intent>],
+    ) -> Result<(), StorageError> {
+        let mut measurements = Vec::new();
+        let mut rejections = Vec::new();
+
+        for intent in batch.iter().flatten() {
+            match intent {
+                Intent::RecordMeasurement { .. } => measurements.push(intent),
+                Intent::RejectMeasurement {
+                    device_id,
+                    gateway_id,
+                    value,
+                    timestamp,
+                    reason,
+                } => rejections.push(Rejection {
+                    gateway_id: gateway_id.clone(),
+                    device_id: device_id.clone(),
+                    timestamp: *timestamp,
+                    value: *value,
+                    reason: reason.clone(),
+                }),
+                _ => Self::apply(tables, undo, intent)?,
+            }
+        }
+
+        // Resolved against the stored values first, applied only when none is rejected
+        let mut pending: BTreeMap<(DeviceKey, i64), f64> = BTreeMap::new();
+        for intent in measurements {
+            let Intent::RecordMeasurement {
+                device_id,
+                gateway_id,
+                value,
+                timestamp,
+                on_duplicate,
+            } = intent
+            else {
+                continue;
+            };
+
+            let key = (gateway_id.clone(), device_id.clone());
+            if !tables.devices.contains_key(&key) {
+                return Err(StorageError::Permanent(format!(
+                    "record_measurement: unknown device {}",
+                    device_id
+                )));
+            }
+            let incoming = value.ok_or_else(|| {
+                StorageError::Permanent(format!(
+                    "record_measurement: missing value for device {}",
+                    device_id
+                ))
+            })?;
+
+            let existing = pending
+                .get(&(key.clone(), *timestamp))
+                .copied()
+                .or_else(|| {
+                    tables
+                        .values
+                        .get(&key)
+                        .and_then(|values| values.get(timestamp))
+                        .copied()
+                });
+            let value = match existing {
+                Some(existing) => on_duplicate.resolve(existing, incoming).ok_or_else(|| {
+                    StorageError::Permanent(format!(
+                        "record_measurement: value {} for device {} at {} conflicts with {}",
+                        incoming, device_id, timestamp, existing
+                    ))
+                })?,
+                None => incoming,
+            };
+            pending.insert((key, *timestamp), value);
+        }
+
+        tables.rejections.extend(rejections);
+        for ((key, timestamp), value) in pending {
+            tables
+                .values
+                .entry(key)
+                .or_default()
+                .insert(timestamp, value);
+        }
+
+        Ok(())
+    }
+
+    // Executes a single non-measurement intent, saving what it overwrites in `undo`
+    fn apply(tables: &mut Tables, undo: &mut UndoLog, intent: &Intent) -> Result<(), StorageError> {
+        let Tables {
+            gateways,
+            devices,
+            alarms,
+            windows,
+            ..
+        } = tables;
+
+        match intent {
+            Intent::EnsureGatewayExists {
+                gateway_id,
+                gateway_name,
+            } => {
+                let previous = gateways.insert(gateway_id.clone(), gateway_name.clone());
+                undo.gateways.push((gateway_id.clone(), previous));
+            }
+
+            Intent::EnsureDeviceExists {
+                device_id,
+                gateway_id,
+            } => {
+                if !gateways.contains_key(gateway_id) {
+                    return Err(StorageError::Permanent(format!(
+                        "ensure_device_exists: unknown gateway {}",
+                        gateway_id
+                    )));
+                }
+                let key = (gateway_id.clone(), device_id.clone());
+                undo.devices.push((key.clone(), devices.get(&key).cloned()));
+                devices.entry(key).or_default();
+            }
+
+            Intent::UpsertDeviceMetadata {
+                device_id,
+                gateway_id,
+                gateway_name,
+                device_name,
+                timestamp,
+            } => {
+                undo.save_device(devices, gateway_id, device_id);
+                if let Some(device) = devices.get_mut(&(gateway_id.clone(), device_id.clone())) {
+                    device.name = Some(device_name.clone());
+                    device.last_seen =
+                        Some(device.last_seen.map_or(*timestamp, |t| t.max(*timestamp)));
+                    if device
+                        .removed_at
+                        .is_some_and(|removed| removed < *timestamp)
+                    {
+                        device.removed_at = None;
+                    }
+                    device.created_at = device.created_at.or(Some(*timestamp));
+                }
+                if let Some(name) = gateways.get_mut(gateway_id) {
+                    let previous = std::mem::replace(name, gateway_name.clone());
+                    undo.gateways.push((gateway_id.clone(), Some(previous)));
+                }
+            }
+
+            Intent::UpdateDeviceLastSeen {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                undo.save_device(devices, gateway_id, device_id);
+                if let Some(device) = devices.get_mut(&(gateway_id.clone(), device_id.clone())) {
+                    device.last_seen =
+                        Some(device.last_seen.map_or(*timestamp, |t| t.max(*timestamp)));
+                }
+            }
+
+            Intent::ReactivateDevice {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                undo.save_device(devices, gateway_id, device_id);
+                if let Some(device) = devices.get_mut(&(gateway_id.clone(), device_id.clone()))
+                    && device
+                        .removed_at
+                        .is_some_and(|removed| removed < *timestamp)
+                {
+                    device.removed_at = None;
+                }
+            }
+
+            Intent::MarkDeviceRemoved {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                undo.save_device(devices, gateway_id, device_id);
+                if let Some(device) = devices.get_mut(&(gateway_id.clone(), device_id.clone()))
+                    && device.removed_at.is_none_or(|removed| removed < *timestamp)
+                {
+                    device.removed_at = Some(*timestamp);
+                }
+            }
+
+            Intent::RecordAlarm {
+                device_id,
+                gateway_id,
+                alarm,
+            } => {
+                if !devices.contains_key(&(gateway_id.clone(), device_id.clone())) {
+                    return Err(StorageError::Permanent(format!(
+                        "record_alarm: unknown device {}",
+                        device_id
+                    )));
+                }
+                let key = (gateway_id.clone(), alarm.id.clone());
+                undo.alarms.push((key.clone(), alarms.get(&key).cloned()));
+                let (_, stored) = alarms
+                    .entry(key)
+                    .or_insert_with(|| (device_id.clone(), alarm.clone()));
+                stored.acknowledged_at = stored.acknowledged_at.or(alarm.acknowledged_at);
+                stored.acknowledged_by = stored
+                    .acknowledged_by
+                    .clone()
+                    .or(alarm.acknowledged_by.clone());
+                stored.cleared_at = stored.cleared_at.or(alarm.cleared_at);
+            }
+
+            Intent::RecordAggregate {
+                device_id,
+                gateway_id,
+                window,
+            } => {
+                let key = (gateway_id.clone(), device_id.clone());
+                if !devices.contains_key(&key) {
+                    return Err(StorageError::Permanent(format!(
+                        "record_aggregate: unknown device {}",
+                        device_id
+                    )));
+                }
+                let previous = windows
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(window.start, window.clone());
+                undo.windows.push((key, window.start, previous));
+            }
+
+            // Measurements are applied last and rejections collected by execute_batch
+            Intent::RecordMeasurement { .. } | Intent::RejectMeasurement { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for MemoryStorage {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    // Changes are applied in place and undone when an intent fails, so a failing batch
+    // leaves nothing behind without copying the tables for every batch
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        let mut tables = self.tables.lock().unwrap();
+        let mut undo = UndoLog::default();
+
+        let result = Self::write(&mut tables, &mut undo, batch);
+        if result.is_err() {
+            undo.rollback(&mut tables);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl TelemetryQueryPort for MemoryStorage {
+    async fn list_gateways(
+        &self,
+    ) -> Result<Vec<GatewayRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables
+            .gateways
+            .iter()
+            .map(|(id, name)| GatewayRecord {
+                id: id.clone(),
+                name: name.clone(),
+            })
+            .collect())
+    }
+
+    async fn list_devices(
+        &self,
+        gateway_id: Option<&str>,
+    ) -> Result<Vec<DeviceRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables
+            .devices
+            .iter()
+            .filter(|((gw, _), _)| gateway_id.is_none_or(|id| id == gw))
+            .map(|((gw, id), row)| DeviceRecord {
+                gateway_id: gw.clone(),
+                id: id.clone(),
+                name: row.name.clone(),
+                created_at: row.created_at,
+                removed_at: row.removed_at,
+                last_seen: row.last_seen,
+            })
+            .collect())
+    }
+
+    async fn measurements(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<Measurement>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        let key = (query.gateway_id.clone(), query.device_id.clone());
+
+        Ok(tables
+            .values
+            .get(&key)
+            .map(|values| {
+                values
+                    .range(query.from..query.to)
+                    .take(query.limit)
+                    .map(|(&timestamp, &value)| Measurement { timestamp, value })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn windows(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<AggregateWindow>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        let key = (query.gateway_id.clone(), query.device_id.clone());
+
+        Ok(tables
+            .windows
+            .get(&key)
+            .map(|windows| {
+                windows
+                    .range(query.from..query.to)
+                    .take(query.limit)
+                    .map(|(_, window)| window.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn aggregate(
+        &self,
+        query: &AggregateQuery,
+    ) -> Result<Vec<AggregateBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        let key = (query.gateway_id.clone(), query.device_id.clone());
+        let Some(values) = tables.values.get(&key) else {
+            return Ok(Vec::new());
+        };
+
+        // Values are ordered by time, so the last one seen in a bucket is its latest
+        let mut buckets: Vec<(AggregateBucket, f64)> = Vec::new();
+        for (&timestamp, &value) in values.range(query.from..query.to) {
+            let bucket = timestamp.div_euclid(query.bucket_ms) * query.bucket_ms;
+            match buckets.last_mut() {
+                Some((b, sum)) if b.bucket == bucket => {
+                    b.min = b.min.min(value);
+                    b.max = b.max.max(value);
+                    b.last = value;
+                    b.count += 1;
+                    *sum += value;
+                }
+                _ => buckets.push((
+                    AggregateBucket {
+                        bucket,
+                        min: value,
+                        max: value,
+                        avg: value,
+                        last: value,
+                        count: 1,
+                    },
+                    value,
+                )),
+            }
+        }
+
+        Ok(buckets
+            .into_iter()
+            .take(query.limit)
+            .map(|(mut b, sum)| {
+                b.avg = sum / b.count as f64;
+                b
+            })
+            .collect())
+    }
+
+    async fn alarms(
+        &self,
+        query: &AlarmQuery,
+    ) -> Result<Vec<StoredAlarm>, Box<dyn std::error::Error + Send + Sync>> {
+        let tables = self.tables.lock().unwrap();
+        let mut alarms: Vec<StoredAlarm> = tables
+            .alarms
+            .iter()
+            .map(|((gateway_id, _), (device_id, alarm))| StoredAlarm {
+                gateway_id: gateway_id.clone(),
+                device_id: device_id.clone(),
+                id: alarm.id.clone(),
+                kind: alarm.kind.as_str().to_string(),
+                severity: alarm.severity.as_str().to_string(),
+                state: AlarmState::of(alarm.acknowledged_at, alarm.cleared_at),
+                value: alarm.value,
+                message: alarm.message.clone(),
+                raised_at: alarm.raised_at,
+                acknowledged_at: alarm.acknowledged_at,
+                acknowledged_by: alarm.acknowledged_by.clone(),
+                cleared_at: alarm.cleared_at,
+            })
+            .filter(|a| {
+                query
+                    .gateway_id
+                    .as_ref()
+                    .is_none_or(|id| *id == a.gateway_id)
+                    && query.device_id.as_ref().is_none_or(|id| *id == a.device_id)
+                    && query.state.is_none_or(|state| state == a.state)
+            })
+            .collect();
+
+        alarms.sort_by(|a, b| b.raised_at.cmp(&a.raised_at).then_with(|| a.id.cmp(&b.id)));
+        alarms.truncate(query.limit);
+        Ok(alarms)
+    }
+}
@@ -1,2 +1,9 @@
+pub mod csv_archive;
+pub mod dead_letter_file;
+pub mod fanout_storage;
+pub mod http;
+pub mod influx_line;
+pub mod memory_storage;
 pub mod mqtt;
 pub mod postgres_storage;
+pub mod sqlite_storage;
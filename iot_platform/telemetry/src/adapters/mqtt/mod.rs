@@ -1,26 +1,63 @@
-use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, Publish, QoS};
 use shared_models::{
-    DeviceCreatedPayload, DeviceRemovedPayload, DeviceValueObservedPayload, TelemetryMessage,
+    AlarmPayload, DeviceAggregatePayload, DeviceCreatedPayload, DeviceRemovedPayload,
+    DeviceValueObservedPayload, TelemetryMessage,
 };
-use std::{error::Error, time::Duration};
-use tracing::info;
+use std::{collections::VecDeque, error::Error, sync::Arc, time::Duration};
+use tokio::sync::{broadcast, oneshot};
+use tracing::{error, info, warn};
 
-use crate::{config::MqttConfig, core::ports::telemetry_input_port::TelemetryInputPort};
+use crate::{
+    config::MqttConfig,
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{
+            dead_letter_port::DeadLetterPort,
+            telemetry_input_port::{Completion, TelemetryInputPort},
+        },
+    },
+    domain::dead_letter::{DeadLetter, FailureStage, RawMessage},
+};
+
+const TOPIC_FILTER: &str = "+/devices/#";
+// How long shutdown waits for queued messages to be stored and acknowledged
+const ACK_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Received messages in arrival order, each with the signal that it has been stored
+type PendingAcks = VecDeque<(Publish, oneshot::Receiver<()>)>;
 
 pub struct MqttAdapter {
     pub config: MqttConfig,
     pub input_port: Box<dyn TelemetryInputPort>,
+    pub dead_letters: Arc<dyn DeadLetterPort>,
+    pub metrics: Metrics,
+    pub health: Health,
 }
 
 impl MqttAdapter {
     pub async fn new(
         config: MqttConfig,
         input_port: Box<dyn TelemetryInputPort>,
+        dead_letters: Arc<dyn DeadLetterPort>,
+        metrics: Metrics,
+        health: Health,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(MqttAdapter { config, input_port })
+        Ok(MqttAdapter {
+            config,
+            input_port,
+            dead_letters,
+            metrics,
+            health,
+        })
     }
 
-    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Runs until shutdown; broker errors are retried with backoff, rumqttc reconnects
+    // on the next poll and the subscription is renewed after every ConnAck
+    pub async fn run(
+        &self,
+        mut shutdown: broadcast::Receiver<()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut mqttoptions = MqttOptions::new(
             &self.config.client_id,
             &self.config.mqtt_host,
@@ -28,54 +65,214 @@ impl MqttAdapter {
         );
 
         mqttoptions.set_keep_alive(Duration::from_secs(5));
+        // Persistent session: the broker queues QoS 1 messages while we are disconnected
+        mqttoptions.set_clean_session(self.config.clean_session);
+        // Messages are acknowledged only once stored or dead-lettered
+        mqttoptions.set_manual_acks(true);
 
-        let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+        let (client, mut eventloop) = AsyncClient::new(mqttoptions, 100);
 
-        client.subscribe("+/devices/#", QoS::AtLeastOnce).await?;
+        let initial_backoff = Duration::from_millis(self.config.reconnect_initial_ms);
+        let max_backoff = Duration::from_millis(self.config.reconnect_max_ms);
+        let mut backoff = initial_backoff;
+        let mut pending = PendingAcks::new();
 
         loop {
-            match eventloop.poll().await {
-                Ok(Event::Incoming(Packet::Publish(p))) => {
-                    let payload = &p.payload;
+            let event = tokio::select! {
+                _ = shutdown.recv() => break,
+                stored = next_stored(&mut pending) => {
+                    acknowledge(&client, pending.pop_front(), stored);
+                    continue;
+                }
+                event = eventloop.poll() => event,
+            };
 
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(ack))) => {
                     info!(
-                        "Received MQTT message on topic: {} and payload: {}",
-                        p.topic,
-                        String::from_utf8_lossy(payload)
+                        "Connected to MQTT broker {}:{} (session present: {})",
+                        self.config.mqtt_host, self.config.mqtt_port, ack.session_present
                     );
+                    self.health.set_mqtt_connected(true);
+                    backoff = initial_backoff;
 
-                    match self.parse_telemetry_message(&p.topic, &p.payload) {
-                        Ok(message) => self.input_port.on_message(message).await,
-                        Err(e) => eprintln!("Failed to parse telemetry message: {:?}", e),
+                    if let Err(e) = client.try_subscribe(TOPIC_FILTER, QoS::AtLeastOnce) {
+                        error!("Failed to subscribe to {}: {}", TOPIC_FILTER, e);
                     }
                 }
+                Ok(Event::Incoming(Packet::Publish(p))) => {
+                    // Acknowledged from the loop above once the batch holding it committed
+                    let (done, stored) = Completion::new();
+                    self.handle_publish(&p, done).await;
+                    pending.push_back((p, stored));
+                }
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("MQTT Error: {:?}", e);
-                    break;
+                    self.health.set_mqtt_connected(false);
+                    self.metrics.mqtt_reconnect();
+                    warn!(
+                        "MQTT connection error: {}, reconnecting in {:?}",
+                        e, backoff
+                    );
+
+                    tokio::select! {
+                        _ = shutdown.recv() => break,
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                    backoff = (backoff * 2).min(max_backoff);
                 }
             }
         }
 
+        info!("MQTT adapter shutting down");
+        // Acknowledge what is still being stored; polling sends the PUBACKs. Messages
+        // arriving meanwhile stay unacknowledged and are redelivered after a restart.
+        let drain = async {
+            while !pending.is_empty() {
+                tokio::select! {
+                    stored = next_stored(&mut pending) => {
+                        acknowledge(&client, pending.pop_front(), stored);
+                    }
+                    event = eventloop.poll() => if event.is_err() {
+                        break;
+                    },
+                }
+            }
+        };
+        if tokio::time::timeout(ACK_DRAIN_TIMEOUT, drain)
+            .await
+            .is_err()
+        {
+            warn!(
+                "{} messages not acknowledged within {:?}, the broker redelivers them",
+                pending.len(),
+                ACK_DRAIN_TIMEOUT
+            );
+        }
+        self.health.set_mqtt_connected(false);
+        if client.try_disconnect().is_ok() {
+            // Poll once more so the DISCONNECT packet is actually sent
+            let _ = tokio::time::timeout(Duration::from_secs(1), eventloop.poll()).await;
+        }
+
         Ok(())
     }
 
+    async fn handle_publish(&self, p: &Publish, done: Completion) {
+        let payload = &p.payload;
+        let topic_type = topic_type(&p.topic);
+        self.metrics.message_received(topic_type);
+
+        info!(
+            "Received MQTT message on topic: {} and payload: {}",
+            p.topic,
+            String::from_utf8_lossy(payload)
+        );
+
+        let raw = RawMessage {
+            topic: p.topic.clone(),
+            payload: p.payload.to_vec(),
+        };
+
+        match parse_telemetry_message(&p.topic, &p.payload) {
+            Ok(message) => {
+                self.metrics.message_parsed(topic_type);
+                self.input_port.on_delivery(raw, message, done).await
+            }
+            Err(e) => {
+                // Poison message: retrying cannot help, keep it for a later replay
+                warn!("Failed to parse telemetry message on {}: {}", p.topic, e);
+                self.metrics.message_failed(topic_type, "parse");
+                self.metrics.dead_lettered(FailureStage::Parse.as_str());
+
+                let letter = DeadLetter::new(&raw, FailureStage::Parse, e.to_string(), 1);
+                match self.dead_letters.store(letter).await {
+                    Ok(()) => done.complete(),
+                    Err(e) => error!("Failed to write dead letter for {}: {:?}", p.topic, e),
+                }
+            }
+        }
+    }
+
     pub fn parse_telemetry_message(
         &self,
         topic: &str,
         payload_bytes: &[u8],
     ) -> Result<TelemetryMessage, Box<dyn Error>> {
-        if topic.ends_with("/created") {
-            let payload: DeviceCreatedPayload = serde_json::from_slice(payload_bytes)?;
-            Ok(TelemetryMessage::DeviceCreated(payload))
-        } else if topic.ends_with("/value") {
-            let payload: DeviceValueObservedPayload = serde_json::from_slice(payload_bytes)?;
-            Ok(TelemetryMessage::DeviceValueObserved(payload))
-        } else if topic.ends_with("/removed") {
-            let payload: DeviceRemovedPayload = serde_json::from_slice(payload_bytes)?;
-            Ok(TelemetryMessage::DeviceRemoved(payload))
-        } else {
-            Err(format!("Unknown MQTT topic: {}", topic).into())
-        }
+        parse_telemetry_message(topic, payload_bytes)
+    }
+}
+
+// Resolves once the oldest pending message is stored (true) or was given up (false).
+// Waiting on the oldest keeps the PUBACKs in arrival order, as MQTT requires.
+async fn next_stored(pending: &mut PendingAcks) -> bool {
+    match pending.front_mut() {
+        Some((_, stored)) => stored.await.is_ok(),
+        None => std::future::pending().await,
+    }
+}
+
+fn acknowledge(
+    client: &AsyncClient,
+    entry: Option<(Publish, oneshot::Receiver<()>)>,
+    stored: bool,
+) {
+    let Some((p, _)) = entry else { return };
+    if !stored {
+        warn!(
+            "Message on {} was not stored, the broker redelivers it",
+            p.topic
+        );
+        return;
+    }
+    // An ack that cannot be queued is not fatal: the broker redelivers and storage is
+    // idempotent
+    if let Err(e) = client.try_ack(&p) {
+        warn!("Failed to acknowledge message on {}: {}", p.topic, e);
+    }
+}
+
+// Maps the topic suffix to the payload type; shared with dead-letter replay
+pub fn parse_telemetry_message(
+    topic: &str,
+    payload_bytes: &[u8],
+) -> Result<TelemetryMessage, Box<dyn Error>> {
+    if topic.ends_with("/created") {
+        let payload: DeviceCreatedPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::DeviceCreated(payload))
+    } else if topic.ends_with("/value") {
+        let payload: DeviceValueObservedPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::DeviceValueObserved(payload))
+    } else if topic.ends_with("/removed") {
+        let payload: DeviceRemovedPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::DeviceRemoved(payload))
+    } else if topic.ends_with("/aggregate") {
+        let payload: DeviceAggregatePayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::DeviceValuesAggregated(payload))
+    } else if topic.ends_with("/alarm_raised") {
+        let payload: AlarmPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::AlarmRaised(payload))
+    } else if topic.ends_with("/alarm_acknowledged") {
+        let payload: AlarmPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::AlarmAcknowledged(payload))
+    } else if topic.ends_with("/alarm_cleared") {
+        let payload: AlarmPayload = serde_json::from_slice(payload_bytes)?;
+        Ok(TelemetryMessage::AlarmCleared(payload))
+    } else {
+        Err(format!("Unknown MQTT topic: {}", topic).into())
+    }
+}
+
+// Last topic segment as metric label; anything unexpected is folded into "unknown"
+fn topic_type(topic: &str) -> &'static str {
+    match topic.rsplit('/').next() {
+        Some("created") => "created",
+        Some("value") => "value",
+        Some("removed") => "removed",
+        Some("aggregate") => "aggregate",
+        Some("alarm_raised") => "alarm_raised",
+        Some("alarm_acknowledged") => "alarm_acknowledged",
+        Some("alarm_cleared") => "alarm_cleared",
+        _ => "unknown",
     }
 }
@@ -0,0 +1,170 @@
+use sqlx::{
+    PgPool,
+    migrate::{Migrate, Migrator},
+};
+use std::collections::HashMap;
+use tracing::info;
+
+// SQL files from telemetry/migrations, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Applies all pending migrations; already applied ones are skipped
+pub async fn run(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    MIGRATOR.run(pool).await?;
+    info!(
+        "Database schema is up to date ({} migrations)",
+        MIGRATOR.iter().count()
+    );
+    Ok(())
+}
+
+// Fails when the database is behind, ahead of or diverged from the embedded migrations,
+// used when schema changes are rolled out separately via `telemetry migrate`
+pub async fn verify(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut conn = pool.acquire().await?;
+    let applied = check(&MIGRATOR, &mut *conn).await?;
+    info!("Database schema verified ({} migrations)", applied);
+    Ok(())
+}
+
+// Generic over the connection so the tests can run it against SQLite
+async fn check<C: Migrate + ?Sized>(
+    migrator: &Migrator,
+    conn: &mut C,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    conn.ensure_migrations_table().await?;
+
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+
+    // Applied by a newer release; this binary does not know the schema it expects
+    let mut unknown: Vec<i64> = applied
+        .keys()
+        .filter(|version| !migrator.iter().any(|m| m.version == **version))
+        .copied()
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort_unstable();
+        return Err(format!(
+            "Database schema is ahead of this binary (unknown migrations: {:?}); deploy a newer release",
+            unknown
+        )
+        .into());
+    }
+
+    let mut pending = Vec::new();
+    for migration in migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+    {
+        match applied.get(&migration.version) {
+            Some(checksum) if *checksum != *migration.checksum => {
+                return Err(format!(
+                    "Migration {} ({}) was modified after it was applied",
+                    migration.version, migration.description
+                )
+                .into());
+            }
+            Some(_) => {}
+            None => pending.push(format!("{} ({})", migration.version, migration.description)),
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(format!(
+            "Database schema is missing migrations: {}; run `telemetry migrate`",
+            pending.join(", ")
+        )
+        .into());
+    }
+
+    Ok(applied.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::sqlite_storage::MIGRATOR as SQLITE_MIGRATOR;
+    use sqlx::{SqlitePool, migrate::MigrateError};
+
+    async fn sqlite() -> SqlitePool {
+        // A single connection, otherwise every pooled connection gets its own in-memory database
+        sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn verify_sqlite(pool: &SqlitePool) -> Result<usize, String> {
+        let mut conn = pool.acquire().await.unwrap();
+        check(&SQLITE_MIGRATOR, &mut *conn)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tokio::test]
+    async fn verify_reports_pending_migrations_until_migrate_has_run() {
+        let pool = sqlite().await;
+
+        let err = verify_sqlite(&pool).await.unwrap_err();
+        assert!(
+            err.contains("missing migrations: 1 (initial schema)"),
+            "{}",
+            err
+        );
+
+        SQLITE_MIGRATOR.run(&pool).await.unwrap();
+        let applied = verify_sqlite(&pool).await.unwrap();
+        assert_eq!(applied, SQLITE_MIGRATOR.iter().count());
+
+        // Running again is a no-op
+        SQLITE_MIGRATOR.run(&pool).await.unwrap();
+        assert_eq!(verify_sqlite(&pool).await.unwrap(), applied);
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_modified_migrations() {
+        let pool = sqlite().await;
+        SQLITE_MIGRATOR.run(&pool).await.unwrap();
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = verify_sqlite(&pool).await.unwrap_err();
+        assert!(
+            err.contains("Migration 1 (initial schema) was modified"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn database_ahead_of_the_binary_is_an_error() {
+        let pool = sqlite().await;
+        SQLITE_MIGRATOR.run(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+             VALUES (9999, 'from a newer release', TRUE, x'00', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let err = verify_sqlite(&pool).await.unwrap_err();
+        assert!(
+            err.contains("ahead of this binary (unknown migrations: [9999])"),
+            "{}",
+            err
+        );
+
+        // `telemetry migrate` refuses as well
+        let err = SQLITE_MIGRATOR.run(&pool).await.unwrap_err();
+        assert!(matches!(err, MigrateError::VersionMissing(9999)), "{}", err);
+    }
+}
@@ -1,285 +1,541 @@
+pub mod migrations;
+mod queries;
+pub mod timeseries;
+
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDateTime, Utc};
-use tracing::{error, info};
+use sqlx::{PgConnection, Postgres, QueryBuilder};
+use std::time::Instant;
+use tracing::info;
 
 use crate::{
-    config::DbConfig, core::ports::telemetry_storage_port::TelemetryStoragePort,
-    domain::intents::Intent,
+    config::{DbConfig, TimeseriesMode},
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    },
+    domain::intents::{DuplicatePolicy, Intent, merge_duplicate_measurements},
 };
 
+// Postgres accepts at most 65535 bind parameters per statement (4 per measurement row)
+const MEASUREMENT_CHUNK_SIZE: usize = 1000;
+
+#[derive(Clone)]
 pub struct PostgresStorage {
     pub config: DbConfig,
     pub pool: sqlx::Pool<sqlx::Postgres>,
+    pub metrics: Metrics,
+    pub health: Health,
 }
 
 impl PostgresStorage {
-    pub fn new(config: DbConfig, pool: sqlx::Pool<sqlx::Postgres>) -> Self {
-        PostgresStorage { config, pool }
+    pub fn new(
+        config: DbConfig,
+        pool: sqlx::Pool<sqlx::Postgres>,
+        metrics: Metrics,
+        health: Health,
+    ) -> Self {
+        PostgresStorage {
+            config,
+            pool,
+            metrics,
+            health,
+        }
     }
 
-    pub fn ts_to_datetime(ts_millis: i64) -> NaiveDateTime {
-        let secs = ts_millis / 1000;
-        let nsecs = (ts_millis % 1000) * 1_000_000;
+    // Creates the hypertable / partitions, rollups and policies for the configured mode
+    pub async fn setup_timeseries(
+        &self,
+    ) -> Result<TimeseriesMode, Box<dyn std::error::Error + Send + Sync>> {
+        timeseries::setup(&self.pool, &self.config.timeseries).await
+    }
 
-        DateTime::<Utc>::from_timestamp(secs, nsecs as u32)
-            .expect("Invalid timestamp")
-            .naive_utc()
+    // Only connection-level errors make the service unready
+    fn track<T>(&self, res: &Result<T, sqlx::Error>) {
+        match res {
+            Ok(_) => self.health.set_db_available(true),
+            Err(
+                sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed,
+            ) => self.health.set_db_available(false),
+            Err(_) => {}
+        }
     }
-}
 
-#[async_trait]
-impl TelemetryStoragePort for PostgresStorage {
-    async fn execute(
-        &self,
-        intents: Vec<Intent>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        for intent in &intents {
-            match intent {
-                // Ensure that the gateway exists in the DB
-                Intent::EnsureGatewayExists {
+    // Transient failures are retried by TelemetryService, permanent ones go to the dead-letter store
+    fn storage_error(context: &str, e: sqlx::Error) -> StorageError {
+        let transient = match &e {
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => true,
+            // 08: connection exception, 40: serialization failure / deadlock,
+            // 53: insufficient resources, 57P: operator intervention (e.g. shutdown)
+            sqlx::Error::Database(db) => db.code().is_some_and(|code| {
+                ["08", "40", "53", "57P"]
+                    .iter()
+                    .any(|class| code.starts_with(class))
+            }),
+            _ => false,
+        };
+
+        let message = format!("{}: {}", context, e);
+        if transient {
+            StorageError::Transient(message)
+        } else {
+            StorageError::Permanent(message)
+        }
+    }
+
+    // Fails for instants chrono cannot represent instead of panicking on client input
+    pub fn ts_to_datetime(ts_millis: i64) -> Result<NaiveDateTime, sqlx::Error> {
+        DateTime::<Utc>::from_timestamp_millis(ts_millis)
+            .map(|ts| ts.naive_utc())
+            .ok_or_else(|| {
+                sqlx::Error::Protocol(format!("timestamp {} ms is out of range", ts_millis))
+            })
+    }
+
+    // Executes a single non-measurement intent inside the current transaction
+    async fn apply(conn: &mut PgConnection, intent: &Intent) -> Result<(), sqlx::Error> {
+        match intent {
+            // Ensure that the gateway exists in the DB
+            Intent::EnsureGatewayExists {
+                gateway_id,
+                gateway_name,
+            } => {
+                let r = sqlx::query(
+                    r#"
+                    INSERT INTO gateways (id, name)
+                    VALUES ($1, $2)
+                    ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(gateway_name)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Inserted/Updated gateway {} (rows affected: {})",
                     gateway_id,
-                    gateway_name,
-                } => {
-                    let res = sqlx::query(
-                        r#"
-                        INSERT INTO gateways (id, name)
-                        VALUES ($1, $2)
-                        ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name;
-                        "#,
-                    )
-                    .bind(gateway_id)
-                    .bind(gateway_name)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Inserted/Updated gateway {} (rows affected: {})",
-                            gateway_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!("Failed to insert/update gateway {}: {:?}", gateway_id, e),
-                    }
-                }
+                    r.rows_affected()
+                );
+            }
+
+            // Ensure that the device exists in the DB
+            Intent::EnsureDeviceExists {
+                device_id,
+                gateway_id,
+            } => {
+                let r = sqlx::query(
+                    r#"
+                    INSERT INTO devices (gateway_id, id)
+                    VALUES ($1, $2)
+                    ON CONFLICT (gateway_id, id) DO NOTHING;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .execute(&mut *conn)
+                .await?;
 
-                // Ensure that the device exists in the DB
-                Intent::EnsureDeviceExists {
+                info!(
+                    "Ensured device {} exists (rows affected: {})",
                     device_id,
-                    gateway_id,
-                } => {
-                    let res = sqlx::query(
-                        r#"
-                        INSERT INTO devices (gateway_id, id)
-                        VALUES ($1, $2)
-                        ON CONFLICT (gateway_id, id) DO NOTHING;
-                        "#,
-                    )
-                    .bind(gateway_id)
-                    .bind(device_id)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Ensured device {} exists (rows affected: {})",
-                            device_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!("Failed to ensure device {} exists: {:?}", device_id, e),
-                    }
-                }
+                    r.rows_affected()
+                );
+            }
+
+            // Measurements are collected and inserted in bulk by execute_batch
+            Intent::RecordMeasurement { .. } => {}
+
+            // Keep refused readings with the reason given by the processor
+            Intent::RejectMeasurement {
+                device_id,
+                gateway_id,
+                value,
+                timestamp,
+                reason,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO rejected_measurements (gateway_id, device_id, "timestamp", value, reason)
+                    VALUES ($1, $2, $3, $4, $5);
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .bind(Self::ts_to_datetime(*timestamp)?)
+                .bind(value)
+                .bind(reason)
+                .execute(&mut *conn)
+                .await?;
+
+                info!("Rejected measurement of device {}: {}", device_id, reason);
+            }
 
-                // Record a telemetry measurement
-                Intent::RecordMeasurement {
+            // Update last_seen_at only if the new timestamp is later
+            Intent::UpdateDeviceLastSeen {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                let ts = Self::ts_to_datetime(*timestamp)?;
+                let r = sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET last_seen = GREATEST(COALESCE(last_seen, $3), $3)
+                    WHERE id = $1 AND gateway_id = $2;
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(ts)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Updated last seen for device {} (rows affected: {})",
                     device_id,
-                    gateway_id,
-                    value,
-                    timestamp,
-                } => {
-                    let ts = Self::ts_to_datetime(*timestamp);
-                    let res = sqlx::query(
-                        r#"
-                        INSERT INTO device_values (gateway_id, device_id, "timestamp", value)
-                        VALUES ($1, $2, $3, $4)
-                        ON CONFLICT (gateway_id, device_id, "timestamp") DO UPDATE SET value = EXCLUDED.value;
-                        "#,
-                    )
-                    .bind(gateway_id)
-                    .bind(device_id)
-                    .bind(ts)
-                    .bind(value)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(_) => info!("Recorded measurement for device {}", device_id),
-                        Err(e) => error!(
-                            "Failed to record measurement for device {}: {:?}",
-                            device_id, e
-                        ),
-                    }
-                }
+                    r.rows_affected()
+                );
+            }
 
-                // Update last_seen_at only if the new timestamp is later
-                Intent::UpdateDeviceLastSeen {
+            // Reactivate a device by clearing removed_at
+            Intent::ReactivateDevice {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                let ts = Self::ts_to_datetime(*timestamp)?;
+                let r = sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET removed_at = NULL
+                    WHERE id = $1 AND gateway_id = $2
+                    AND (removed_at IS NOT NULL AND removed_at < $3);
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(ts)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Reactivated device {} (rows affected: {})",
                     device_id,
-                    gateway_id,
-                    timestamp,
-                } => {
-                    let ts = Self::ts_to_datetime(*timestamp);
-                    let res = sqlx::query(
-                        r#"
-                        UPDATE devices
-                        SET last_seen = GREATEST(COALESCE(last_seen, $3), $3)
-                        WHERE id = $1 AND gateway_id = $2;
-                        "#,
-                    )
-                    .bind(device_id)
-                    .bind(gateway_id)
-                    .bind(ts)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Updated last seen for device {} (rows affected: {})",
-                            device_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!(
-                            "Failed to update last seen for device {}: {:?}",
-                            device_id, e
-                        ),
-                    }
-                }
+                    r.rows_affected()
+                );
+            }
 
-                // Reactivate a device by clearing removed_at
-                Intent::ReactivateDevice {
+            // Mark device as removed
+            Intent::MarkDeviceRemoved {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                let ts = Self::ts_to_datetime(*timestamp)?;
+                let r = sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET removed_at = $3
+                    WHERE id = $1 AND gateway_id = $2
+                    AND (removed_at IS NULL OR removed_at < $3);
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(ts)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Marked device {} as removed (rows affected: {})",
                     device_id,
-                    gateway_id,
-                    timestamp,
-                } => {
-                    let ts = Self::ts_to_datetime(*timestamp);
-                    let res = sqlx::query(
-                        r#"
-                        UPDATE devices
-                        SET removed_at = NULL
-                        WHERE id = $1 AND gateway_id = $2
-                        AND (removed_at IS NOT NULL AND removed_at < $3);
-                        "#,
+                    r.rows_affected()
+                );
+            }
+
+            // Acknowledgement and clearing are kept once set, whatever order the events arrive in
+            Intent::RecordAlarm {
+                device_id,
+                gateway_id,
+                alarm,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO alarms (
+                        gateway_id, id, device_id, kind, severity, value, message,
+                        raised_at, acknowledged_at, acknowledged_by, cleared_at
                     )
-                    .bind(device_id)
-                    .bind(gateway_id)
-                    .bind(ts)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Reactivated device {} (rows affected: {})",
-                            device_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!("Failed to reactivate device {}: {:?}", device_id, e),
-                    }
-                }
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                    ON CONFLICT (gateway_id, id) DO UPDATE SET
+                        acknowledged_at = COALESCE(alarms.acknowledged_at, EXCLUDED.acknowledged_at),
+                        acknowledged_by = COALESCE(alarms.acknowledged_by, EXCLUDED.acknowledged_by),
+                        cleared_at = COALESCE(alarms.cleared_at, EXCLUDED.cleared_at);
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(&alarm.id)
+                .bind(device_id)
+                .bind(alarm.kind.as_str())
+                .bind(alarm.severity.as_str())
+                .bind(alarm.value)
+                .bind(&alarm.message)
+                .bind(Self::ts_to_datetime(alarm.raised_at)?)
+                .bind(alarm.acknowledged_at.map(Self::ts_to_datetime).transpose()?)
+                .bind(&alarm.acknowledged_by)
+                .bind(alarm.cleared_at.map(Self::ts_to_datetime).transpose()?)
+                .execute(&mut *conn)
+                .await?;
 
-                // Mark device as removed
-                Intent::MarkDeviceRemoved {
-                    device_id,
-                    gateway_id,
-                    timestamp,
-                } => {
-                    let ts = Self::ts_to_datetime(*timestamp);
-                    let res = sqlx::query(
-                        r#"
-                        UPDATE devices
-                        SET removed_at = $3
-                        WHERE id = $1 AND gateway_id = $2
-                        AND (removed_at IS NULL OR removed_at < $3);
-                        "#,
+                info!("Recorded alarm {} of device {}", alarm.id, device_id);
+            }
+
+            Intent::RecordAggregate {
+                device_id,
+                gateway_id,
+                window,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO device_aggregates (
+                        gateway_id, device_id, window_start, window_end, count,
+                        min, max, avg, last, first_timestamp, last_timestamp
                     )
-                    .bind(device_id)
-                    .bind(gateway_id)
-                    .bind(ts)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Marked device {} as removed (rows affected: {})",
-                            device_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!("Failed to mark device {} as removed: {:?}", device_id, e),
-                    }
-                }
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                    ON CONFLICT (gateway_id, device_id, window_start) DO UPDATE SET
+                        window_end = EXCLUDED.window_end,
+                        count = EXCLUDED.count,
+                        min = EXCLUDED.min,
+                        max = EXCLUDED.max,
+                        avg = EXCLUDED.avg,
+                        last = EXCLUDED.last,
+                        first_timestamp = EXCLUDED.first_timestamp,
+                        last_timestamp = EXCLUDED.last_timestamp;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .bind(Self::ts_to_datetime(window.start)?)
+                .bind(Self::ts_to_datetime(window.end)?)
+                .bind(window.count as i64)
+                .bind(window.min)
+                .bind(window.max)
+                .bind(window.avg)
+                .bind(window.last)
+                .bind(Self::ts_to_datetime(window.first_timestamp)?)
+                .bind(Self::ts_to_datetime(window.last_timestamp)?)
+                .execute(&mut *conn)
+                .await?;
+            }
 
-                // Update device and gateway metadata
-                Intent::UpsertDeviceMetadata {
+            // Update device and gateway metadata
+            Intent::UpsertDeviceMetadata {
+                device_id,
+                gateway_id,
+                gateway_name,
+                device_name,
+                timestamp,
+            } => {
+                let ts = Self::ts_to_datetime(*timestamp)?;
+
+                // Update device metadata
+                let r = sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET 
+                        name = $3,
+                        last_seen = GREATEST(COALESCE(last_seen, $4), $4),
+                        removed_at = CASE WHEN removed_at < $4 THEN NULL ELSE removed_at END,
+                        created_at = COALESCE(created_at, $4)
+                    WHERE id = $1 AND gateway_id = $2;
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(device_name)
+                .bind(ts)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Upserted device metadata {} (rows affected: {})",
                     device_id,
+                    r.rows_affected()
+                );
+
+                // Update gateway metadata
+                let r = sqlx::query(
+                    r#"
+                    UPDATE gateways
+                    SET name = $2
+                    WHERE id = $1;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(gateway_name)
+                .execute(&mut *conn)
+                .await?;
+
+                info!(
+                    "Upserted gateway metadata {} (rows affected: {})",
                     gateway_id,
-                    gateway_name,
-                    device_name,
-                    timestamp,
-                } => {
-                    let ts = Self::ts_to_datetime(*timestamp);
-
-                    // Update device metadata
-                    let res = sqlx::query(
-                        r#"
-                        UPDATE devices
-                        SET 
-                            name = $3,
-                            last_seen = GREATEST(COALESCE(last_seen, $4), $4),
-                            removed_at = CASE WHEN removed_at IS NOT NULL THEN NULL ELSE removed_at END,
-                            created_at = COALESCE(created_at, $4)
-                        WHERE id = $1 AND gateway_id = $2;
-                        "#
-                    )
-                    .bind(device_id)
-                    .bind(gateway_id)
-                    .bind(device_name)
-                    .bind(ts)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Upserted device metadata {} (rows affected: {})",
-                            device_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => error!("Failed to upsert device metadata {}: {:?}", device_id, e),
-                    }
+                    r.rows_affected()
+                );
+            }
+        }
 
-                    // Update gateway metadata
-                    let res = sqlx::query(
-                        r#"
-                        UPDATE gateways
-                        SET name = $2
-                        WHERE id = $1;
-                        "#,
-                    )
-                    .bind(gateway_id)
-                    .bind(gateway_name)
-                    .execute(&self.pool)
-                    .await;
-
-                    match res {
-                        Ok(r) => info!(
-                            "Upserted gateway metadata {} (rows affected: {})",
-                            gateway_id,
-                            r.rows_affected()
-                        ),
-                        Err(e) => {
-                            error!("Failed to upsert gateway metadata {}: {:?}", gateway_id, e)
+        Ok(())
+    }
+
+    // Multi-row upserts, one statement per duplicate policy and chunk to stay below the
+    // bind parameter limit
+    async fn insert_measurements(
+        &self,
+        conn: &mut PgConnection,
+        measurements: &[&Intent],
+    ) -> Result<(), StorageError> {
+        let unique = merge_duplicate_measurements(measurements)
+            .map_err(|e| StorageError::Permanent(format!("record_measurement: {}", e)))?;
+
+        for policy in [
+            DuplicatePolicy::KeepFirst,
+            DuplicatePolicy::KeepLast,
+            DuplicatePolicy::Reject,
+        ] {
+            let rows: Vec<&Intent> = unique
+                .iter()
+                .copied()
+                .filter(|intent| {
+                    matches!(intent, Intent::RecordMeasurement { on_duplicate, .. } if *on_duplicate == policy)
+                })
+                .collect();
+
+            for chunk in rows.chunks(MEASUREMENT_CHUNK_SIZE) {
+                // Converted up front, push_values cannot fail halfway through a row
+                let timestamps = chunk
+                    .iter()
+                    .map(|intent| match intent {
+                        Intent::RecordMeasurement { timestamp, .. } => {
+                            Self::ts_to_datetime(*timestamp)
                         }
+                        _ => unreachable!("only measurements are collected"),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| Self::storage_error("record_measurement", e))?;
+
+                let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+                    r#"INSERT INTO device_values (gateway_id, device_id, "timestamp", value) "#,
+                );
+
+                query.push_values(chunk.iter().zip(timestamps), |mut row, (intent, ts)| {
+                    if let Intent::RecordMeasurement {
+                        device_id,
+                        gateway_id,
+                        value,
+                        ..
+                    } = intent
+                    {
+                        row.push_bind(gateway_id)
+                            .push_bind(device_id)
+                            .push_bind(ts)
+                            .push_bind(value);
                     }
+                });
+                query.push(on_conflict(policy));
+
+                let res = query.build().fetch_all(&mut *conn).await;
+                self.track(&res);
+                let written = res.map_err(|e| Self::storage_error("record_measurement", e))?;
+
+                if policy == DuplicatePolicy::Reject && written.len() < chunk.len() {
+                    return Err(StorageError::Permanent(format!(
+                        "record_measurement: {} measurements conflict with stored values",
+                        chunk.len() - written.len()
+                    )));
                 }
+                info!("Recorded {} measurements", chunk.len());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Reject only updates rows whose value is unchanged and returns every written row,
+// so a short result means a stored value conflicted
+fn on_conflict(policy: DuplicatePolicy) -> &'static str {
+    match policy {
+        DuplicatePolicy::KeepFirst => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO NOTHING"#
+        }
+        DuplicatePolicy::KeepLast => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO UPDATE SET value = EXCLUDED.value"#
+        }
+        DuplicatePolicy::Reject => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO UPDATE SET value = EXCLUDED.value WHERE device_values.value = EXCLUDED.value RETURNING 1"#
+        }
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for PostgresStorage {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    // One transaction for the whole batch; measurements are written last so the
+    // gateway/device rows they reference already exist
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        let res = self.pool.begin().await;
+        self.track(&res);
+        let mut tx = res.map_err(|e| Self::storage_error("begin transaction", e))?;
+
+        let mut measurements = Vec::new();
+        let mut executed = self.metrics.transaction();
+
+        for intent in batch.iter().flatten() {
+            if matches!(intent, Intent::RecordMeasurement { .. }) {
+                measurements.push(intent);
+                continue;
             }
+
+            let started = Instant::now();
+            let res = Self::apply(&mut tx, intent).await;
+            self.track(&res);
+            executed.observe(
+                intent.kind(),
+                1,
+                started.elapsed().as_secs_f64(),
+                res.is_ok(),
+            );
+            res.map_err(|e| Self::storage_error(intent.kind(), e))?;
         }
 
+        if !measurements.is_empty() {
+            let started = Instant::now();
+            let res = self.insert_measurements(&mut tx, &measurements).await;
+            executed.observe(
+                "record_measurement",
+                measurements.len() as u64,
+                started.elapsed().as_secs_f64(),
+                res.is_ok(),
+            );
+            res?;
+        }
+
+        let res = tx.commit().await;
+        self.track(&res);
+        res.map_err(|e| Self::storage_error("commit", e))?;
+        executed.committed();
+
         Ok(())
     }
 }
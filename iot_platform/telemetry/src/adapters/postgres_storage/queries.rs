@@ -0,0 +1,251 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use shared_models::AggregateWindow;
+use sqlx::Row;
+
+use super::PostgresStorage;
+use crate::{
+    core::ports::telemetry_query_port::TelemetryQueryPort,
+    domain::queries::{
+        AggregateBucket, AggregateQuery, AlarmQuery, AlarmState, DeviceRecord, GatewayRecord,
+        Measurement, MeasurementQuery, StoredAlarm,
+    },
+};
+
+fn to_millis(ts: Option<NaiveDateTime>) -> Option<i64> {
+    ts.map(|ts| ts.and_utc().timestamp_millis())
+}
+
+// 4714-11-24 BC, the earliest timestamp Postgres accepts
+const MIN_TIMESTAMP_MS: i64 = -210_866_803_200_000;
+
+// Range bounds beyond what Postgres and chrono represent still mean "since ever" / "until the end"
+fn clamp(ts_millis: i64) -> i64 {
+    ts_millis.clamp(
+        MIN_TIMESTAMP_MS,
+        DateTime::<Utc>::MAX_UTC.timestamp_millis(),
+    )
+}
+
+#[async_trait]
+impl TelemetryQueryPort for PostgresStorage {
+    async fn ping(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let res = sqlx::query("SELECT 1").execute(&self.pool).await;
+        self.track(&res);
+        res?;
+        Ok(())
+    }
+
+    async fn list_gateways(
+        &self,
+    ) -> Result<Vec<GatewayRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query("SELECT id, name FROM gateways ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(GatewayRecord {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_devices(
+        &self,
+        gateway_id: Option<&str>,
+    ) -> Result<Vec<DeviceRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT gateway_id, id, name, created_at, removed_at, last_seen
+            FROM devices
+            WHERE $1::text IS NULL OR gateway_id = $1
+            ORDER BY gateway_id, id
+            "#,
+        )
+        .bind(gateway_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(DeviceRecord {
+                    gateway_id: row.try_get("gateway_id")?,
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                    created_at: to_millis(row.try_get("created_at")?),
+                    removed_at: to_millis(row.try_get("removed_at")?),
+                    last_seen: to_millis(row.try_get("last_seen")?),
+                })
+            })
+            .collect()
+    }
+
+    async fn measurements(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<Measurement>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT "timestamp", value
+            FROM device_values
+            WHERE gateway_id = $1 AND device_id = $2
+            AND "timestamp" >= $3 AND "timestamp" < $4
+            ORDER BY "timestamp"
+            LIMIT $5
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(Self::ts_to_datetime(clamp(query.from))?)
+        .bind(Self::ts_to_datetime(clamp(query.to))?)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let ts: NaiveDateTime = row.try_get("timestamp")?;
+                Ok(Measurement {
+                    timestamp: ts.and_utc().timestamp_millis(),
+                    value: row.try_get("value")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn windows(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<AggregateWindow>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT window_start, window_end, count, min, max, avg, last,
+                   first_timestamp, last_timestamp
+            FROM device_aggregates
+            WHERE gateway_id = $1 AND device_id = $2
+            AND window_start >= $3 AND window_start < $4
+            ORDER BY window_start
+            LIMIT $5
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(Self::ts_to_datetime(clamp(query.from))?)
+        .bind(Self::ts_to_datetime(clamp(query.to))?)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let millis = |ts: NaiveDateTime| ts.and_utc().timestamp_millis();
+        rows.iter()
+            .map(|row| {
+                Ok(AggregateWindow {
+                    start: millis(row.try_get("window_start")?),
+                    end: millis(row.try_get("window_end")?),
+                    count: row.try_get::<i64, _>("count")? as u64,
+                    min: row.try_get("min")?,
+                    max: row.try_get("max")?,
+                    avg: row.try_get("avg")?,
+                    last: row.try_get("last")?,
+                    first_timestamp: millis(row.try_get("first_timestamp")?),
+                    last_timestamp: millis(row.try_get("last_timestamp")?),
+                })
+            })
+            .collect()
+    }
+
+    async fn aggregate(
+        &self,
+        query: &AggregateQuery,
+    ) -> Result<Vec<AggregateBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT (floor(extract(epoch FROM "timestamp") * 1000 / $5) * $5)::bigint AS bucket,
+                   min(value) AS min,
+                   max(value) AS max,
+                   avg(value) AS avg,
+                   (array_agg(value ORDER BY "timestamp" DESC))[1] AS last,
+                   count(*) AS count
+            FROM device_values
+            WHERE gateway_id = $1 AND device_id = $2
+            AND "timestamp" >= $3 AND "timestamp" < $4
+            GROUP BY bucket
+            ORDER BY bucket
+            LIMIT $6
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(Self::ts_to_datetime(clamp(query.from))?)
+        .bind(Self::ts_to_datetime(clamp(query.to))?)
+        .bind(query.bucket_ms)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(AggregateBucket {
+                    bucket: row.try_get("bucket")?,
+                    min: row.try_get("min")?,
+                    max: row.try_get("max")?,
+                    avg: row.try_get("avg")?,
+                    last: row.try_get("last")?,
+                    count: row.try_get("count")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn alarms(
+        &self,
+        query: &AlarmQuery,
+    ) -> Result<Vec<StoredAlarm>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT gateway_id, device_id, id, kind, severity, value, message,
+                   raised_at, acknowledged_at, acknowledged_by, cleared_at
+            FROM alarms
+            WHERE ($1::text IS NULL OR gateway_id = $1)
+            AND ($2::text IS NULL OR device_id = $2)
+            AND ($3::text IS NULL
+                OR ($3 = 'cleared' AND cleared_at IS NOT NULL)
+                OR ($3 = 'acknowledged' AND cleared_at IS NULL AND acknowledged_at IS NOT NULL)
+                OR ($3 = 'active' AND cleared_at IS NULL AND acknowledged_at IS NULL))
+            ORDER BY raised_at DESC, id
+            LIMIT $4
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(query.state.map(|s| s.as_str()))
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let raised_at: NaiveDateTime = row.try_get("raised_at")?;
+                let acknowledged_at = to_millis(row.try_get("acknowledged_at")?);
+                let cleared_at = to_millis(row.try_get("cleared_at")?);
+                Ok(StoredAlarm {
+                    gateway_id: row.try_get("gateway_id")?,
+                    device_id: row.try_get("device_id")?,
+                    id: row.try_get("id")?,
+                    kind: row.try_get("kind")?,
+                    severity: row.try_get("severity")?,
+                    state: AlarmState::of(acknowledged_at, cleared_at),
+                    value: row.try_get("value")?,
+                    message: row.try_get("message")?,
+                    raised_at: raised_at.and_utc().timestamp_millis(),
+                    acknowledged_at,
+                    acknowledged_by: row.try_get("acknowledged_by")?,
+                    cleared_at,
+                })
+            })
+            .collect()
+    }
+}
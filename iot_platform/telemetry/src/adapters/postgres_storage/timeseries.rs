@@ -0,0 +1,477 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{PgPool, Row};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::config::{TimeseriesConfig, TimeseriesMode};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Rollup name, bucket width for time_bucket / date_trunc, refresh window
+const AGGREGATES: [(&str, &str, &str, &str); 3] = [
+    ("device_values_1m", "1 minute", "minute", "1 hour"),
+    ("device_values_1h", "1 hour", "hour", "1 day"),
+    ("device_values_1d", "1 day", "day", "7 days"),
+];
+
+// Partitions created ahead of time in partitioned mode
+const PREMADE_PARTITIONS: i64 = 3;
+
+const LEGACY_TABLE: &str = "device_values_legacy";
+const DEFAULT_PARTITION: &str = "device_values_default";
+
+// Prepares device_values for the configured mode and returns the mode actually used.
+// Every statement is idempotent so this runs on each startup and picks up config changes.
+pub async fn setup(pool: &PgPool, config: &TimeseriesConfig) -> Result<TimeseriesMode, BoxError> {
+    let mode = match config.mode {
+        TimeseriesMode::Auto if timescale_available(pool).await? => TimeseriesMode::Timescale,
+        TimeseriesMode::Auto => TimeseriesMode::Partitioned,
+        mode => mode,
+    };
+
+    match mode {
+        TimeseriesMode::Off => {}
+        TimeseriesMode::Timescale => setup_timescale(pool, config).await?,
+        TimeseriesMode::Partitioned => {
+            setup_partitioned(pool, config).await?;
+            maintain_partitioned(pool, config).await?;
+        }
+        TimeseriesMode::Auto => unreachable!("auto mode is resolved above"),
+    }
+
+    info!("Time-series mode: {:?}", mode);
+    Ok(mode)
+}
+
+// TimescaleDB runs its policies as background jobs; the fallback needs a periodic task
+pub fn spawn_maintenance(
+    pool: PgPool,
+    config: TimeseriesConfig,
+    mode: TimeseriesMode,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    if mode != TimeseriesMode::Partitioned {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(config.maintenance_interval_secs.max(1)));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.recv() => break,
+                _ = interval.tick() => {
+                    if let Err(e) = maintain_partitioned(&pool, &config).await {
+                        error!("Time-series maintenance failed: {:?}", e);
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn timescale_available(pool: &PgPool) -> Result<bool, BoxError> {
+    let row = sqlx::query(
+        "SELECT EXISTS (SELECT 1 FROM pg_available_extensions WHERE name = 'timescaledb')",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.try_get(0)?)
+}
+
+async fn setup_timescale(pool: &PgPool, config: &TimeseriesConfig) -> Result<(), BoxError> {
+    sqlx::query("CREATE EXTENSION IF NOT EXISTS timescaledb")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        SELECT create_hypertable('device_values', 'timestamp',
+            chunk_time_interval => $1::interval,
+            if_not_exists => TRUE,
+            migrate_data => TRUE)
+        "#,
+    )
+    .bind(&config.chunk_interval)
+    .execute(pool)
+    .await?;
+
+    // Applies to new chunks when the interval was changed
+    sqlx::query("SELECT set_chunk_time_interval('device_values', $1::interval)")
+        .bind(&config.chunk_interval)
+        .execute(pool)
+        .await?;
+
+    if config.aggregates {
+        for (name, bucket, _, refresh_window) in AGGREGATES {
+            let view = format!(
+                r#"
+                CREATE MATERIALIZED VIEW IF NOT EXISTS {name}
+                WITH (timescaledb.continuous) AS
+                SELECT gateway_id, device_id,
+                       time_bucket(INTERVAL '{bucket}', "timestamp") AS bucket,
+                       min(value) AS min, max(value) AS max, avg(value) AS avg, count(*) AS count
+                FROM device_values
+                GROUP BY gateway_id, device_id, bucket
+                WITH NO DATA
+                "#
+            );
+            sqlx::query(&view).execute(pool).await?;
+
+            sqlx::query(
+                r#"
+                SELECT add_continuous_aggregate_policy($1::regclass,
+                    start_offset => $2::interval,
+                    end_offset => $3::interval,
+                    schedule_interval => $3::interval,
+                    if_not_exists => TRUE)
+                "#,
+            )
+            .bind(name)
+            .bind(refresh_window)
+            .bind(bucket)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    // Policies are recreated so interval changes in the config take effect
+    sqlx::query("SELECT remove_compression_policy('device_values', if_exists => TRUE)")
+        .execute(pool)
+        .await?;
+    if let Some(compress_after) = &config.compress_after {
+        // Settings cannot be changed once chunks are compressed, so they are only set once
+        let enabled: bool = sqlx::query_scalar(
+            r#"
+            SELECT compression_enabled FROM timescaledb_information.hypertables
+            WHERE hypertable_name = 'device_values'
+            "#,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if !enabled {
+            sqlx::query(
+                r#"
+                ALTER TABLE device_values SET (
+                    timescaledb.compress,
+                    timescaledb.compress_segmentby = 'gateway_id, device_id',
+                    timescaledb.compress_orderby = '"timestamp" DESC'
+                )
+                "#,
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        sqlx::query("SELECT add_compression_policy('device_values', $1::interval)")
+            .bind(compress_after)
+            .execute(pool)
+            .await?;
+    }
+
+    sqlx::query("SELECT remove_retention_policy('device_values', if_exists => TRUE)")
+        .execute(pool)
+        .await?;
+    if let Some(retention) = &config.retention {
+        sqlx::query("SELECT add_retention_policy('device_values', $1::interval)")
+            .bind(retention)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Vanilla Postgres: device_values becomes a range-partitioned table, the existing
+// rows are kept as one partition covering everything up to the first new partition
+async fn setup_partitioned(pool: &PgPool, config: &TimeseriesConfig) -> Result<(), BoxError> {
+    if config.compress_after.is_some() {
+        info!("Compression requires TimescaleDB and is ignored in partitioned mode");
+    }
+
+    let kind: Option<String> = sqlx::query_scalar(
+        r#"
+        SELECT c.relkind::text FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE c.relname = 'device_values' AND n.nspname = current_schema()
+        "#,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    if kind.as_deref() == Some("r") {
+        let width = interval_seconds(pool, &config.chunk_interval).await?;
+        let max_ts: Option<NaiveDateTime> =
+            sqlx::query_scalar(r#"SELECT max("timestamp") FROM device_values"#)
+                .fetch_one(pool)
+                .await?;
+        let now = Utc::now().naive_utc();
+        let boundary = align_up(max_ts.map_or(now, |ts| ts.max(now)), width);
+
+        info!(
+            "Converting device_values to a partitioned table (existing rows kept up to {})",
+            boundary
+        );
+
+        let mut tx = pool.begin().await?;
+        for statement in [
+            format!("ALTER TABLE device_values RENAME TO {LEGACY_TABLE}"),
+            format!(
+                "ALTER TABLE {LEGACY_TABLE} RENAME CONSTRAINT device_values_pkey TO {LEGACY_TABLE}_pkey"
+            ),
+            r#"
+            CREATE TABLE device_values (
+                gateway_id TEXT NOT NULL,
+                device_id TEXT NOT NULL,
+                "timestamp" TIMESTAMP NOT NULL,
+                value DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (gateway_id, device_id, "timestamp"),
+                FOREIGN KEY (gateway_id, device_id)
+                    REFERENCES devices(gateway_id, id)
+            ) PARTITION BY RANGE ("timestamp")
+            "#
+            .to_string(),
+            format!(
+                "ALTER TABLE device_values ATTACH PARTITION {LEGACY_TABLE} FOR VALUES FROM (MINVALUE) TO ('{}')",
+                format_ts(boundary)
+            ),
+            format!("CREATE TABLE {DEFAULT_PARTITION} PARTITION OF device_values DEFAULT"),
+        ] {
+            sqlx::query(&statement).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+    } else if kind.as_deref() != Some("p") {
+        return Err("device_values is missing or not a regular table, run migrations first".into());
+    }
+
+    if config.aggregates {
+        for (name, _, _, _) in AGGREGATES {
+            let table = format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {name} (
+                    gateway_id TEXT NOT NULL,
+                    device_id TEXT NOT NULL,
+                    bucket TIMESTAMP NOT NULL,
+                    min DOUBLE PRECISION NOT NULL,
+                    max DOUBLE PRECISION NOT NULL,
+                    avg DOUBLE PRECISION NOT NULL,
+                    count BIGINT NOT NULL,
+                    PRIMARY KEY (gateway_id, device_id, bucket)
+                )
+                "#
+            );
+            sqlx::query(&table).execute(pool).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Creates upcoming partitions, drops expired ones and refreshes the rollup tables
+async fn maintain_partitioned(pool: &PgPool, config: &TimeseriesConfig) -> Result<(), BoxError> {
+    let width = interval_seconds(pool, &config.chunk_interval).await?;
+    let now = Utc::now().naive_utc();
+
+    let partitions: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT c.relname::text, pg_get_expr(c.relpartbound, c.oid)
+        FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'device_values'::regclass
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let bounds: Vec<(String, PartitionBound)> = partitions
+        .into_iter()
+        .filter_map(|(name, expr)| parse_bound(&expr).map(|bound| (name, bound)))
+        .collect();
+
+    // New partitions start after the highest existing upper bound so they never overlap
+    let covered_until = bounds.iter().map(|(_, b)| b.to).max();
+    let mut start = align_down(now, width);
+    if let Some(covered) = covered_until {
+        start = start.max(covered);
+    }
+
+    let horizon = align_down(now, width) + chrono::Duration::seconds(width * PREMADE_PARTITIONS);
+    while start < horizon {
+        let end = start + chrono::Duration::seconds(width);
+        let statement = format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF device_values FOR VALUES FROM ('{}') TO ('{}')",
+            quote_ident(&partition_name(start)),
+            format_ts(start),
+            format_ts(end)
+        );
+        if let Err(e) = sqlx::query(&statement).execute(pool).await {
+            // Happens when rows for this range already landed in the default partition
+            warn!(
+                "Could not create partition {}: {}",
+                partition_name(start),
+                e
+            );
+        }
+        start = end;
+    }
+
+    if let Some(retention) = &config.retention {
+        let cutoff = now - chrono::Duration::seconds(interval_seconds(pool, retention).await?);
+        for (name, bound) in &bounds {
+            if bound.to <= cutoff {
+                info!(
+                    "Dropping expired partition {} (data before {})",
+                    name, bound.to
+                );
+                // Names come from the catalog and may have been created by hand
+                sqlx::query(&format!("DROP TABLE IF EXISTS {}", quote_ident(name)))
+                    .execute(pool)
+                    .await?;
+            }
+        }
+    }
+
+    if config.aggregates {
+        for (name, _, unit, refresh_window) in AGGREGATES {
+            let refresh = format!(
+                r#"
+                INSERT INTO {name} (gateway_id, device_id, bucket, min, max, avg, count)
+                SELECT gateway_id, device_id, date_trunc('{unit}', "timestamp") AS bucket,
+                       min(value), max(value), avg(value), count(*)
+                FROM device_values
+                WHERE "timestamp" >= date_trunc('{unit}', (now() AT TIME ZONE 'UTC') - $1::interval)
+                GROUP BY gateway_id, device_id, bucket
+                ON CONFLICT (gateway_id, device_id, bucket) DO UPDATE SET
+                    min = EXCLUDED.min, max = EXCLUDED.max,
+                    avg = EXCLUDED.avg, count = EXCLUDED.count
+                "#
+            );
+            sqlx::query(&refresh)
+                .bind(refresh_window)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn interval_seconds(pool: &PgPool, interval: &str) -> Result<i64, BoxError> {
+    let seconds: i64 = sqlx::query_scalar("SELECT EXTRACT(EPOCH FROM $1::interval)::bigint")
+        .bind(interval)
+        .fetch_one(pool)
+        .await?;
+
+    if seconds <= 0 {
+        return Err(format!("Interval '{}' must be positive", interval).into());
+    }
+    Ok(seconds)
+}
+
+#[derive(Debug, PartialEq)]
+struct PartitionBound {
+    to: NaiveDateTime,
+}
+
+// Parses "FOR VALUES FROM (...) TO ('2024-01-02 00:00:00')"; DEFAULT has no bound
+fn parse_bound(expr: &str) -> Option<PartitionBound> {
+    let to = expr.split(" TO (").nth(1)?.trim_end_matches(')');
+    let to = to.trim_matches('\'');
+    NaiveDateTime::parse_from_str(to, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|to| PartitionBound { to })
+}
+
+fn align_down(ts: NaiveDateTime, width: i64) -> NaiveDateTime {
+    let secs = ts.and_utc().timestamp();
+    from_secs(secs - secs.rem_euclid(width))
+}
+
+fn align_up(ts: NaiveDateTime, width: i64) -> NaiveDateTime {
+    align_down(ts, width) + chrono::Duration::seconds(width)
+}
+
+fn from_secs(secs: i64) -> NaiveDateTime {
+    DateTime::<Utc>::from_timestamp(secs, 0)
+        .expect("Invalid timestamp")
+        .naive_utc()
+}
+
+fn format_ts(ts: NaiveDateTime) -> String {
+    ts.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn partition_name(start: NaiveDateTime) -> String {
+    format!("device_values_p{}", start.format("%Y%m%d_%H%M"))
+}
+
+// Identifiers cannot be bound as parameters; quoting keeps names with spaces, capitals
+// or quotes intact instead of splicing them into the statement
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn aligns_to_interval_boundaries() {
+        let day = 86_400;
+
+        assert_eq!(
+            align_down(ts("2024-03-05 13:45:10"), day),
+            ts("2024-03-05 00:00:00")
+        );
+        assert_eq!(
+            align_up(ts("2024-03-05 13:45:10"), day),
+            ts("2024-03-06 00:00:00")
+        );
+        assert_eq!(
+            align_down(ts("2024-03-05 13:45:10"), 3_600),
+            ts("2024-03-05 13:00:00")
+        );
+    }
+
+    #[test]
+    fn parses_partition_bounds() {
+        assert_eq!(
+            parse_bound("FOR VALUES FROM (MINVALUE) TO ('2024-03-06 00:00:00')"),
+            Some(PartitionBound {
+                to: ts("2024-03-06 00:00:00")
+            })
+        );
+        assert_eq!(
+            parse_bound("FOR VALUES FROM ('2024-03-06 00:00:00') TO ('2024-03-07 00:00:00')"),
+            Some(PartitionBound {
+                to: ts("2024-03-07 00:00:00")
+            })
+        );
+        assert_eq!(parse_bound("DEFAULT"), None);
+    }
+
+    #[test]
+    fn partition_names_follow_start_time() {
+        assert_eq!(
+            partition_name(ts("2024-03-06 00:00:00")),
+            "device_values_p20240306_0000"
+        );
+    }
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_ident("device_values_p1"), r#""device_values_p1""#);
+        assert_eq!(
+            quote_ident(r#"x"; DROP TABLE devices; --"#),
+            r#""x""; DROP TABLE devices; --""#
+        );
+    }
+}
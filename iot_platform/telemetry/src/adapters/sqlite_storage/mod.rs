@@ -0,0 +1,443 @@
+mod queries;
+
+use async_trait::async_trait;
+use sqlx::{
+    QueryBuilder, Sqlite, SqliteConnection, SqlitePool,
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode},
+};
+use std::time::Instant;
+use tracing::info;
+
+use crate::{
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::telemetry_storage_port::{StorageError, TelemetryStoragePort},
+    },
+    domain::intents::{DuplicatePolicy, Intent, merge_duplicate_measurements},
+};
+
+// SQL files from telemetry/migrations_sqlite, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+// SQLite's default limit is 32766 bind parameters per statement (4 per measurement row)
+const MEASUREMENT_CHUNK_SIZE: usize = 1000;
+
+// Single-file storage for deployments without a database server; timestamps are
+// stored as epoch milliseconds
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pub pool: SqlitePool,
+    pub metrics: Metrics,
+    pub health: Health,
+}
+
+impl SqliteStorage {
+    // Opens (or creates) the database file and applies the embedded migrations
+    pub async fn connect(
+        path: &str,
+        metrics: Metrics,
+        health: Health,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+        let pool = SqlitePool::connect_with(options).await?;
+
+        MIGRATOR.run(&pool).await?;
+        info!("SQLite database {} is up to date", path);
+
+        Ok(SqliteStorage {
+            pool,
+            metrics,
+            health,
+        })
+    }
+
+    // Only locking conflicts and I/O problems are worth retrying
+    fn storage_error(context: &str, e: sqlx::Error) -> StorageError {
+        let transient = match &e {
+            sqlx::Error::Io(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => true,
+            // Extended result codes; the low byte is the primary code (5: BUSY, 6: LOCKED)
+            sqlx::Error::Database(db) => db
+                .code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+            _ => false,
+        };
+
+        let message = format!("{}: {}", context, e);
+        if transient {
+            StorageError::Transient(message)
+        } else {
+            StorageError::Permanent(message)
+        }
+    }
+
+    // Same statements as PostgresStorage::apply in SQLite syntax
+    async fn apply(conn: &mut SqliteConnection, intent: &Intent) -> Result<(), sqlx::Error> {
+        match intent {
+            Intent::EnsureGatewayExists {
+                gateway_id,
+                gateway_name,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO gateways (id, name)
+                    VALUES ($1, $2)
+                    ON CONFLICT (id) DO UPDATE SET name = excluded.name;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(gateway_name)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::EnsureDeviceExists {
+                device_id,
+                gateway_id,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO devices (gateway_id, id)
+                    VALUES ($1, $2)
+                    ON CONFLICT (gateway_id, id) DO NOTHING;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            // Measurements are inserted in bulk by execute_batch
+            Intent::RecordMeasurement { .. } => {}
+
+            Intent::RejectMeasurement {
+                device_id,
+                gateway_id,
+                value,
+                timestamp,
+                reason,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO rejected_measurements (gateway_id, device_id, "timestamp", value, reason)
+                    VALUES ($1, $2, $3, $4, $5);
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .bind(timestamp)
+                .bind(value)
+                .bind(reason)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::UpdateDeviceLastSeen {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET last_seen = MAX(COALESCE(last_seen, $3), $3)
+                    WHERE id = $1 AND gateway_id = $2;
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(timestamp)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::ReactivateDevice {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET removed_at = NULL
+                    WHERE id = $1 AND gateway_id = $2
+                    AND (removed_at IS NOT NULL AND removed_at < $3);
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(timestamp)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::MarkDeviceRemoved {
+                device_id,
+                gateway_id,
+                timestamp,
+            } => {
+                sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET removed_at = $3
+                    WHERE id = $1 AND gateway_id = $2
+                    AND (removed_at IS NULL OR removed_at < $3);
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(timestamp)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::RecordAlarm {
+                device_id,
+                gateway_id,
+                alarm,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO alarms (
+                        gateway_id, id, device_id, kind, severity, value, message,
+                        raised_at, acknowledged_at, acknowledged_by, cleared_at
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                    ON CONFLICT (gateway_id, id) DO UPDATE SET
+                        acknowledged_at = COALESCE(alarms.acknowledged_at, excluded.acknowledged_at),
+                        acknowledged_by = COALESCE(alarms.acknowledged_by, excluded.acknowledged_by),
+                        cleared_at = COALESCE(alarms.cleared_at, excluded.cleared_at);
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(&alarm.id)
+                .bind(device_id)
+                .bind(alarm.kind.as_str())
+                .bind(alarm.severity.as_str())
+                .bind(alarm.value)
+                .bind(&alarm.message)
+                .bind(alarm.raised_at)
+                .bind(alarm.acknowledged_at)
+                .bind(&alarm.acknowledged_by)
+                .bind(alarm.cleared_at)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::RecordAggregate {
+                device_id,
+                gateway_id,
+                window,
+            } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO device_aggregates (
+                        gateway_id, device_id, window_start, window_end, count,
+                        min, max, avg, last, first_timestamp, last_timestamp
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                    ON CONFLICT (gateway_id, device_id, window_start) DO UPDATE SET
+                        window_end = excluded.window_end,
+                        count = excluded.count,
+                        min = excluded.min,
+                        max = excluded.max,
+                        avg = excluded.avg,
+                        last = excluded.last,
+                        first_timestamp = excluded.first_timestamp,
+                        last_timestamp = excluded.last_timestamp;
+                    "#,
+                )
+                .bind(gateway_id)
+                .bind(device_id)
+                .bind(window.start)
+                .bind(window.end)
+                .bind(window.count as i64)
+                .bind(window.min)
+                .bind(window.max)
+                .bind(window.avg)
+                .bind(window.last)
+                .bind(window.first_timestamp)
+                .bind(window.last_timestamp)
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            Intent::UpsertDeviceMetadata {
+                device_id,
+                gateway_id,
+                gateway_name,
+                device_name,
+                timestamp,
+            } => {
+                sqlx::query(
+                    r#"
+                    UPDATE devices
+                    SET
+                        name = $3,
+                        last_seen = MAX(COALESCE(last_seen, $4), $4),
+                        removed_at = CASE WHEN removed_at < $4 THEN NULL ELSE removed_at END,
+                        created_at = COALESCE(created_at, $4)
+                    WHERE id = $1 AND gateway_id = $2;
+                    "#,
+                )
+                .bind(device_id)
+                .bind(gateway_id)
+                .bind(device_name)
+                .bind(timestamp)
+                .execute(&mut *conn)
+                .await?;
+
+                sqlx::query("UPDATE gateways SET name = $2 WHERE id = $1;")
+                    .bind(gateway_id)
+                    .bind(gateway_name)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Multi-row upserts, one statement per duplicate policy and chunk
+    async fn insert_measurements(
+        conn: &mut SqliteConnection,
+        measurements: &[&Intent],
+    ) -> Result<(), StorageError> {
+        let unique = merge_duplicate_measurements(measurements)
+            .map_err(|e| StorageError::Permanent(format!("record_measurement: {}", e)))?;
+
+        for policy in [
+            DuplicatePolicy::KeepFirst,
+            DuplicatePolicy::KeepLast,
+            DuplicatePolicy::Reject,
+        ] {
+            let rows: Vec<&Intent> = unique
+                .iter()
+                .copied()
+                .filter(|intent| {
+                    matches!(intent, Intent::RecordMeasurement { on_duplicate, .. } if *on_duplicate == policy)
+                })
+                .collect();
+
+            for chunk in rows.chunks(MEASUREMENT_CHUNK_SIZE) {
+                let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+                    r#"INSERT INTO device_values (gateway_id, device_id, "timestamp", value) "#,
+                );
+
+                query.push_values(chunk, |mut row, intent| {
+                    if let Intent::RecordMeasurement {
+                        device_id,
+                        gateway_id,
+                        value,
+                        timestamp,
+                        ..
+                    } = intent
+                    {
+                        row.push_bind(gateway_id)
+                            .push_bind(device_id)
+                            .push_bind(timestamp)
+                            .push_bind(value);
+                    }
+                });
+                query.push(on_conflict(policy));
+
+                let written = query
+                    .build()
+                    .fetch_all(&mut *conn)
+                    .await
+                    .map_err(|e| Self::storage_error("record_measurement", e))?;
+
+                if policy == DuplicatePolicy::Reject && written.len() < chunk.len() {
+                    return Err(StorageError::Permanent(format!(
+                        "record_measurement: {} measurements conflict with stored values",
+                        chunk.len() - written.len()
+                    )));
+                }
+                info!("Recorded {} measurements", chunk.len());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Same clauses as the Postgres adapter; RETURNING needs SQLite 3.35
+fn on_conflict(policy: DuplicatePolicy) -> &'static str {
+    match policy {
+        DuplicatePolicy::KeepFirst => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO NOTHING"#
+        }
+        DuplicatePolicy::KeepLast => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO UPDATE SET value = excluded.value"#
+        }
+        DuplicatePolicy::Reject => {
+            r#" ON CONFLICT (gateway_id, device_id, "timestamp") DO UPDATE SET value = excluded.value WHERE device_values.value = excluded.value RETURNING 1"#
+        }
+    }
+}
+
+#[async_trait]
+impl TelemetryStoragePort for SqliteStorage {
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError> {
+        self.execute_batch(std::slice::from_ref(&intents)).await
+    }
+
+    // One transaction for the whole batch, measurements last like PostgresStorage
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Self::storage_error("begin transaction", e))?;
+
+        let mut measurements = Vec::new();
+        let mut executed = self.metrics.transaction();
+
+        for intent in batch.iter().flatten() {
+            if matches!(intent, Intent::RecordMeasurement { .. }) {
+                measurements.push(intent);
+                continue;
+            }
+
+            let started = Instant::now();
+            let res = Self::apply(&mut tx, intent).await;
+            executed.observe(
+                intent.kind(),
+                1,
+                started.elapsed().as_secs_f64(),
+                res.is_ok(),
+            );
+            res.map_err(|e| Self::storage_error(intent.kind(), e))?;
+        }
+
+        if !measurements.is_empty() {
+            let started = Instant::now();
+            let res = Self::insert_measurements(&mut tx, &measurements).await;
+            executed.observe(
+                "record_measurement",
+                measurements.len() as u64,
+                started.elapsed().as_secs_f64(),
+                res.is_ok(),
+            );
+            res?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| Self::storage_error("commit", e))?;
+        self.health.set_db_available(true);
+        executed.committed();
+
+        Ok(())
+    }
+}
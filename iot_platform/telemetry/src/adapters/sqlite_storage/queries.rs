@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use shared_models::AggregateWindow;
+use sqlx::Row;
+
+use super::SqliteStorage;
+use crate::{
+    core::ports::telemetry_query_port::TelemetryQueryPort,
+    domain::queries::{
+        AggregateBucket, AggregateQuery, AlarmQuery, AlarmState, DeviceRecord, GatewayRecord,
+        Measurement, MeasurementQuery, StoredAlarm,
+    },
+};
+
+#[async_trait]
+impl TelemetryQueryPort for SqliteStorage {
+    async fn ping(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn list_gateways(
+        &self,
+    ) -> Result<Vec<GatewayRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query("SELECT id, name FROM gateways ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(GatewayRecord {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_devices(
+        &self,
+        gateway_id: Option<&str>,
+    ) -> Result<Vec<DeviceRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT gateway_id, id, name, created_at, removed_at, last_seen
+            FROM devices
+            WHERE $1 IS NULL OR gateway_id = $1
+            ORDER BY gateway_id, id
+            "#,
+        )
+        .bind(gateway_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(DeviceRecord {
+                    gateway_id: row.try_get("gateway_id")?,
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                    created_at: row.try_get("created_at")?,
+                    removed_at: row.try_get("removed_at")?,
+                    last_seen: row.try_get("last_seen")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn measurements(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<Measurement>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT "timestamp", value
+            FROM device_values
+            WHERE gateway_id = $1 AND device_id = $2
+            AND "timestamp" >= $3 AND "timestamp" < $4
+            ORDER BY "timestamp"
+            LIMIT $5
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(Measurement {
+                    timestamp: row.try_get("timestamp")?,
+                    value: row.try_get("value")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn windows(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<AggregateWindow>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT window_start, window_end, count, min, max, avg, last,
+                   first_timestamp, last_timestamp
+            FROM device_aggregates
+            WHERE gateway_id = $1 AND device_id = $2
+            AND window_start >= $3 AND window_start < $4
+            ORDER BY window_start
+            LIMIT $5
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(AggregateWindow {
+                    start: row.try_get("window_start")?,
+                    end: row.try_get("window_end")?,
+                    count: row.try_get::<i64, _>("count")? as u64,
+                    min: row.try_get("min")?,
+                    max: row.try_get("max")?,
+                    avg: row.try_get("avg")?,
+                    last: row.try_get("last")?,
+                    first_timestamp: row.try_get("first_timestamp")?,
+                    last_timestamp: row.try_get("last_timestamp")?,
+                })
+            })
+            .collect()
+    }
+
+    // The latest value per bucket comes from a window function, SQLite has no array_agg
+    async fn aggregate(
+        &self,
+        query: &AggregateQuery,
+    ) -> Result<Vec<AggregateBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT bucket,
+                   min(value) AS min,
+                   max(value) AS max,
+                   avg(value) AS avg,
+                   max(CASE WHEN rn = 1 THEN value END) AS last,
+                   count(*) AS count
+            FROM (
+                SELECT ("timestamp" / $5) * $5 AS bucket,
+                       value,
+                       row_number() OVER (
+                           PARTITION BY "timestamp" / $5 ORDER BY "timestamp" DESC
+                       ) AS rn
+                FROM device_values
+                WHERE gateway_id = $1 AND device_id = $2
+                AND "timestamp" >= $3 AND "timestamp" < $4
+            )
+            GROUP BY bucket
+            ORDER BY bucket
+            LIMIT $6
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.bucket_ms)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(AggregateBucket {
+                    bucket: row.try_get("bucket")?,
+                    min: row.try_get("min")?,
+                    max: row.try_get("max")?,
+                    avg: row.try_get("avg")?,
+                    last: row.try_get("last")?,
+                    count: row.try_get("count")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn alarms(
+        &self,
+        query: &AlarmQuery,
+    ) -> Result<Vec<StoredAlarm>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT gateway_id, device_id, id, kind, severity, value, message,
+                   raised_at, acknowledged_at, acknowledged_by, cleared_at
+            FROM alarms
+            WHERE ($1 IS NULL OR gateway_id = $1)
+            AND ($2 IS NULL OR device_id = $2)
+            AND ($3 IS NULL
+                OR ($3 = 'cleared' AND cleared_at IS NOT NULL)
+                OR ($3 = 'acknowledged' AND cleared_at IS NULL AND acknowledged_at IS NOT NULL)
+                OR ($3 = 'active' AND cleared_at IS NULL AND acknowledged_at IS NULL))
+            ORDER BY raised_at DESC, id
+            LIMIT $4
+            "#,
+        )
+        .bind(&query.gateway_id)
+        .bind(&query.device_id)
+        .bind(query.state.map(|s| s.as_str()))
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let acknowledged_at = row.try_get("acknowledged_at")?;
+                let cleared_at = row.try_get("cleared_at")?;
+                Ok(StoredAlarm {
+                    gateway_id: row.try_get("gateway_id")?,
+                    device_id: row.try_get("device_id")?,
+                    id: row.try_get("id")?,
+                    kind: row.try_get("kind")?,
+                    severity: row.try_get("severity")?,
+                    state: AlarmState::of(acknowledged_at, cleared_at),
+                    value: row.try_get("value")?,
+                    message: row.try_get("message")?,
+                    raised_at: row.try_get("raised_at")?,
+                    acknowledged_at,
+                    acknowledged_by: row.try_get("acknowledged_by")?,
+                    cleared_at,
+                })
+            })
+            .collect()
+    }
+}
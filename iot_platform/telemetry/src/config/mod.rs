@@ -1,34 +1,290 @@
 use std::env::var;
 
+use crate::domain::intents::DuplicatePolicy;
+
 pub struct MqttConfig {
     pub client_id: String,
     pub mqtt_host: String,
     pub mqtt_port: u16,
+    pub clean_session: bool,
+    pub reconnect_initial_ms: u64,
+    pub reconnect_max_ms: u64,
+}
+
+// What the service does with pending migrations at startup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrateMode {
+    Apply,
+    Verify,
+}
+
+// Which storage adapter persists the intents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageBackend {
+    Postgres,
+    Sqlite,
+    Memory,
+}
+
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub sqlite_path: String,
+}
+
+// Storage layout of device_values; Auto picks TimescaleDB when the extension is available
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeseriesMode {
+    Off,
+    Auto,
+    Timescale,
+    Partitioned,
 }
 
+// Intervals use Postgres interval syntax, e.g. "1 day" or "90 days"
+#[derive(Debug, Clone)]
+pub struct TimeseriesConfig {
+    pub mode: TimeseriesMode,
+    pub chunk_interval: String,
+    pub compress_after: Option<String>,
+    pub retention: Option<String>,
+    pub aggregates: bool,
+    pub maintenance_interval_secs: u64,
+}
+
+#[derive(Clone)]
 pub struct DbConfig {
     pub url: String,
+    pub migrate: MigrateMode,
+    pub timeseries: TimeseriesConfig,
+}
+
+pub struct HttpConfig {
+    pub host: String,
+    pub port: u16,
+    pub max_query_limit: usize,
+}
+
+pub struct BatchConfig {
+    pub max_size: usize,
+    pub queue_size: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+pub struct ProcessorConfig {
+    pub duplicates: DuplicatePolicy,
+    // TOML file with validation and conversion rules; enables ValidatingProcessor
+    pub rules_path: Option<String>,
+}
+
+// InfluxDB write endpoint (v1 `/write` or v2 `/api/v2/write`) including `precision=ms`
+pub struct InfluxConfig {
+    pub url: String,
+    pub token: Option<String>,
+    pub measurement: String,
+}
+
+// Secondary sinks fed after the primary storage committed; each one is optional
+pub struct SinksConfig {
+    pub influx: Option<InfluxConfig>,
+    pub csv_dir: Option<String>,
+    pub queue_size: usize,
+}
+
+pub struct DeadLetterConfig {
+    pub path: String,
 }
 
 pub struct AppConfig {
     pub mqtt: MqttConfig,
+    pub storage: StorageConfig,
     pub db: DbConfig,
+    pub http: HttpConfig,
+    pub batch: BatchConfig,
+    pub retry: RetryConfig,
+    pub processor: ProcessorConfig,
+    pub sinks: SinksConfig,
+    pub dead_letter: DeadLetterConfig,
 }
 
 impl AppConfig {
     pub fn load() -> Self {
+        let clean_session = var("MQTT_CLEAN_SESSION")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .expect("MQTT_CLEAN_SESSION must be true or false");
+
         Self {
             mqtt: MqttConfig {
-                client_id: var("MQTT_CLIENT").unwrap_or_else(|_| "default-client".to_string()),
+                client_id: client_id(var("MQTT_CLIENT").ok(), clean_session)
+                    .unwrap_or_else(|e| panic!("{}", e)),
                 mqtt_host: var("MQTT_HOST").unwrap_or_else(|_| "localhost".to_string()),
                 mqtt_port: var("MQTT_PORT")
                     .unwrap_or_else(|_| "1883".to_string())
                     .parse::<u16>()
                     .expect("MQTT_PORT must be a valid u16"),
+                clean_session,
+                reconnect_initial_ms: var("MQTT_RECONNECT_INITIAL_MS")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse::<u64>()
+                    .expect("MQTT_RECONNECT_INITIAL_MS must be a valid u64"),
+                reconnect_max_ms: var("MQTT_RECONNECT_MAX_MS")
+                    .unwrap_or_else(|_| "30000".to_string())
+                    .parse::<u64>()
+                    .expect("MQTT_RECONNECT_MAX_MS must be a valid u64"),
+            },
+            storage: StorageConfig {
+                backend: match var("STORAGE_BACKEND").as_deref() {
+                    Ok("postgres") | Err(_) => StorageBackend::Postgres,
+                    Ok("sqlite") => StorageBackend::Sqlite,
+                    Ok("memory") => StorageBackend::Memory,
+                    Ok(other) => panic!(
+                        "STORAGE_BACKEND must be postgres, sqlite or memory, got '{}'",
+                        other
+                    ),
+                },
+                sqlite_path: var("SQLITE_PATH").unwrap_or_else(|_| "telemetry.db".to_string()),
             },
             db: DbConfig {
                 url: var("DB_URL").unwrap_or_else(|_| "postgres://localhost/telemetry".to_string()),
+                migrate: match var("DB_MIGRATE").as_deref() {
+                    Ok("verify") => MigrateMode::Verify,
+                    Ok("apply") | Err(_) => MigrateMode::Apply,
+                    Ok(other) => panic!("DB_MIGRATE must be 'apply' or 'verify', got '{}'", other),
+                },
+                timeseries: TimeseriesConfig {
+                    mode: match var("TIMESERIES_MODE").as_deref() {
+                        Ok("off") | Err(_) => TimeseriesMode::Off,
+                        Ok("auto") => TimeseriesMode::Auto,
+                        Ok("timescale") => TimeseriesMode::Timescale,
+                        Ok("partitioned") => TimeseriesMode::Partitioned,
+                        Ok(other) => panic!(
+                            "TIMESERIES_MODE must be off, auto, timescale or partitioned, got '{}'",
+                            other
+                        ),
+                    },
+                    chunk_interval: var("TIMESERIES_CHUNK_INTERVAL")
+                        .unwrap_or_else(|_| "1 day".to_string()),
+                    compress_after: optional(var("TIMESERIES_COMPRESS_AFTER").ok(), "7 days"),
+                    retention: optional(var("TIMESERIES_RETENTION").ok(), "90 days"),
+                    aggregates: var("TIMESERIES_AGGREGATES")
+                        .unwrap_or_else(|_| "true".to_string())
+                        .parse::<bool>()
+                        .expect("TIMESERIES_AGGREGATES must be true or false"),
+                    maintenance_interval_secs: var("TIMESERIES_MAINTENANCE_INTERVAL_SECS")
+                        .unwrap_or_else(|_| "300".to_string())
+                        .parse::<u64>()
+                        .expect("TIMESERIES_MAINTENANCE_INTERVAL_SECS must be a valid u64"),
+                },
+            },
+            http: HttpConfig {
+                host: var("HTTP_HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+                port: var("HTTP_PORT")
+                    .unwrap_or_else(|_| "9090".to_string())
+                    .parse::<u16>()
+                    .expect("HTTP_PORT must be a valid u16"),
+                max_query_limit: var("QUERY_MAX_LIMIT")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse::<usize>()
+                    .expect("QUERY_MAX_LIMIT must be a valid usize")
+                    .max(1),
+            },
+            batch: BatchConfig {
+                max_size: var("BATCH_MAX_SIZE")
+                    .unwrap_or_else(|_| "100".to_string())
+                    .parse::<usize>()
+                    .expect("BATCH_MAX_SIZE must be a valid usize")
+                    .max(1),
+                queue_size: var("BATCH_QUEUE_SIZE")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse::<usize>()
+                    .expect("BATCH_QUEUE_SIZE must be a valid usize")
+                    .max(1),
+            },
+            retry: RetryConfig {
+                max_attempts: var("RETRY_MAX_ATTEMPTS")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse::<u32>()
+                    .expect("RETRY_MAX_ATTEMPTS must be a valid u32")
+                    .max(1),
+                initial_backoff_ms: var("RETRY_INITIAL_BACKOFF_MS")
+                    .unwrap_or_else(|_| "200".to_string())
+                    .parse::<u64>()
+                    .expect("RETRY_INITIAL_BACKOFF_MS must be a valid u64"),
+                max_backoff_ms: var("RETRY_MAX_BACKOFF_MS")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse::<u64>()
+                    .expect("RETRY_MAX_BACKOFF_MS must be a valid u64"),
+            },
+            processor: ProcessorConfig {
+                duplicates: match var("DUPLICATE_POLICY").as_deref() {
+                    Ok("keep_last") | Err(_) => DuplicatePolicy::KeepLast,
+                    Ok("keep_first") => DuplicatePolicy::KeepFirst,
+                    Ok("reject") => DuplicatePolicy::Reject,
+                    Ok(other) => panic!(
+                        "DUPLICATE_POLICY must be keep_first, keep_last or reject, got '{}'",
+                        other
+                    ),
+                },
+                rules_path: var("PROCESSOR_RULES_PATH").ok(),
+            },
+            sinks: SinksConfig {
+                influx: var("INFLUX_URL").ok().map(|url| InfluxConfig {
+                    url,
+                    token: var("INFLUX_TOKEN").ok(),
+                    measurement: var("INFLUX_MEASUREMENT")
+                        .unwrap_or_else(|_| "device_values".to_string()),
+                }),
+                csv_dir: var("CSV_ARCHIVE_DIR").ok(),
+                queue_size: var("SINK_QUEUE_SIZE")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse::<usize>()
+                    .expect("SINK_QUEUE_SIZE must be a valid usize")
+                    .max(1),
+            },
+            dead_letter: DeadLetterConfig {
+                path: var("DEAD_LETTER_PATH").unwrap_or_else(|_| "dead_letters.jsonl".to_string()),
             },
         }
     }
 }
+
+// The broker keys a persistent session by client id; with a shared default two
+// instances would take over each other's session and its queued messages
+fn client_id(value: Option<String>, clean_session: bool) -> Result<String, &'static str> {
+    match value.filter(|id| !id.trim().is_empty()) {
+        Some(id) => Ok(id),
+        None if clean_session => Ok("default-client".to_string()),
+        None => Err("MQTT_CLIENT must be set to a unique id unless MQTT_CLEAN_SESSION=true"),
+    }
+}
+
+// Unset uses the default, "off" disables the policy
+fn optional(value: Option<String>, default: &str) -> Option<String> {
+    match value.as_deref() {
+        None => Some(default.to_string()),
+        Some("off") | Some("") => None,
+        Some(v) => Some(v.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persistent_sessions_need_an_explicit_client_id() {
+        assert!(client_id(None, false).is_err());
+        assert!(client_id(Some(" ".into()), false).is_err());
+        assert_eq!(
+            client_id(Some("telemetry-1".into()), false).unwrap(),
+            "telemetry-1"
+        );
+        assert_eq!(client_id(None, true).unwrap(), "default-client");
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{Value, json};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicI64, Ordering},
+};
+
+// Connection state reported by the adapters, read by the HTTP health endpoints
+#[derive(Clone, Default)]
+pub struct Health {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    mqtt_connected: AtomicBool,
+    db_available: AtomicBool,
+    last_message_at: AtomicI64,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_mqtt_connected(&self, connected: bool) {
+        self.inner
+            .mqtt_connected
+            .store(connected, Ordering::Relaxed);
+    }
+
+    pub fn set_db_available(&self, available: bool) {
+        self.inner.db_available.store(available, Ordering::Relaxed);
+    }
+
+    pub fn message_processed(&self) {
+        self.inner
+            .last_message_at
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    // Ready to ingest: subscribed to the broker and able to write to storage
+    pub fn is_ready(&self) -> bool {
+        self.inner.mqtt_connected.load(Ordering::Relaxed)
+            && self.inner.db_available.load(Ordering::Relaxed)
+    }
+
+    // Degraded while only the broker is lost: stored data can still be queried. Without
+    // the database nothing works.
+    pub fn status(&self) -> &'static str {
+        match (
+            self.inner.mqtt_connected.load(Ordering::Relaxed),
+            self.inner.db_available.load(Ordering::Relaxed),
+        ) {
+            (true, true) => "up",
+            (false, true) => "degraded",
+            (_, false) => "down",
+        }
+    }
+
+    pub fn report(&self) -> Value {
+        let last_message_at = self.inner.last_message_at.load(Ordering::Relaxed);
+
+        json!({
+            "status": self.status(),
+            "mqtt_connected": self.inner.mqtt_connected.load(Ordering::Relaxed),
+            "db_available": self.inner.db_available.load(Ordering::Relaxed),
+            "last_message_at": (last_message_at > 0).then_some(last_message_at),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_follows_broker_and_database() {
+        let health = Health::new();
+        assert_eq!(health.status(), "down");
+
+        health.set_db_available(true);
+        assert_eq!(health.status(), "degraded");
+        assert!(!health.is_ready());
+
+        health.set_mqtt_connected(true);
+        assert_eq!(health.status(), "up");
+        assert!(health.is_ready());
+
+        health.set_db_available(false);
+        assert_eq!(health.status(), "down");
+        assert_eq!(health.report()["db_available"], false);
+        assert_eq!(health.report()["last_message_at"], Value::Null);
+
+        health.message_processed();
+        assert!(health.report()["last_message_at"].as_i64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,310 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::Arc;
+
+// Prometheus metrics for ingestion (MQTT), processing (service) and storage
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    messages_received: IntCounterVec,
+    messages_parsed: IntCounterVec,
+    messages_failed: IntCounterVec,
+    intents_executed: IntCounterVec,
+    db_duration: HistogramVec,
+    ingest_lag: Histogram,
+    batch_size: Histogram,
+    storage_retries: IntCounter,
+    dead_letters: IntCounterVec,
+    mqtt_reconnects: IntCounter,
+    sink_writes: IntCounterVec,
+    sink_dropped: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("telemetry".into()), None).expect("valid metrics namespace");
+
+        let messages_received = IntCounterVec::new(
+            Opts::new("messages_received_total", "MQTT messages received"),
+            &["topic_type"],
+        )
+        .unwrap();
+        let messages_parsed = IntCounterVec::new(
+            Opts::new("messages_parsed_total", "MQTT messages parsed successfully"),
+            &["topic_type"],
+        )
+        .unwrap();
+        let messages_failed = IntCounterVec::new(
+            Opts::new(
+                "messages_failed_total",
+                "Messages that failed to parse or store",
+            ),
+            &["topic_type", "stage"],
+        )
+        .unwrap();
+        let intents_executed = IntCounterVec::new(
+            Opts::new("intents_executed_total", "Intents executed against storage"),
+            &["intent", "result"],
+        )
+        .unwrap();
+        let db_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "Storage latency per intent").buckets(
+                vec![0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0],
+            ),
+            &["intent"],
+        )
+        .unwrap();
+        let ingest_lag = Histogram::with_opts(
+            HistogramOpts::new(
+                "ingest_lag_seconds",
+                "Time between payload timestamp and processing",
+            )
+            .buckets(vec![
+                0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0, 60.0, 300.0, 3600.0,
+            ]),
+        )
+        .unwrap();
+
+        let batch_size = Histogram::with_opts(
+            HistogramOpts::new("batch_size", "Messages written per storage transaction")
+                .buckets(vec![1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0]),
+        )
+        .unwrap();
+
+        let storage_retries = IntCounter::new(
+            "storage_retries_total",
+            "Storage attempts retried after a transient error",
+        )
+        .unwrap();
+        let dead_letters = IntCounterVec::new(
+            Opts::new(
+                "dead_letters_total",
+                "Messages written to the dead-letter store",
+            ),
+            &["stage"],
+        )
+        .unwrap();
+
+        let mqtt_reconnects = IntCounter::new(
+            "mqtt_reconnects_total",
+            "MQTT connection errors followed by a reconnect attempt",
+        )
+        .unwrap();
+
+        let sink_writes = IntCounterVec::new(
+            Opts::new(
+                "sink_writes_total",
+                "Batches written to secondary storage sinks",
+            ),
+            &["sink", "result"],
+        )
+        .unwrap();
+        let sink_dropped = IntCounterVec::new(
+            Opts::new(
+                "sink_dropped_total",
+                "Batches a secondary sink did not receive",
+            ),
+            &["sink", "reason"],
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(messages_received.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(messages_parsed.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(messages_failed.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(intents_executed.clone()))
+            .unwrap();
+        registry.register(Box::new(db_duration.clone())).unwrap();
+        registry.register(Box::new(ingest_lag.clone())).unwrap();
+        registry.register(Box::new(batch_size.clone())).unwrap();
+        registry
+            .register(Box::new(storage_retries.clone()))
+            .unwrap();
+        registry.register(Box::new(dead_letters.clone())).unwrap();
+        registry
+            .register(Box::new(mqtt_reconnects.clone()))
+            .unwrap();
+        registry.register(Box::new(sink_writes.clone())).unwrap();
+        registry.register(Box::new(sink_dropped.clone())).unwrap();
+
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                messages_received,
+                messages_parsed,
+                messages_failed,
+                intents_executed,
+                db_duration,
+                ingest_lag,
+                batch_size,
+                storage_retries,
+                dead_letters,
+                mqtt_reconnects,
+                sink_writes,
+                sink_dropped,
+            }),
+        }
+    }
+
+    pub fn message_received(&self, topic_type: &str) {
+        self.inner
+            .messages_received
+            .with_label_values(&[topic_type])
+            .inc();
+    }
+
+    pub fn message_parsed(&self, topic_type: &str) {
+        self.inner
+            .messages_parsed
+            .with_label_values(&[topic_type])
+            .inc();
+    }
+
+    pub fn message_failed(&self, topic_type: &str, stage: &str) {
+        self.inner
+            .messages_failed
+            .with_label_values(&[topic_type, stage])
+            .inc();
+    }
+
+    // Bulk statements (multi-row inserts) count every intent but observe the latency once
+    pub fn intents_executed(&self, intent: &str, count: u64, seconds: f64, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.inner
+            .intents_executed
+            .with_label_values(&[intent, result])
+            .inc_by(count);
+        self.inner
+            .db_duration
+            .with_label_values(&[intent])
+            .observe(seconds);
+    }
+
+    // Successful intents of a transaction, counted once it has committed
+    pub fn transaction(&self) -> TransactionMetrics {
+        TransactionMetrics {
+            metrics: self.clone(),
+            executed: Vec::new(),
+        }
+    }
+
+    pub fn observe_batch(&self, size: usize) {
+        self.inner.batch_size.observe(size as f64);
+    }
+
+    pub fn storage_retried(&self) {
+        self.inner.storage_retries.inc();
+    }
+
+    pub fn dead_lettered(&self, stage: &str) {
+        self.inner.dead_letters.with_label_values(&[stage]).inc();
+    }
+
+    pub fn mqtt_reconnect(&self) {
+        self.inner.mqtt_reconnects.inc();
+    }
+
+    pub fn sink_written(&self, sink: &str, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.inner
+            .sink_writes
+            .with_label_values(&[sink, result])
+            .inc();
+    }
+
+    pub fn sink_dropped(&self, sink: &str, reason: &str) {
+        self.inner
+            .sink_dropped
+            .with_label_values(&[sink, reason])
+            .inc();
+    }
+
+    // Lag is clamped at zero so clock skew between gateway and telemetry does not go negative
+    pub fn observe_ingest_lag(&self, payload_timestamp_ms: i64) {
+        let lag_ms = chrono::Utc::now().timestamp_millis() - payload_timestamp_ms;
+        self.inner.ingest_lag.observe(lag_ms.max(0) as f64 / 1000.0);
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.inner.registry.gather(), &mut buffer)
+            .expect("text encoding of metrics cannot fail");
+        String::from_utf8(buffer).expect("metrics are valid UTF-8")
+    }
+}
+
+// Holds back the success counts of a transaction, so a rollback is not counted as stored.
+// Failures are recorded right away.
+pub struct TransactionMetrics {
+    metrics: Metrics,
+    executed: Vec<(&'static str, u64, f64)>,
+}
+
+impl TransactionMetrics {
+    pub fn observe(&mut self, intent: &'static str, count: u64, seconds: f64, success: bool) {
+        if success {
+            self.executed.push((intent, count, seconds));
+        } else {
+            self.metrics.intents_executed(intent, count, seconds, false);
+        }
+    }
+
+    pub fn committed(self) {
+        for (intent, count, seconds) in self.executed {
+            self.metrics.intents_executed(intent, count, seconds, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_contains_namespaced_metrics() {
+        let metrics = Metrics::new();
+        metrics.message_received("value");
+        metrics.message_failed("value", "parse");
+        metrics.intents_executed("insert_measurement", 3, 0.004, true);
+        metrics.observe_batch(3);
+        metrics.dead_lettered("storage");
+        metrics.sink_written("csv", false);
+
+        let text = metrics.render();
+
+        assert!(text.contains(r#"telemetry_messages_received_total{topic_type="value"} 1"#));
+        assert!(
+            text.contains(r#"telemetry_messages_failed_total{stage="parse",topic_type="value"} 1"#)
+        );
+        assert!(text.contains(
+            r#"telemetry_intents_executed_total{intent="insert_measurement",result="success"} 3"#
+        ));
+        assert!(text.contains(
+            r#"telemetry_db_query_duration_seconds_count{intent="insert_measurement"} 1"#
+        ));
+        assert!(text.contains("telemetry_batch_size_sum 3"));
+        assert!(text.contains(r#"telemetry_dead_letters_total{stage="storage"} 1"#));
+        assert!(text.contains(r#"telemetry_sink_writes_total{result="failure",sink="csv"} 1"#));
+    }
+}
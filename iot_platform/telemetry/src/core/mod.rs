@@ -1,3 +1,5 @@
+pub mod health;
+pub mod metrics;
 pub mod ports;
 pub mod processors;
 pub mod services;
@@ -1,7 +1,11 @@
+pub mod dead_letter_port;
 pub mod telemetry_input_port;
 pub mod telemetry_processor_port;
+pub mod telemetry_query_port;
 pub mod telemetry_storage_port;
 
+pub use dead_letter_port::DeadLetterPort;
 pub use telemetry_input_port::TelemetryInputPort;
 pub use telemetry_processor_port::TelemetryProcessorPort;
-pub use telemetry_storage_port::TelemetryStoragePort;
+pub use telemetry_query_port::TelemetryQueryPort;
+pub use telemetry_storage_port::{StorageError, TelemetryStoragePort};
@@ -0,0 +1,22 @@
+use crate::domain::dead_letter::DeadLetter;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait DeadLetterPort: Send + Sync {
+    async fn store(
+        &self,
+        letter: DeadLetter,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Moves all letters aside for a replay and returns them. They stay claimed until
+    // `release`, so letters of an interrupted replay are claimed again by the next one.
+    async fn claim(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Ends a replay: stores the letters it did not get to and drops the claimed ones
+    async fn release(
+        &self,
+        unprocessed: Vec<DeadLetter>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
@@ -1,7 +1,41 @@
 use async_trait::async_trait;
 use shared_models::TelemetryMessage;
+use tokio::sync::oneshot;
+
+use crate::domain::dead_letter::RawMessage;
 
 #[async_trait]
 pub trait TelemetryInputPort: Send + Sync {
-    async fn on_message(&self, msg: TelemetryMessage);
+    // The raw message is kept so it can be dead-lettered verbatim if processing fails
+    async fn on_message(&self, raw: RawMessage, msg: TelemetryMessage);
+
+    // Like `on_message`, but signals `done` once the message is stored or dead-lettered.
+    // Inputs that queue messages must override it and signal only after the write.
+    async fn on_delivery(&self, raw: RawMessage, msg: TelemetryMessage, done: Completion) {
+        self.on_message(raw, msg).await;
+        done.complete();
+    }
+}
+
+// Signals that a message has been taken care of, e.g. so the MQTT adapter can acknowledge
+// it to the broker. Dropped without `complete` (a crash, a stopped worker) it signals
+// nothing and the broker redelivers the message.
+pub struct Completion(Option<oneshot::Sender<()>>);
+
+impl Completion {
+    pub fn new() -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Some(tx)), rx)
+    }
+
+    // For callers that do not wait for the outcome
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn complete(mut self) {
+        if let Some(tx) = self.0.take() {
+            let _ = tx.send(());
+        }
+    }
 }
@@ -0,0 +1,47 @@
+use crate::domain::queries::{
+    AggregateBucket, AggregateQuery, AlarmQuery, DeviceRecord, GatewayRecord, Measurement,
+    MeasurementQuery, StoredAlarm,
+};
+use async_trait::async_trait;
+use shared_models::AggregateWindow;
+
+// Read side of the storage, used by the query API
+#[async_trait]
+pub trait TelemetryQueryPort: Send + Sync {
+    async fn list_gateways(
+        &self,
+    ) -> Result<Vec<GatewayRecord>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // All devices, or only those of one gateway; removed devices are included
+    async fn list_devices(
+        &self,
+        gateway_id: Option<&str>,
+    ) -> Result<Vec<DeviceRecord>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn measurements(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<Measurement>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Buckets are aligned to multiples of bucket_ms since the epoch; empty buckets are omitted
+    async fn aggregate(
+        &self,
+        query: &AggregateQuery,
+    ) -> Result<Vec<AggregateBucket>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Edge windows reported by the gateway whose start lies in [from, to), ordered by start
+    async fn windows(
+        &self,
+        query: &MeasurementQuery,
+    ) -> Result<Vec<AggregateWindow>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn alarms(
+        &self,
+        query: &AlarmQuery,
+    ) -> Result<Vec<StoredAlarm>, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Round trip to the database for the readiness check; in-process backends are always up
+    async fn ping(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
@@ -1,10 +1,44 @@
 use crate::domain::intents::Intent;
 use async_trait::async_trait;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum StorageError {
+    // Connection loss, timeouts, serialization conflicts: retrying may succeed
+    Transient(String),
+    // Constraint violations, invalid data: retrying cannot help
+    Permanent(String),
+}
+
+impl StorageError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, StorageError::Transient(_))
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Transient(msg) => write!(f, "transient storage error: {}", msg),
+            StorageError::Permanent(msg) => write!(f, "storage error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
 
 #[async_trait]
 pub trait TelemetryStoragePort: Send + Sync {
-    async fn execute(
-        &self,
-        intents: Vec<Intent>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    // Applies all intents of one message atomically: either all of them or none
+    async fn execute(&self, intents: Vec<Intent>) -> Result<(), StorageError>;
+
+    // Applies the intents of several messages; the default runs one transaction per
+    // message, adapters may override this to use a single transaction and bulk statements
+    async fn execute_batch(&self, batch: &[Vec<Intent>]) -> Result<(), StorageError> {
+        for intents in batch {
+            self.execute(intents.clone()).await?;
+        }
+
+        Ok(())
+    }
 }
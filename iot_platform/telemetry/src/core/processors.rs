@@ -1,3 +1,5 @@
 pub mod default_processor;
+pub mod validating_processor;
 
 pub use default_processor::DefaultProcessor;
+pub use validating_processor::{ValidatingProcessor, ValidationRules};
@@ -1,7 +1,17 @@
-use crate::{core::ports::TelemetryProcessorPort, domain::intents::Intent};
-use shared_models::TelemetryMessage::{DeviceCreated, DeviceRemoved, DeviceValueObserved};
+use crate::{
+    core::ports::TelemetryProcessorPort,
+    domain::intents::{DuplicatePolicy, Intent},
+};
+use shared_models::TelemetryMessage::{
+    AlarmAcknowledged, AlarmCleared, AlarmRaised, DeviceCreated, DeviceRemoved,
+    DeviceValueObserved, DeviceValuesAggregated,
+};
 
-pub struct DefaultProcessor;
+#[derive(Default)]
+pub struct DefaultProcessor {
+    // Attached to every RecordMeasurement, storage applies it on conflicting timestamps
+    pub duplicates: DuplicatePolicy,
+}
 
 impl TelemetryProcessorPort for DefaultProcessor {
     fn process(&self, msg: shared_models::TelemetryMessage) -> Vec<Intent> {
@@ -20,6 +30,7 @@ impl TelemetryProcessorPort for DefaultProcessor {
                     gateway_id: p.ctx.gateway_id.clone(),
                     value: Some(p.value),
                     timestamp: p.meta.timestamp,
+                    on_duplicate: self.duplicates,
                 },
                 Intent::ReactivateDevice {
                     device_id: p.ctx.device_id.clone(),
@@ -59,6 +70,47 @@ impl TelemetryProcessorPort for DefaultProcessor {
                     timestamp: p.meta.timestamp,
                 },
             ],
+            // A window counts as activity of the device at its last sample
+            DeviceValuesAggregated(p) => vec![
+                Intent::EnsureGatewayExists {
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    gateway_name: p.ctx.gateway_name.clone(),
+                },
+                Intent::EnsureDeviceExists {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                },
+                Intent::UpdateDeviceLastSeen {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    timestamp: p.window.last_timestamp,
+                },
+                Intent::ReactivateDevice {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    timestamp: p.window.last_timestamp,
+                },
+                Intent::RecordAggregate {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    window: p.window,
+                },
+            ],
+            AlarmRaised(p) | AlarmAcknowledged(p) | AlarmCleared(p) => vec![
+                Intent::EnsureGatewayExists {
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    gateway_name: p.ctx.gateway_name.clone(),
+                },
+                Intent::EnsureDeviceExists {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                },
+                Intent::RecordAlarm {
+                    device_id: p.ctx.device_id.clone(),
+                    gateway_id: p.ctx.gateway_id.clone(),
+                    alarm: p.alarm,
+                },
+            ],
         }
     }
 }
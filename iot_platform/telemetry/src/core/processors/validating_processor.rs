@@ -0,0 +1,294 @@
+use serde::Deserialize;
+
+use crate::{core::ports::TelemetryProcessorPort, domain::intents::Intent};
+use shared_models::TelemetryMessage;
+
+// Named conversions for sensors reporting in a different unit than the one stored
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitConversion {
+    FahrenheitToCelsius,
+    CelsiusToFahrenheit,
+    KelvinToCelsius,
+    CelsiusToKelvin,
+    MillivoltsToVolts,
+    PascalsToHectopascals,
+}
+
+impl UnitConversion {
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            UnitConversion::FahrenheitToCelsius => (value - 32.0) * 5.0 / 9.0,
+            UnitConversion::CelsiusToFahrenheit => value * 9.0 / 5.0 + 32.0,
+            UnitConversion::KelvinToCelsius => value - 273.15,
+            UnitConversion::CelsiusToKelvin => value + 273.15,
+            UnitConversion::MillivoltsToVolts => value / 1000.0,
+            UnitConversion::PascalsToHectopascals => value / 100.0,
+        }
+    }
+}
+
+// One entry of the rules file. Missing ids match every gateway / device; the
+// value is converted, then calibrated (value * scale + offset), then range checked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRule {
+    pub gateway_id: Option<String>,
+    pub device_id: Option<String>,
+    pub convert: Option<UnitConversion>,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl ValidationRule {
+    fn matches(&self, gateway_id: &str, device_id: &str) -> bool {
+        self.gateway_id.as_deref().is_none_or(|id| id == gateway_id)
+            && self.device_id.as_deref().is_none_or(|id| id == device_id)
+    }
+
+    // The stored value, or the reason it is rejected
+    pub fn check(&self, raw: f64) -> Result<f64, String> {
+        let converted = self.convert.map_or(raw, |c| c.apply(raw));
+        let value = converted * self.scale + self.offset;
+
+        if !value.is_finite() {
+            return Err(format!("value {} is not finite after conversion", raw));
+        }
+        if let Some(min) = self.min
+            && value < min
+        {
+            return Err(format!("value {} is below the minimum {}", value, min));
+        }
+        if let Some(max) = self.max
+            && value > max
+        {
+            return Err(format!("value {} is above the maximum {}", value, max));
+        }
+
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRules {
+    #[serde(default)]
+    pub rules: Vec<ValidationRule>,
+}
+
+impl ValidationRules {
+    // Reads a TOML file with a list of [[rules]]; a broken file stops startup
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let contents = std::fs::read_to_string(path)?;
+        let rules: ValidationRules = toml::from_str(&contents)?;
+
+        for rule in &rules.rules {
+            if let (Some(min), Some(max)) = (rule.min, rule.max)
+                && min > max
+            {
+                return Err(format!(
+                    "rule for {}/{}: min {} is greater than max {}",
+                    rule.gateway_id.as_deref().unwrap_or("*"),
+                    rule.device_id.as_deref().unwrap_or("*"),
+                    min,
+                    max
+                )
+                .into());
+            }
+        }
+
+        Ok(rules)
+    }
+
+    // The first rule matching the device wins, so specific rules go before generic ones
+    pub fn find(&self, gateway_id: &str, device_id: &str) -> Option<&ValidationRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(gateway_id, device_id))
+    }
+}
+
+// Wraps another processor and checks every measurement it produces. Invalid values are
+// replaced by RejectMeasurement so storage keeps the reason instead of the reading;
+// the device and gateway intents of the message are left untouched.
+pub struct ValidatingProcessor {
+    pub inner: Box<dyn TelemetryProcessorPort>,
+    pub rules: ValidationRules,
+}
+
+impl ValidatingProcessor {
+    fn validate(&self, intent: Intent) -> Intent {
+        let Intent::RecordMeasurement {
+            device_id,
+            gateway_id,
+            value: Some(raw),
+            timestamp,
+            on_duplicate,
+        } = intent
+        else {
+            return intent;
+        };
+
+        // NaN and infinities never reach storage, with or without a rule
+        let result = if !raw.is_finite() {
+            Err(format!("value {} is not finite", raw))
+        } else {
+            match self.rules.find(&gateway_id, &device_id) {
+                Some(rule) => rule.check(raw),
+                None => Ok(raw),
+            }
+        };
+
+        match result {
+            Ok(value) => Intent::RecordMeasurement {
+                device_id,
+                gateway_id,
+                value: Some(value),
+                timestamp,
+                on_duplicate,
+            },
+            Err(reason) => {
+                tracing::debug!(%gateway_id, %device_id, timestamp, %reason, "Measurement rejected");
+                Intent::RejectMeasurement {
+                    device_id,
+                    gateway_id,
+                    value: raw,
+                    timestamp,
+                    reason,
+                }
+            }
+        }
+    }
+}
+
+impl TelemetryProcessorPort for ValidatingProcessor {
+    fn process(&self, message: TelemetryMessage) -> Vec<Intent> {
+        self.inner
+            .process(message)
+            .into_iter()
+            .map(|intent| self.validate(intent))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::processors::DefaultProcessor;
+    use shared_models::{DeviceContext, DeviceValueObservedPayload, Metadata};
+
+    fn processor(rules: &str) -> ValidatingProcessor {
+        ValidatingProcessor {
+            inner: Box::new(DefaultProcessor::default()),
+            rules: toml::from_str(rules).unwrap(),
+        }
+    }
+
+    fn measurement(processor: &ValidatingProcessor, device_id: &str, value: f64) -> Intent {
+        let msg = TelemetryMessage::DeviceValueObserved(DeviceValueObservedPayload {
+            ctx: DeviceContext {
+                gateway_id: "gw1".into(),
+                gateway_name: "Gateway 1".into(),
+                device_name: "Sensor".into(),
+                device_id: device_id.into(),
+            },
+            value,
+            meta: Metadata { timestamp: 1_000 },
+        });
+
+        processor
+            .process(msg)
+            .into_iter()
+            .find(|i| {
+                matches!(
+                    i,
+                    Intent::RecordMeasurement { .. } | Intent::RejectMeasurement { .. }
+                )
+            })
+            .unwrap()
+    }
+
+    const RULES: &str = r#"
+        [[rules]]
+        device_id = "outdoor"
+        convert = "fahrenheit_to_celsius"
+        min = -40.0
+        max = 60.0
+
+        [[rules]]
+        device_id = "humidity"
+        scale = 1.1
+        offset = -2.0
+        min = 0.0
+        max = 100.0
+
+        [[rules]]
+        min = -100.0
+    "#;
+
+    #[test]
+    fn converts_and_calibrates_valid_values() {
+        let p = processor(RULES);
+
+        let Intent::RecordMeasurement { value, .. } = measurement(&p, "outdoor", 50.0) else {
+            panic!("expected a measurement");
+        };
+        assert!((value.unwrap() - 10.0).abs() < 1e-9);
+
+        let Intent::RecordMeasurement { value, .. } = measurement(&p, "humidity", 50.0) else {
+            panic!("expected a measurement");
+        };
+        assert!((value.unwrap() - 53.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values_with_a_reason() {
+        let p = processor(RULES);
+
+        // 212 °F is 100 °C, above the outdoor maximum
+        let Intent::RejectMeasurement { value, reason, .. } = measurement(&p, "outdoor", 212.0)
+        else {
+            panic!("expected a rejection");
+        };
+        assert_eq!(value, 212.0);
+        assert!(reason.contains("above the maximum 60"), "{}", reason);
+
+        // Falls through to the catch-all rule
+        let Intent::RejectMeasurement { reason, .. } = measurement(&p, "pressure", -500.0) else {
+            panic!("expected a rejection");
+        };
+        assert!(reason.contains("below the minimum -100"), "{}", reason);
+    }
+
+    #[test]
+    fn rejects_non_finite_values_without_rules() {
+        let p = processor("");
+
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                measurement(&p, "temp", value),
+                Intent::RejectMeasurement { .. }
+            ));
+        }
+        assert!(matches!(
+            measurement(&p, "temp", 21.5),
+            Intent::RecordMeasurement {
+                value: Some(21.5),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unknown_fields_are_refused() {
+        assert!(toml::from_str::<ValidationRules>("[[rules]]\nmaximum = 1.0").is_err());
+    }
+}
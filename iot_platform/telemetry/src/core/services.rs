@@ -1,3 +1,5 @@
+pub mod batching_input;
 pub mod telemetry_service;
 
+pub use batching_input::BatchingInput;
 pub use telemetry_service::TelemetryService;
@@ -0,0 +1,181 @@
+use shared_models::TelemetryMessage;
+use std::sync::Arc;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    config::BatchConfig,
+    core::{
+        ports::telemetry_input_port::{Completion, TelemetryInputPort},
+        services::TelemetryService,
+    },
+    domain::dead_letter::RawMessage,
+};
+
+type Queued = (RawMessage, TelemetryMessage, Completion);
+
+// Decouples the MQTT loop from storage: messages are queued and written in batches.
+// Under low load every batch holds a single message, under load the queue fills up
+// while a transaction is in flight and the next batch takes everything available.
+pub struct BatchingInput {
+    tx: mpsc::Sender<Queued>,
+}
+
+impl BatchingInput {
+    // The worker finishes once the input is dropped and the queue is drained;
+    // awaiting the handle lets shutdown wait for in-flight messages
+    pub fn spawn(service: Arc<TelemetryService>, config: BatchConfig) -> (Self, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel(config.queue_size);
+        let worker = tokio::spawn(run(service, config.max_size, rx));
+        (Self { tx }, worker)
+    }
+}
+
+#[async_trait::async_trait]
+impl TelemetryInputPort for BatchingInput {
+    async fn on_message(&self, raw: RawMessage, msg: TelemetryMessage) {
+        self.on_delivery(raw, msg, Completion::none()).await;
+    }
+
+    // Returns once queued; `done` is signalled only after the batch has been written
+    async fn on_delivery(&self, raw: RawMessage, msg: TelemetryMessage, done: Completion) {
+        // Waits when the queue is full, which backpressures the MQTT loop
+        if self.tx.send((raw, msg, done)).await.is_err() {
+            tracing::error!("Batch worker stopped, dropping telemetry message");
+        }
+    }
+}
+
+async fn run(service: Arc<TelemetryService>, max_size: usize, mut rx: mpsc::Receiver<Queued>) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+
+        while batch.len() < max_size {
+            match rx.try_recv() {
+                Ok(msg) => batch.push(msg),
+                Err(_) => break,
+            }
+        }
+
+        let (messages, completions): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .map(|(raw, msg, done)| ((raw, msg), done))
+            .unzip();
+        service.on_batch(messages).await;
+        completions.into_iter().for_each(Completion::complete);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::RetryConfig,
+        core::{
+            health::Health,
+            metrics::Metrics,
+            ports::{DeadLetterPort, StorageError, TelemetryStoragePort},
+            processors::DefaultProcessor,
+        },
+        domain::{dead_letter::DeadLetter, intents::Intent},
+    };
+    use shared_models::{DeviceContext, DeviceValueObservedPayload, Metadata};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    // Blocks every write until a permit is released
+    struct GatedStorage(Arc<Semaphore>);
+
+    #[async_trait::async_trait]
+    impl TelemetryStoragePort for GatedStorage {
+        async fn execute(&self, _intents: Vec<Intent>) -> Result<(), StorageError> {
+            self.0.acquire().await.unwrap().forget();
+            Ok(())
+        }
+    }
+
+    struct NoDeadLetters;
+
+    #[async_trait::async_trait]
+    impl DeadLetterPort for NoDeadLetters {
+        async fn store(
+            &self,
+            _letter: DeadLetter,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Vec::new())
+        }
+
+        async fn claim(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Vec::new())
+        }
+
+        async fn release(
+            &self,
+            _unprocessed: Vec<DeadLetter>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    fn message() -> (RawMessage, TelemetryMessage) {
+        let payload = DeviceValueObservedPayload {
+            ctx: DeviceContext {
+                gateway_id: "gw-1".into(),
+                gateway_name: "gw".into(),
+                device_name: "temp".into(),
+                device_id: "1".into(),
+            },
+            value: 21.5,
+            meta: Metadata { timestamp: 1_000 },
+        };
+        let raw = RawMessage {
+            topic: "gw/devices/1/value".into(),
+            payload: serde_json::to_vec(&payload).unwrap(),
+        };
+        (raw, TelemetryMessage::DeviceValueObserved(payload))
+    }
+
+    #[tokio::test]
+    async fn message_is_completed_only_after_its_batch_is_written() {
+        let gate = Arc::new(Semaphore::new(0));
+        let service = TelemetryService {
+            processor: Box::new(DefaultProcessor::default()),
+            storage: Box::new(GatedStorage(gate.clone())),
+            dead_letters: Arc::new(NoDeadLetters),
+            retry: RetryConfig {
+                max_attempts: 1,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 1,
+            },
+            metrics: Metrics::new(),
+            health: Health::new(),
+        };
+        let (input, worker) = BatchingInput::spawn(
+            Arc::new(service),
+            BatchConfig {
+                max_size: 10,
+                queue_size: 10,
+            },
+        );
+
+        let (done, mut acked) = Completion::new();
+        let (raw, msg) = message();
+        input.on_delivery(raw, msg, done).await;
+
+        // Queued and picked up by the worker, but the write is still pending
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(acked.try_recv().is_err(), "not completed before the write");
+
+        gate.add_permits(1);
+        tokio::time::timeout(Duration::from_secs(1), acked)
+            .await
+            .expect("completed after the write")
+            .unwrap();
+
+        drop(input);
+        worker.await.unwrap();
+    }
+}
@@ -1,23 +1,462 @@
 use shared_models::TelemetryMessage;
+use std::{sync::Arc, time::Duration};
 
-use crate::core::ports::{
-    telemetry_input_port::TelemetryInputPort, telemetry_processor_port::TelemetryProcessorPort,
-    telemetry_storage_port::TelemetryStoragePort,
+use crate::{
+    config::RetryConfig,
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{
+            dead_letter_port::DeadLetterPort,
+            telemetry_input_port::TelemetryInputPort,
+            telemetry_processor_port::TelemetryProcessorPort,
+            telemetry_storage_port::{StorageError, TelemetryStoragePort},
+        },
+    },
+    domain::{
+        dead_letter::{DeadLetter, FailureStage, RawMessage},
+        intents::Intent,
+    },
 };
 
 pub struct TelemetryService {
     pub processor: Box<dyn TelemetryProcessorPort>,
     pub storage: Box<dyn TelemetryStoragePort>,
+    pub dead_letters: Arc<dyn DeadLetterPort>,
+    pub retry: RetryConfig,
+    pub metrics: Metrics,
+    pub health: Health,
+}
+
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub replayed: usize,
+    pub failed: usize,
+}
+
+// Parses a raw MQTT message; supplied by the adapter so replay reuses its topic mapping
+pub type ParseFn =
+    dyn Fn(&str, &[u8]) -> Result<TelemetryMessage, Box<dyn std::error::Error>> + Send + Sync;
+
+impl TelemetryService {
+    // Writes all messages in one storage call; when that fails each message is retried
+    // on its own so a single bad message does not discard the rest of the batch
+    pub async fn on_batch(&self, messages: Vec<(RawMessage, TelemetryMessage)>) {
+        tracing::debug!(size = messages.len(), "Processing telemetry batch");
+        self.metrics.observe_batch(messages.len());
+
+        let mut pending = Vec::with_capacity(messages.len());
+        let mut batch = Vec::with_capacity(messages.len());
+
+        for (raw, msg) in messages {
+            tracing::debug!(?msg, "Processing telemetry message");
+            pending.push((raw, msg.kind()));
+            self.metrics.observe_ingest_lag(msg.timestamp());
+            batch.push(self.processor.process(msg));
+        }
+
+        if batch.len() > 1 {
+            match self.storage.execute_batch(&batch).await {
+                Ok(()) => {
+                    self.health.message_processed();
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Batch failed, retrying messages individually")
+                }
+            }
+        }
+
+        for (intents, (raw, topic_type)) in batch.into_iter().zip(pending) {
+            if let Err((e, attempts)) = self.execute_with_retry(intents).await {
+                tracing::error!(error = %e, attempts, topic = %raw.topic, "Failed to execute intents");
+                self.metrics.message_failed(topic_type, "storage");
+                self.dead_letter(DeadLetter::new(
+                    &raw,
+                    FailureStage::Storage,
+                    e.to_string(),
+                    attempts,
+                ))
+                .await;
+            }
+        }
+
+        self.health.message_processed();
+    }
+
+    // Retries transient errors with exponential backoff; permanent errors fail immediately
+    async fn execute_with_retry(&self, intents: Vec<Intent>) -> Result<(), (StorageError, u32)> {
+        let mut backoff = Duration::from_millis(self.retry.initial_backoff_ms);
+        let max_backoff = Duration::from_millis(self.retry.max_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            match self.storage.execute(intents.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.is_transient() && attempt < self.retry.max_attempts => {
+                    tracing::warn!(error = %e, attempt, "Transient storage error, retrying in {:?}", backoff);
+                    self.metrics.storage_retried();
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(max_backoff);
+                    attempt += 1;
+                }
+                Err(e) => return Err((e, attempt)),
+            }
+        }
+    }
+
+    // A failing dead-letter store must not stop ingestion, the letter is logged instead
+    pub async fn dead_letter(&self, letter: DeadLetter) {
+        self.metrics.dead_lettered(letter.stage.as_str());
+
+        let stage = letter.stage;
+        let topic = letter.topic.clone();
+        if let Err(e) = self.dead_letters.store(letter).await {
+            tracing::error!(error = ?e, ?stage, %topic, "Failed to write dead letter, message lost");
+        }
+    }
+
+    // Reprocesses every dead letter; letters that fail again are stored back with an increased attempt count.
+    // If that store fails, the letters not yet handled are put back before the error is returned.
+    pub async fn replay_dead_letters(
+        &self,
+        parse: &ParseFn,
+    ) -> Result<ReplaySummary, Box<dyn std::error::Error + Send + Sync>> {
+        let mut letters = self.dead_letters.claim().await?.into_iter();
+        let mut summary = ReplaySummary::default();
+
+        while let Some(mut letter) = letters.next() {
+            let raw = letter.raw();
+
+            let result = match parse(&raw.topic, &raw.payload) {
+                Ok(msg) => self
+                    .execute_with_retry(self.processor.process(msg))
+                    .await
+                    .map_err(|(e, attempts)| (FailureStage::Storage, e.to_string(), attempts)),
+                Err(e) => Err((FailureStage::Parse, e.to_string(), 1)),
+            };
+
+            match result {
+                Ok(()) => summary.replayed += 1,
+                Err((stage, error, attempts)) => {
+                    tracing::warn!(topic = %letter.topic, %error, "Dead letter failed again");
+                    letter.stage = stage;
+                    letter.error = error;
+                    letter.attempts += attempts;
+                    letter.failed_at = chrono::Utc::now().timestamp_millis();
+                    summary.failed += 1;
+                    if let Err(e) = self.dead_letters.store(letter.clone()).await {
+                        let unprocessed: Vec<_> = std::iter::once(letter).chain(letters).collect();
+                        // Left claimed if this fails too, the next replay picks them up
+                        if let Err(e) = self.dead_letters.release(unprocessed).await {
+                            tracing::error!(error = ?e, "Failed to put back unprocessed dead letters");
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        self.dead_letters.release(Vec::new()).await?;
+        Ok(summary)
+    }
 }
 
 #[async_trait::async_trait]
 impl TelemetryInputPort for TelemetryService {
-    async fn on_message(&self, msg: TelemetryMessage) {
-        tracing::debug!(?msg, "Processing telemetry message");
-        let intents = self.processor.process(msg);
+    async fn on_message(&self, raw: RawMessage, msg: TelemetryMessage) {
+        self.on_batch(vec![(raw, msg)]).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::processors::DefaultProcessor;
+    use shared_models::{DeviceContext, DeviceValueObservedPayload, Metadata};
+    use std::sync::Mutex;
+
+    // Fails the first `failures` calls with the given error kind
+    struct FlakyStorage {
+        failures: Mutex<u32>,
+        transient: bool,
+        calls: Arc<Mutex<u32>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TelemetryStoragePort for FlakyStorage {
+        async fn execute(&self, _intents: Vec<Intent>) -> Result<(), StorageError> {
+            *self.calls.lock().unwrap() += 1;
+            let mut failures = self.failures.lock().unwrap();
+            if *failures == 0 {
+                return Ok(());
+            }
+            *failures -= 1;
+            Err(if self.transient {
+                StorageError::Transient("connection reset".into())
+            } else {
+                StorageError::Permanent("constraint violated".into())
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryDeadLetters {
+        letters: Mutex<Vec<DeadLetter>>,
+        claimed: Mutex<Vec<DeadLetter>>,
+        // Stores that succeed before every further one fails; unlimited when None
+        stores_left: Mutex<Option<u32>>,
+    }
+
+    #[async_trait::async_trait]
+    impl DeadLetterPort for MemoryDeadLetters {
+        async fn store(
+            &self,
+            letter: DeadLetter,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if let Some(left) = self.stores_left.lock().unwrap().as_mut() {
+                if *left == 0 {
+                    return Err("disk full".into());
+                }
+                *left -= 1;
+            }
+            self.letters.lock().unwrap().push(letter);
+            Ok(())
+        }
+
+        async fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(self.letters.lock().unwrap().clone())
+        }
+
+        async fn claim(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error + Send + Sync>> {
+            let mut claimed = self.claimed.lock().unwrap();
+            claimed.append(&mut self.letters.lock().unwrap());
+            Ok(claimed.clone())
+        }
+
+        async fn release(
+            &self,
+            unprocessed: Vec<DeadLetter>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.claimed.lock().unwrap().clear();
+            self.letters.lock().unwrap().extend(unprocessed);
+            Ok(())
+        }
+    }
+
+    fn service(
+        failures: u32,
+        transient: bool,
+    ) -> (TelemetryService, Arc<MemoryDeadLetters>, Arc<Mutex<u32>>) {
+        let dead_letters = Arc::new(MemoryDeadLetters::default());
+        let calls = Arc::new(Mutex::new(0));
+        let service = TelemetryService {
+            processor: Box::new(DefaultProcessor::default()),
+            storage: Box::new(FlakyStorage {
+                failures: Mutex::new(failures),
+                transient,
+                calls: calls.clone(),
+            }),
+            dead_letters: dead_letters.clone(),
+            retry: RetryConfig {
+                max_attempts: 3,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 2,
+            },
+            metrics: Metrics::new(),
+            health: Health::new(),
+        };
+        (service, dead_letters, calls)
+    }
+
+    fn value_message() -> (RawMessage, TelemetryMessage) {
+        value_message_with(21.5)
+    }
+
+    fn value_message_with(value: f64) -> (RawMessage, TelemetryMessage) {
+        let payload = DeviceValueObservedPayload {
+            ctx: DeviceContext {
+                gateway_id: "gw-1".into(),
+                gateway_name: "gw".into(),
+                device_name: "temp".into(),
+                device_id: "1".into(),
+            },
+            value,
+            meta: Metadata { timestamp: 1_000 },
+        };
+        let raw = RawMessage {
+            topic: "gw/devices/1/value".into(),
+            payload: serde_json::to_vec(&payload).unwrap(),
+        };
+        (raw, TelemetryMessage::DeviceValueObserved(payload))
+    }
+
+    #[tokio::test]
+    async fn failing_row_falls_back_to_per_message_execution() {
+        let dir = std::env::temp_dir().join(format!(
+            "telemetry-batch-fallback-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let metrics = Metrics::new();
+        let storage = crate::adapters::sqlite_storage::SqliteStorage::connect(
+            dir.join("telemetry.db").to_str().unwrap(),
+            metrics.clone(),
+            Health::new(),
+        )
+        .await
+        .unwrap();
+        let dead_letters = Arc::new(MemoryDeadLetters::default());
+        let service = TelemetryService {
+            processor: Box::new(DefaultProcessor {
+                duplicates: crate::domain::intents::DuplicatePolicy::Reject,
+            }),
+            storage: Box::new(storage),
+            dead_letters: dead_letters.clone(),
+            retry: RetryConfig {
+                max_attempts: 1,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 1,
+            },
+            metrics: metrics.clone(),
+            health: Health::new(),
+        };
+
+        // Same timestamp, different value: rejected, which rolls back the whole batch
+        service
+            .on_batch(vec![value_message_with(21.5), value_message_with(22.0)])
+            .await;
+
+        let letters = dead_letters.letters.lock().unwrap();
+        assert_eq!(letters.len(), 1, "only the conflicting message fails");
+        assert_eq!(letters[0].raw().payload, value_message_with(22.0).0.payload);
+        // The rolled back batch is not counted, only the message stored on its own
+        let text = metrics.render();
+        assert!(text.contains(
+            r#"telemetry_intents_executed_total{intent="record_measurement",result="success"} 1"#
+        ));
+        assert!(text.contains(
+            r#"telemetry_intents_executed_total{intent="ensure_gateway_exists",result="success"} 1"#
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let (service, dead_letters, calls) = service(2, true);
+        let (raw, msg) = value_message();
+
+        service.on_message(raw, msg).await;
+
+        assert_eq!(*calls.lock().unwrap(), 3);
+        assert!(dead_letters.letters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_are_dead_lettered() {
+        let (service, dead_letters, calls) = service(10, true);
+        let (raw, msg) = value_message();
+
+        service.on_message(raw.clone(), msg).await;
+
+        assert_eq!(*calls.lock().unwrap(), 3);
+        let letters = dead_letters.letters.lock().unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(letters[0].stage, FailureStage::Storage);
+        assert_eq!(letters[0].raw().payload, raw.payload);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let (service, dead_letters, calls) = service(1, false);
+        let (raw, msg) = value_message();
 
-        if let Err(e) = self.storage.execute(intents).await {
-            tracing::error!(error = ?e, "Failed to execute intents");
+        service.on_message(raw, msg).await;
+
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert_eq!(dead_letters.letters.lock().unwrap()[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn replay_reprocesses_and_keeps_failures() {
+        let (service, dead_letters, _) = service(0, true);
+        let (raw, _) = value_message();
+        let broken = RawMessage {
+            topic: "gw/devices/1/value".into(),
+            payload: b"{".to_vec(),
+        };
+        dead_letters
+            .store(DeadLetter::new(
+                &raw,
+                FailureStage::Storage,
+                "db down".into(),
+                3,
+            ))
+            .await
+            .unwrap();
+        dead_letters
+            .store(DeadLetter::new(
+                &broken,
+                FailureStage::Parse,
+                "eof".into(),
+                1,
+            ))
+            .await
+            .unwrap();
+
+        let summary = service
+            .replay_dead_letters(&crate::adapters::mqtt::parse_telemetry_message)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.replayed, 1);
+        assert_eq!(summary.failed, 1);
+        let remaining = dead_letters.list().await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn replay_puts_back_unprocessed_letters_when_storing_fails() {
+        let (service, dead_letters, _) = service(0, true);
+        for topic in ["a", "b", "c"] {
+            let broken = RawMessage {
+                topic: format!("gw/devices/{}/value", topic),
+                payload: b"{".to_vec(),
+            };
+            dead_letters
+                .store(DeadLetter::new(
+                    &broken,
+                    FailureStage::Parse,
+                    "eof".into(),
+                    1,
+                ))
+                .await
+                .unwrap();
         }
+        // The first letter that fails again is stored, the second is not
+        *dead_letters.stores_left.lock().unwrap() = Some(1);
+
+        let result = service
+            .replay_dead_letters(&crate::adapters::mqtt::parse_telemetry_message)
+            .await;
+
+        assert!(result.is_err());
+        let remaining = dead_letters.list().await.unwrap();
+        let attempts: Vec<_> = remaining
+            .iter()
+            .map(|l| (l.topic.as_str(), l.attempts))
+            .collect();
+        assert_eq!(
+            attempts,
+            [
+                ("gw/devices/a/value", 2),
+                ("gw/devices/b/value", 2),
+                ("gw/devices/c/value", 1)
+            ]
+        );
+        assert!(dead_letters.claimed.lock().unwrap().is_empty());
     }
 }
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+// Topic and payload exactly as received from the broker
+#[derive(Debug, Clone)]
+pub struct RawMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureStage {
+    Parse,
+    Storage,
+}
+
+impl FailureStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureStage::Parse => "parse",
+            FailureStage::Storage => "storage",
+        }
+    }
+}
+
+// A message that could not be processed, kept for inspection and replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub topic: String,
+    pub payload: String,
+    pub stage: FailureStage,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: i64,
+}
+
+impl DeadLetter {
+    pub fn new(raw: &RawMessage, stage: FailureStage, error: String, attempts: u32) -> Self {
+        Self {
+            topic: raw.topic.clone(),
+            payload: String::from_utf8_lossy(&raw.payload).into_owned(),
+            stage,
+            error,
+            attempts,
+            failed_at: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn raw(&self) -> RawMessage {
+        RawMessage {
+            topic: self.topic.clone(),
+            payload: self.payload.clone().into_bytes(),
+        }
+    }
+}
@@ -1,4 +1,42 @@
-#[derive(Debug)]
+use shared_models::{AggregateWindow, AlarmRecord};
+use std::collections::HashMap;
+
+// What storage does when a measurement arrives for a series and timestamp that already
+// has a value. Re-delivering the same value is a no-op under every policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DuplicatePolicy {
+    KeepFirst,
+    // Matches the upsert used before the policy existed (ADR-003)
+    #[default]
+    KeepLast,
+    // A different value is refused so the reading ends up in the dead-letter store
+    Reject,
+}
+
+impl DuplicatePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::KeepFirst => "keep_first",
+            DuplicatePolicy::KeepLast => "keep_last",
+            DuplicatePolicy::Reject => "reject",
+        }
+    }
+
+    // The value to keep, or None when a conflicting value is rejected
+    pub fn resolve(&self, existing: f64, incoming: f64) -> Option<f64> {
+        if existing == incoming {
+            return Some(existing);
+        }
+
+        match self {
+            DuplicatePolicy::KeepFirst => Some(existing),
+            DuplicatePolicy::KeepLast => Some(incoming),
+            DuplicatePolicy::Reject => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Intent {
     EnsureGatewayExists {
         gateway_id: String,
@@ -29,6 +67,16 @@ pub enum Intent {
         gateway_id: String,
         value: Option<f64>,
         timestamp: i64,
+        on_duplicate: DuplicatePolicy,
+    },
+
+    // A reading refused by validation; stored with the reason instead of the value
+    RejectMeasurement {
+        device_id: String,
+        gateway_id: String,
+        value: f64,
+        timestamp: i64,
+        reason: String,
     },
 
     MarkDeviceRemoved {
@@ -42,4 +90,164 @@ pub enum Intent {
         gateway_id: String,
         timestamp: i64,
     },
+
+    // Merged with the stored alarm: acknowledgement and clearing are only ever set once,
+    // so lifecycle events can arrive in any order
+    RecordAlarm {
+        device_id: String,
+        gateway_id: String,
+        alarm: AlarmRecord,
+    },
+
+    // Window statistics computed at the edge, one row per device and window start;
+    // a redelivered window replaces the stored one
+    RecordAggregate {
+        device_id: String,
+        gateway_id: String,
+        window: AggregateWindow,
+    },
+}
+
+impl Intent {
+    // Stable label used for metrics and logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Intent::EnsureGatewayExists { .. } => "ensure_gateway_exists",
+            Intent::EnsureDeviceExists { .. } => "ensure_device_exists",
+            Intent::UpsertDeviceMetadata { .. } => "upsert_device_metadata",
+            Intent::UpdateDeviceLastSeen { .. } => "update_device_last_seen",
+            Intent::RecordMeasurement { .. } => "record_measurement",
+            Intent::RejectMeasurement { .. } => "reject_measurement",
+            Intent::MarkDeviceRemoved { .. } => "mark_device_removed",
+            Intent::ReactivateDevice { .. } => "reactivate_device",
+            Intent::RecordAlarm { .. } => "record_alarm",
+            Intent::RecordAggregate { .. } => "record_aggregate",
+        }
+    }
+}
+
+// Collapses measurements of the same series and timestamp within one batch according to
+// the policy of the later one, so a statement never touches a row twice. Measurements
+// without a value are passed through for storage to reject.
+pub fn merge_duplicate_measurements<'a>(
+    measurements: &[&'a Intent],
+) -> Result<Vec<&'a Intent>, String> {
+    let mut index: HashMap<(&str, &str, i64), usize> = HashMap::new();
+    let mut unique: Vec<&Intent> = Vec::with_capacity(measurements.len());
+
+    for intent in measurements {
+        let Intent::RecordMeasurement {
+            device_id,
+            gateway_id,
+            value,
+            timestamp,
+            on_duplicate,
+        } = intent
+        else {
+            continue;
+        };
+
+        let key = (gateway_id.as_str(), device_id.as_str(), *timestamp);
+        let Some(&i) = index.get(&key) else {
+            index.insert(key, unique.len());
+            unique.push(intent);
+            continue;
+        };
+
+        if let (
+            Intent::RecordMeasurement {
+                value: Some(existing),
+                ..
+            },
+            Some(incoming),
+        ) = (unique[i], value)
+        {
+            match on_duplicate.resolve(*existing, *incoming) {
+                Some(v) if v == *existing => {}
+                Some(_) => unique[i] = intent,
+                None => {
+                    return Err(format!(
+                        "conflicting values {} and {} for device {} at {}",
+                        existing, incoming, device_id, timestamp
+                    ));
+                }
+            }
+        } else {
+            unique[i] = intent;
+        }
+    }
+
+    Ok(unique)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(device_id: &str, timestamp: i64, value: f64, policy: DuplicatePolicy) -> Intent {
+        Intent::RecordMeasurement {
+            device_id: device_id.into(),
+            gateway_id: "gw".into(),
+            value: Some(value),
+            timestamp,
+            on_duplicate: policy,
+        }
+    }
+
+    fn value(intent: &Intent) -> f64 {
+        match intent {
+            Intent::RecordMeasurement { value: Some(v), .. } => *v,
+            _ => panic!("not a measurement"),
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_last_value_per_row() {
+        let policy = DuplicatePolicy::KeepLast;
+        let intents = [
+            measurement("a", 1, 1.0, policy),
+            measurement("b", 1, 2.0, policy),
+            measurement("a", 1, 3.0, policy),
+            measurement("a", 2, 4.0, policy),
+        ];
+        let refs: Vec<&Intent> = intents.iter().collect();
+
+        let unique = merge_duplicate_measurements(&refs).unwrap();
+
+        assert_eq!(unique.len(), 3);
+        assert_eq!(value(unique[0]), 3.0);
+    }
+
+    #[test]
+    fn keep_first_ignores_later_values() {
+        let policy = DuplicatePolicy::KeepFirst;
+        let intents = [
+            measurement("a", 1, 1.0, policy),
+            measurement("a", 1, 2.0, policy),
+        ];
+        let refs: Vec<&Intent> = intents.iter().collect();
+
+        let unique = merge_duplicate_measurements(&refs).unwrap();
+
+        assert_eq!(unique.len(), 1);
+        assert_eq!(value(unique[0]), 1.0);
+    }
+
+    #[test]
+    fn reject_accepts_redelivery_but_not_conflicts() {
+        let policy = DuplicatePolicy::Reject;
+        let same = [
+            measurement("a", 1, 1.0, policy),
+            measurement("a", 1, 1.0, policy),
+        ];
+        let refs: Vec<&Intent> = same.iter().collect();
+        assert_eq!(merge_duplicate_measurements(&refs).unwrap().len(), 1);
+
+        let conflict = [
+            measurement("a", 1, 1.0, policy),
+            measurement("a", 1, 2.0, policy),
+        ];
+        let refs: Vec<&Intent> = conflict.iter().collect();
+        assert!(merge_duplicate_measurements(&refs).is_err());
+    }
 }
@@ -1 +1,3 @@
+pub mod dead_letter;
 pub mod intents;
+pub mod queries;
@@ -0,0 +1,118 @@
+use serde::Serialize;
+
+// Read models returned by TelemetryQueryPort; timestamps are Unix epoch milliseconds
+// like the payload timestamps coming from the gateway
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GatewayRecord {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceRecord {
+    pub gateway_id: String,
+    pub id: String,
+    pub name: Option<String>,
+    pub created_at: Option<i64>,
+    pub removed_at: Option<i64>,
+    pub last_seen: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Measurement {
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregateBucket {
+    // Start of the bucket
+    pub bucket: i64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+    pub count: i64,
+}
+
+// Half-open time range [from, to) for one device, ordered by time ascending
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementQuery {
+    pub gateway_id: String,
+    pub device_id: String,
+    pub from: i64,
+    pub to: i64,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateQuery {
+    pub gateway_id: String,
+    pub device_id: String,
+    pub from: i64,
+    pub to: i64,
+    pub bucket_ms: i64,
+    pub limit: usize,
+}
+
+// Derived from the stored timestamps: cleared wins over acknowledged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmState {
+    Active,
+    Acknowledged,
+    Cleared,
+}
+
+impl AlarmState {
+    pub fn of(acknowledged_at: Option<i64>, cleared_at: Option<i64>) -> Self {
+        match (acknowledged_at, cleared_at) {
+            (_, Some(_)) => AlarmState::Cleared,
+            (Some(_), None) => AlarmState::Acknowledged,
+            (None, None) => AlarmState::Active,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlarmState::Active => "active",
+            AlarmState::Acknowledged => "acknowledged",
+            AlarmState::Cleared => "cleared",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "active" => Some(AlarmState::Active),
+            "acknowledged" => Some(AlarmState::Acknowledged),
+            "cleared" => Some(AlarmState::Cleared),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoredAlarm {
+    pub gateway_id: String,
+    pub device_id: String,
+    pub id: String,
+    pub kind: String,
+    pub severity: String,
+    pub state: AlarmState,
+    pub value: Option<f64>,
+    pub message: String,
+    pub raised_at: i64,
+    pub acknowledged_at: Option<i64>,
+    pub acknowledged_by: Option<String>,
+    pub cleared_at: Option<i64>,
+}
+
+// Newest alarms first; every filter is optional
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmQuery {
+    pub gateway_id: Option<String>,
+    pub device_id: Option<String>,
+    pub state: Option<AlarmState>,
+    pub limit: usize,
+}
@@ -1,14 +1,36 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use sqlx::PgPool;
+use tracing::{error, info, warn};
 
 use telemetry::{
-    adapters::{mqtt::MqttAdapter, postgres_storage::PostgresStorage},
-    config::AppConfig,
-    core::{processors::DefaultProcessor, services::TelemetryService},
+    adapters::{
+        csv_archive::CsvArchiver,
+        dead_letter_file::FileDeadLetterStore,
+        fanout_storage::{FanOutStorage, Sink},
+        http::{self, HttpState},
+        influx_line::InfluxLineWriter,
+        memory_storage::MemoryStorage,
+        mqtt::{self, MqttAdapter},
+        postgres_storage::{PostgresStorage, migrations, timeseries},
+        sqlite_storage::SqliteStorage,
+    },
+    config::{AppConfig, MigrateMode, StorageBackend},
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{DeadLetterPort, TelemetryProcessorPort, TelemetryQueryPort, TelemetryStoragePort},
+        processors::{DefaultProcessor, ValidatingProcessor, ValidationRules},
+        services::{BatchingInput, TelemetryService},
+    },
 };
 
-use tracing_subscriber;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Migrate,
+    ReplayDeadLetters,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -19,24 +41,241 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let config = AppConfig::load();
 
-    let processor = DefaultProcessor;
+    let command = match env::args().nth(1).as_deref() {
+        None => Command::Run,
+        Some("migrate") => Command::Migrate,
+        Some("replay-dead-letters") => Command::ReplayDeadLetters,
+        Some(other) => {
+            return Err(format!(
+                "Unknown command '{}' (available: migrate, replay-dead-letters)",
+                other
+            )
+            .into());
+        }
+    };
+
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+
+    let metrics = Metrics::new();
+    let health = Health::new();
+    let dead_letters: Arc<dyn DeadLetterPort> =
+        Arc::new(FileDeadLetterStore::new(&config.dead_letter.path));
+
+    // The storage and the query API share one backend instance
+    let mut postgres = None;
+    let (storage, query): (Box<dyn TelemetryStoragePort>, Arc<dyn TelemetryQueryPort>) =
+        match config.storage.backend {
+            StorageBackend::Postgres => {
+                // Connections are opened on first use, the database is checked further down
+                let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+                let pool = PgPool::connect_lazy(&database_url)?;
+                let storage =
+                    PostgresStorage::new(config.db, pool, metrics.clone(), health.clone());
+                postgres = Some(storage.clone());
+                (Box::new(storage.clone()), Arc::new(storage))
+            }
+            StorageBackend::Sqlite => {
+                let storage = SqliteStorage::connect(
+                    &config.storage.sqlite_path,
+                    metrics.clone(),
+                    health.clone(),
+                )
+                .await?;
+                (Box::new(storage.clone()), Arc::new(storage))
+            }
+            StorageBackend::Memory => {
+                warn!("Using in-memory storage, all data is lost when the service stops");
+                let storage = MemoryStorage::new();
+                (Box::new(storage.clone()), Arc::new(storage))
+            }
+        };
+
+    // Started first so /health answers while the broker and database are still connecting
+    if command == Command::Run {
+        let http_state = HttpState {
+            metrics: metrics.clone(),
+            health: health.clone(),
+            query,
+            max_query_limit: config.http.max_query_limit,
+        };
+        let http_shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = http::serve(config.http, http_state, http_shutdown).await {
+                error!("HTTP server error: {:?}", e);
+            }
+        });
+    }
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = PgPool::connect(&database_url).await?;
+    let mut processor: Box<dyn TelemetryProcessorPort> = Box::new(DefaultProcessor {
+        duplicates: config.processor.duplicates,
+    });
+    if let Some(path) = &config.processor.rules_path {
+        let rules = ValidationRules::load(path)
+            .map_err(|e| format!("Invalid processor rules in {}: {}", path, e))?;
+        info!(
+            "Validating measurements with {} rules from {}",
+            rules.rules.len(),
+            path
+        );
+        processor = Box::new(ValidatingProcessor {
+            inner: processor,
+            rules,
+        });
+    }
 
-    let storage = PostgresStorage::new(config.db, pool);
+    match &postgres {
+        Some(postgres) => {
+            prepare_postgres(postgres, command, shutdown_tx.subscribe()).await?;
+            if command == Command::Migrate {
+                return Ok(());
+            }
+        }
+        // SQLite applies its migrations when the file is opened, memory has no schema
+        None => {
+            health.set_db_available(true);
+            if command == Command::Migrate {
+                info!(
+                    "Schema of the {:?} backend is up to date",
+                    config.storage.backend
+                );
+                return Ok(());
+            }
+        }
+    }
+
+    let mut sinks = Vec::new();
+    if let Some(influx) = config.sinks.influx {
+        sinks.push(Sink {
+            name: "influx",
+            storage: Box::new(InfluxLineWriter::new(influx)),
+        });
+    }
+    if let Some(dir) = &config.sinks.csv_dir {
+        sinks.push(Sink {
+            name: "csv",
+            storage: Box::new(CsvArchiver::new(dir)),
+        });
+    }
+
+    let (storage, sink_workers): (Box<dyn TelemetryStoragePort>, _) = if sinks.is_empty() {
+        (storage, Vec::new())
+    } else {
+        info!(
+            "Forwarding stored intents to {} secondary sinks",
+            sinks.len()
+        );
+        let (fanout, workers) = FanOutStorage::spawn(
+            storage,
+            sinks,
+            config.sinks.queue_size,
+            config.retry,
+            metrics.clone(),
+        );
+        (Box::new(fanout), workers)
+    };
 
     let service = TelemetryService {
-        processor: Box::new(processor),
-        storage: Box::new(storage),
+        processor,
+        storage,
+        dead_letters: dead_letters.clone(),
+        retry: config.retry,
+        metrics: metrics.clone(),
+        health: health.clone(),
     };
 
+    if command == Command::ReplayDeadLetters {
+        let summary = service
+            .replay_dead_letters(&mqtt::parse_telemetry_message)
+            .await?;
+        info!(
+            "Replayed {} dead letters, {} failed again and were kept",
+            summary.replayed, summary.failed
+        );
+        drop(service);
+        for worker in sink_workers {
+            worker.await?;
+        }
+        return Ok(());
+    }
+
+    let (input, batch_worker) = BatchingInput::spawn(Arc::new(service), config.batch);
+
     let mqtt = MqttAdapter {
         config: config.mqtt,
-        input_port: Box::new(service),
+        input_port: Box::new(input),
+        dead_letters,
+        metrics,
+        health,
     };
 
-    mqtt.run().await?;
+    let shutdown_signal = shutdown_tx.clone();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        let _ = shutdown_signal.send(());
+    });
+
+    mqtt.run(shutdown_tx.subscribe()).await?;
+
+    // Dropping the adapter closes the batch queue; wait until queued messages are stored
+    drop(mqtt);
+    info!("Draining queued telemetry messages");
+    batch_worker.await?;
+    // The service is gone with the batch worker, which closes the sink queues
+    for worker in sink_workers {
+        worker.await?;
+    }
+    info!("Telemetry service stopped");
 
     Ok(())
 }
+
+// Checks the connection, applies or verifies migrations and sets up the time-series layout
+async fn prepare_postgres(
+    storage: &PostgresStorage,
+    command: Command,
+    shutdown: tokio::sync::broadcast::Receiver<()>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Fails fast when the database is unreachable at startup
+    storage.pool.acquire().await?;
+    storage.health.set_db_available(true);
+
+    match (command, storage.config.migrate) {
+        (Command::Migrate, _) => return migrations::run(&storage.pool).await,
+        (_, MigrateMode::Apply) => migrations::run(&storage.pool).await?,
+        (_, MigrateMode::Verify) => migrations::verify(&storage.pool).await?,
+    }
+
+    if command == Command::Run {
+        let mode = storage.setup_timeseries().await?;
+        timeseries::spawn_maintenance(
+            storage.pool.clone(),
+            storage.config.timeseries.clone(),
+            mode,
+            shutdown,
+        );
+    }
+
+    Ok(())
+}
+
+// Ctrl+C locally, SIGTERM from docker / systemd
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("Shutdown signal received (Ctrl+C)"),
+            _ = sigterm.recv() => info!("Shutdown signal received (SIGTERM)"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+        info!("Shutdown signal received (Ctrl+C)");
+    }
+}
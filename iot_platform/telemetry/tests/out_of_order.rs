@@ -0,0 +1,264 @@
+// End-to-end ordering guarantees (ADR-001, ADR-002, ADR-003): MQTT topic and payload are
+// parsed, processed by DefaultProcessor and executed by TelemetryService. Every case runs
+// against the memory and SQLite adapters.
+use serde_json::json;
+use std::{path::PathBuf, sync::Arc};
+use telemetry::{
+    adapters::{
+        dead_letter_file::FileDeadLetterStore, memory_storage::MemoryStorage,
+        mqtt::parse_telemetry_message, sqlite_storage::SqliteStorage,
+    },
+    config::RetryConfig,
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{DeadLetterPort, TelemetryInputPort, TelemetryQueryPort, TelemetryStoragePort},
+        processors::DefaultProcessor,
+        services::TelemetryService,
+    },
+    domain::{
+        dead_letter::RawMessage,
+        intents::DuplicatePolicy,
+        queries::{DeviceRecord, MeasurementQuery},
+    },
+};
+
+const T0: i64 = 1_767_225_600_000;
+
+#[derive(Clone, Copy, Debug)]
+enum Backend {
+    Memory,
+    Sqlite,
+}
+
+struct Harness {
+    service: TelemetryService,
+    query: Arc<dyn TelemetryQueryPort>,
+    dead_letters: Arc<FileDeadLetterStore>,
+    // Removed on drop, holds the SQLite database
+    dir: PathBuf,
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Harness {
+    async fn new(backend: Backend, name: &str, duplicates: DuplicatePolicy) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("telemetry-ooo-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (storage, query): (Box<dyn TelemetryStoragePort>, Arc<dyn TelemetryQueryPort>) =
+            match backend {
+                Backend::Memory => {
+                    let storage = MemoryStorage::new();
+                    (Box::new(storage.clone()), Arc::new(storage))
+                }
+                Backend::Sqlite => {
+                    let path = dir.join("telemetry.db");
+                    let storage = SqliteStorage::connect(
+                        path.to_str().unwrap(),
+                        Metrics::new(),
+                        Health::new(),
+                    )
+                    .await
+                    .unwrap();
+                    (Box::new(storage.clone()), Arc::new(storage))
+                }
+            };
+        let dead_letters = Arc::new(FileDeadLetterStore::new(dir.join("dead_letters.jsonl")));
+        let service = TelemetryService {
+            processor: Box::new(DefaultProcessor { duplicates }),
+            storage,
+            dead_letters: dead_letters.clone(),
+            retry: RetryConfig {
+                max_attempts: 1,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 1,
+            },
+            metrics: Metrics::new(),
+            health: Health::new(),
+        };
+
+        Harness {
+            service,
+            query,
+            dead_letters,
+            dir,
+        }
+    }
+
+    async fn publish(&self, kind: &str, timestamp: i64, value: Option<f64>) {
+        let mut payload = json!({
+            "ctx": {
+                "gateway_id": "gw1",
+                "gateway_name": "Gateway 1",
+                "device_name": "Temperature",
+                "device_id": "temp",
+            },
+            "meta": { "timestamp": timestamp },
+        });
+        if let Some(value) = value {
+            payload["value"] = json!(value);
+        }
+
+        let raw = RawMessage {
+            topic: format!("gw1/devices/temp/{}", kind),
+            payload: serde_json::to_vec(&payload).unwrap(),
+        };
+        let msg = parse_telemetry_message(&raw.topic, &raw.payload).unwrap();
+        self.service.on_message(raw, msg).await;
+    }
+
+    async fn device(&self) -> DeviceRecord {
+        self.query
+            .list_devices(Some("gw1"))
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    async fn values(&self) -> Vec<(i64, f64)> {
+        let query = MeasurementQuery {
+            gateway_id: "gw1".into(),
+            device_id: "temp".into(),
+            from: 0,
+            to: i64::MAX / 2,
+            limit: 100,
+        };
+        self.query
+            .measurements(&query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.timestamp, m.value))
+            .collect()
+    }
+
+    async fn dead_letter_count(&self) -> usize {
+        let count = self.dead_letters.list().await.unwrap().len();
+        let _ = std::fs::remove_file(&self.dead_letters.path);
+        count
+    }
+}
+
+macro_rules! for_each_backend {
+    ($($case:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::cases::$case(super::Backend::Memory).await;
+                }
+            )*
+        }
+
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::cases::$case(super::Backend::Sqlite).await;
+                }
+            )*
+        }
+    };
+}
+
+for_each_backend!(
+    value_before_created_creates_ghost_device_that_is_enriched_later,
+    late_value_does_not_reactivate_but_newer_value_does,
+    late_created_and_removed_events_keep_the_newest_state,
+    redelivered_value_is_stored_once_under_every_policy,
+    conflicting_value_follows_the_duplicate_policy,
+);
+
+mod cases {
+    use super::*;
+
+    pub async fn value_before_created_creates_ghost_device_that_is_enriched_later(
+        backend: Backend,
+    ) {
+        let h = Harness::new(backend, "ghost", DuplicatePolicy::KeepLast).await;
+
+        h.publish("value", T0 + 1000, Some(21.5)).await;
+        let ghost = h.device().await;
+        assert_eq!(ghost.name, None);
+        assert_eq!(h.values().await, vec![(T0 + 1000, 21.5)]);
+
+        h.publish("created", T0, None).await;
+        let device = h.device().await;
+        assert_eq!(device.name.as_deref(), Some("Temperature"));
+        assert_eq!(device.created_at, Some(T0));
+        assert_eq!(h.dead_letter_count().await, 0);
+    }
+
+    pub async fn late_value_does_not_reactivate_but_newer_value_does(backend: Backend) {
+        let h = Harness::new(backend, "reactivate", DuplicatePolicy::KeepLast).await;
+
+        h.publish("created", T0, None).await;
+        h.publish("removed", T0 + 2000, None).await;
+
+        // Delayed reading taken before the removal
+        h.publish("value", T0 + 1000, Some(1.0)).await;
+        assert_eq!(h.device().await.removed_at, Some(T0 + 2000));
+
+        // Telemetry after the removal wins over the lifecycle event
+        h.publish("value", T0 + 3000, Some(2.0)).await;
+        assert_eq!(h.device().await.removed_at, None);
+
+        assert_eq!(h.values().await, vec![(T0 + 1000, 1.0), (T0 + 3000, 2.0)]);
+        assert_eq!(h.dead_letter_count().await, 0);
+    }
+
+    pub async fn late_created_and_removed_events_keep_the_newest_state(backend: Backend) {
+        let h = Harness::new(backend, "lifecycle", DuplicatePolicy::KeepLast).await;
+
+        h.publish("value", T0 + 1000, Some(1.0)).await;
+        h.publish("removed", T0 + 3000, None).await;
+        h.publish("created", T0, None).await;
+        h.publish("removed", T0 + 2000, None).await;
+
+        let device = h.device().await;
+        assert_eq!(device.removed_at, Some(T0 + 3000));
+        assert_eq!(device.created_at, Some(T0));
+    }
+
+    pub async fn redelivered_value_is_stored_once_under_every_policy(backend: Backend) {
+        for policy in [
+            DuplicatePolicy::KeepFirst,
+            DuplicatePolicy::KeepLast,
+            DuplicatePolicy::Reject,
+        ] {
+            let h = Harness::new(backend, policy.as_str(), policy).await;
+
+            h.publish("value", T0, Some(21.5)).await;
+            h.publish("value", T0, Some(21.5)).await;
+
+            assert_eq!(h.values().await, vec![(T0, 21.5)], "{:?}", policy);
+            assert_eq!(h.dead_letter_count().await, 0, "{:?}", policy);
+        }
+    }
+
+    pub async fn conflicting_value_follows_the_duplicate_policy(backend: Backend) {
+        let cases = [
+            (DuplicatePolicy::KeepFirst, 1.0, 0),
+            (DuplicatePolicy::KeepLast, 2.0, 0),
+            // The rejected reading is kept in the dead-letter store instead of being lost
+            (DuplicatePolicy::Reject, 1.0, 1),
+        ];
+
+        for (policy, stored, dead_letters) in cases {
+            let h = Harness::new(backend, &format!("conflict-{}", policy.as_str()), policy).await;
+
+            h.publish("value", T0, Some(1.0)).await;
+            h.publish("value", T0, Some(2.0)).await;
+
+            assert_eq!(h.values().await, vec![(T0, stored)], "{:?}", policy);
+            assert_eq!(h.dead_letter_count().await, dead_letters, "{:?}", policy);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use async_trait::async_trait;
+use axum::{
+    body::{Body, to_bytes},
+    http::{Request, StatusCode},
+};
+use serde_json::Value;
+use shared_models::AggregateWindow;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+use telemetry::{
+    adapters::http::{HttpState, router},
+    core::{health::Health, metrics::Metrics, ports::telemetry_query_port::TelemetryQueryPort},
+    domain::queries::{
+        AggregateBucket, AggregateQuery, AlarmQuery, AlarmState, DeviceRecord, GatewayRecord,
+        Measurement, MeasurementQuery, StoredAlarm,
+    },
+};
+use tower::ServiceExt;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Returns canned rows and records the last measurement query it received
+#[derive(Default)]
+struct FakeQueries {
+    last_query: Mutex<Option<MeasurementQuery>>,
+    last_alarm_query: Mutex<Option<AlarmQuery>>,
+    db_down: AtomicBool,
+}
+
+#[async_trait]
+impl TelemetryQueryPort for FakeQueries {
+    async fn ping(&self) -> Result<(), BoxError> {
+        if self.db_down.load(Ordering::Relaxed) {
+            return Err("connection refused".into());
+        }
+        Ok(())
+    }
+
+    async fn list_gateways(&self) -> Result<Vec<GatewayRecord>, BoxError> {
+        Ok(vec![GatewayRecord {
+            id: "gw1".into(),
+            name: "Gateway 1".into(),
+        }])
+    }
+
+    async fn list_devices(&self, gateway_id: Option<&str>) -> Result<Vec<DeviceRecord>, BoxError> {
+        if gateway_id == Some("broken") {
+            return Err("connection refused".into());
+        }
+        Ok(vec![DeviceRecord {
+            gateway_id: gateway_id.unwrap_or("gw1").into(),
+            id: "temp".into(),
+            name: Some("Temperature".into()),
+            created_at: Some(1_000),
+            removed_at: None,
+            last_seen: Some(2_000),
+        }])
+    }
+
+    async fn measurements(&self, query: &MeasurementQuery) -> Result<Vec<Measurement>, BoxError> {
+        *self.last_query.lock().unwrap() = Some(query.clone());
+        Ok(vec![Measurement {
+            timestamp: query.from,
+            value: 21.5,
+        }])
+    }
+
+    async fn aggregate(&self, query: &AggregateQuery) -> Result<Vec<AggregateBucket>, BoxError> {
+        Ok(vec![AggregateBucket {
+            bucket: query.from,
+            min: 1.0,
+            max: 3.0,
+            avg: 2.0,
+            last: 3.0,
+            count: 3,
+        }])
+    }
+
+    async fn windows(&self, query: &MeasurementQuery) -> Result<Vec<AggregateWindow>, BoxError> {
+        Ok(vec![AggregateWindow {
+            start: query.from,
+            end: query.from + 60_000,
+            count: 2,
+            min: 1.0,
+            max: 3.0,
+            avg: 2.0,
+            last: 3.0,
+            first_timestamp: query.from + 1_000,
+            last_timestamp: query.from + 59_000,
+        }])
+    }
+
+    async fn alarms(&self, query: &AlarmQuery) -> Result<Vec<StoredAlarm>, BoxError> {
+        *self.last_alarm_query.lock().unwrap() = Some(query.clone());
+        Ok(vec![StoredAlarm {
+            gateway_id: "gw1".into(),
+            device_id: "temp".into(),
+            id: "temp-hi-1000".into(),
+            kind: "hi".into(),
+            severity: "warning".into(),
+            state: AlarmState::Acknowledged,
+            value: Some(31.0),
+            message: "value 31 above 30".into(),
+            raised_at: 1_000,
+            acknowledged_at: Some(2_000),
+            acknowledged_by: Some("operator".into()),
+            cleared_at: None,
+        }])
+    }
+}
+
+fn app(queries: Arc<FakeQueries>) -> axum::Router {
+    app_with_health(queries, Health::new())
+}
+
+fn app_with_health(queries: Arc<FakeQueries>, health: Health) -> axum::Router {
+    router(HttpState {
+        metrics: Metrics::new(),
+        health,
+        query: queries,
+        max_query_limit: 500,
+    })
+}
+
+async fn get(app: axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
+    let response = app
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn lists_gateways_and_devices() {
+    let app = app(Arc::new(FakeQueries::default()));
+
+    let (status, body) = get(app.clone(), "/api/gateways").await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["id"], "gw1");
+
+    let (status, body) = get(app, "/api/gateways/gw2/devices").await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["gateway_id"], "gw2");
+    assert_eq!(json[0]["last_seen"], 2_000);
+}
+
+#[tokio::test]
+async fn windows_reported_by_the_gateway_are_listed() {
+    let app = app(Arc::new(FakeQueries::default()));
+
+    let (status, body) = get(
+        app.clone(),
+        "/api/gateways/gw1/devices/temp/windows?from=60000&to=120000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["start"], 60_000);
+    assert_eq!(json[0]["count"], 2);
+
+    let (status, _) = get(app, "/api/gateways/gw1/devices/temp/windows?limit=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn measurements_pass_range_and_limit() {
+    let queries = Arc::new(FakeQueries::default());
+    let app = app(queries.clone());
+
+    let (status, body) = get(
+        app,
+        "/api/gateways/gw1/devices/temp/measurements?from=1000&to=5000&limit=10",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["value"], 21.5);
+
+    let query = queries.last_query.lock().unwrap().clone().unwrap();
+    assert_eq!(query.gateway_id, "gw1");
+    assert_eq!(query.device_id, "temp");
+    assert_eq!((query.from, query.to, query.limit), (1000, 5000, 10));
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_rejected() {
+    let app = app(Arc::new(FakeQueries::default()));
+    let base = "/api/gateways/gw1/devices/temp";
+
+    for uri in [
+        format!("{base}/measurements?from=5000&to=1000"),
+        format!("{base}/measurements?from=-1&to=1000"),
+        format!("{base}/measurements?limit=501"),
+        format!("{base}/measurements?limit=0"),
+        format!("{base}/aggregate?bucket_ms=10"),
+        // 1000 one-second buckets exceed the limit of 500
+        format!("{base}/aggregate?from=0&to=1000000&bucket_ms=1000"),
+    ] {
+        let (status, _) = get(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn out_of_range_bounds_are_clamped_or_rejected() {
+    let queries = Arc::new(FakeQueries::default());
+    let app = app(queries.clone());
+    let base = "/api/gateways/gw1/devices/temp";
+    let max = chrono::DateTime::<chrono::Utc>::MAX_UTC.timestamp_millis();
+
+    let (status, _) = get(
+        app.clone(),
+        &format!("{base}/measurements?from=0&to={}", i64::MAX),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let query = queries.last_query.lock().unwrap().clone().unwrap();
+    assert_eq!((query.from, query.to), (0, max));
+
+    // The default range starts an hour before `to`
+    let (status, _) = get(app.clone(), &format!("{base}/measurements?to={}", i64::MAX)).await;
+    assert_eq!(status, StatusCode::OK);
+    let query = queries.last_query.lock().unwrap().clone().unwrap();
+    assert_eq!((query.from, query.to), (max - 3_600_000, max));
+
+    for uri in [
+        format!("{base}/measurements?to={}", i64::MIN),
+        format!("{base}/measurements?to=-1"),
+        format!("{base}/measurements?from={}", i64::MAX),
+    ] {
+        let (status, _) = get(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    // The bucket count must not overflow either
+    let uri = format!(
+        "{base}/aggregate?from=0&to={}&bucket_ms={}",
+        i64::MAX,
+        i64::MAX
+    );
+    let (status, _) = get(app, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn bucket_count_follows_epoch_aligned_buckets() {
+    let queries = Arc::new(FakeQueries::default());
+    let app = app(queries.clone());
+    // 30s..90s spans one minute, but touches the buckets starting at 0 and 60s
+    let uri = "/api/gateways/gw1/devices/temp/aggregate?from=30000&to=90000&bucket_ms=60000";
+
+    let (status, _) = get(app.clone(), &format!("{uri}&limit=1")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get(app, &format!("{uri}&limit=2")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn aggregate_returns_buckets() {
+    let app = app(Arc::new(FakeQueries::default()));
+
+    let (status, body) = get(
+        app,
+        "/api/gateways/gw1/devices/temp/aggregate?from=0&to=3600000&bucket_ms=60000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["count"], 3);
+    assert_eq!(json[0]["avg"], 2.0);
+}
+
+#[tokio::test]
+async fn storage_errors_map_to_internal_error() {
+    let app = app(Arc::new(FakeQueries::default()));
+
+    let (status, _) = get(app, "/api/devices?gateway_id=broken").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn alarms_filter_by_state() {
+    let queries = Arc::new(FakeQueries::default());
+    let app = app(queries.clone());
+
+    let (status, body) = get(app.clone(), "/api/alarms?gateway_id=gw1&state=acknowledged").await;
+    assert_eq!(status, StatusCode::OK);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json[0]["state"], "acknowledged");
+    assert_eq!(json[0]["acknowledged_by"], "operator");
+
+    let query = queries.last_alarm_query.lock().unwrap().clone().unwrap();
+    assert_eq!(query.gateway_id.as_deref(), Some("gw1"));
+    assert_eq!(query.state, Some(AlarmState::Acknowledged));
+
+    let (status, _) = get(app, "/api/alarms?state=open").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn readiness_probes_the_database_on_every_call() {
+    let queries = Arc::new(FakeQueries::default());
+    let health = Health::new();
+    let app = app_with_health(queries.clone(), health.clone());
+
+    let (status, _) = get(app.clone(), "/health").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Database reachable, broker not (yet) connected
+    let (status, body) = get(app.clone(), "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["status"], "degraded");
+    assert_eq!(json["db_available"], true);
+
+    health.set_mqtt_connected(true);
+    let (status, body) = get(app.clone(), "/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_slice::<Value>(&body).unwrap()["status"],
+        "up"
+    );
+
+    // No writes in between: the probe alone notices the outage and the recovery
+    queries.db_down.store(true, Ordering::Relaxed);
+    let (status, body) = get(app.clone(), "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        serde_json::from_slice::<Value>(&body).unwrap()["status"],
+        "down"
+    );
+
+    queries.db_down.store(false, Ordering::Relaxed);
+    let (status, _) = get(app, "/ready").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn metrics_are_served_as_prometheus_text() {
+    let metrics = Metrics::new();
+    metrics.message_received("value");
+    let app = router(HttpState {
+        metrics,
+        health: Health::new(),
+        query: Arc::new(FakeQueries::default()),
+        max_query_limit: 500,
+    });
+
+    let response = app
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; version=0.0.4"
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains(r#"telemetry_messages_received_total{topic_type="value"} 1"#));
+}
@@ -0,0 +1,697 @@
+// Shared behaviour every TelemetryStoragePort adapter must implement. Each case runs against
+// the memory and SQLite adapters, and against Postgres when TEST_DATABASE_URL is set.
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use telemetry::{
+    adapters::{
+        memory_storage::MemoryStorage,
+        postgres_storage::{PostgresStorage, migrations},
+        sqlite_storage::SqliteStorage,
+    },
+    config::{DbConfig, MigrateMode, TimeseriesConfig, TimeseriesMode},
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{TelemetryQueryPort, TelemetryStoragePort},
+    },
+    domain::{
+        intents::{DuplicatePolicy, Intent},
+        queries::DeviceRecord,
+    },
+};
+
+pub struct Backend {
+    storage: Box<dyn TelemetryStoragePort>,
+    query: Arc<dyn TelemetryQueryPort>,
+    // Cases use their own gateway so they can share one Postgres database
+    gateway: String,
+    _dir: Option<TempDir>,
+}
+
+pub struct TempDir(std::path::PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn unique(name: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{}-{}-{}", name, std::process::id(), nanos)
+}
+
+async fn memory(case: &str) -> Option<Backend> {
+    let storage = MemoryStorage::new();
+    Some(Backend {
+        storage: Box::new(storage.clone()),
+        query: Arc::new(storage),
+        gateway: unique(case),
+        _dir: None,
+    })
+}
+
+async fn sqlite(case: &str) -> Option<Backend> {
+    let dir = std::env::temp_dir().join(unique(&format!("telemetry-sqlite-{}", case)));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("telemetry.db");
+    let storage = SqliteStorage::connect(path.to_str().unwrap(), Metrics::new(), Health::new())
+        .await
+        .unwrap();
+
+    Some(Backend {
+        storage: Box::new(storage.clone()),
+        query: Arc::new(storage),
+        gateway: unique(case),
+        _dir: Some(TempDir(dir)),
+    })
+}
+
+async fn postgres(case: &str) -> Option<Backend> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping Postgres conformance");
+        return None;
+    };
+
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    migrations::run(&pool).await.unwrap();
+
+    let config = DbConfig {
+        url,
+        migrate: MigrateMode::Apply,
+        timeseries: TimeseriesConfig {
+            mode: TimeseriesMode::Off,
+            chunk_interval: "1 day".into(),
+            compress_after: None,
+            retention: None,
+            aggregates: false,
+            maintenance_interval_secs: 300,
+        },
+    };
+    let storage = PostgresStorage::new(config, pool, Metrics::new(), Health::new());
+
+    Some(Backend {
+        storage: Box::new(storage.clone()),
+        query: Arc::new(storage),
+        gateway: unique(case),
+        _dir: None,
+    })
+}
+
+macro_rules! conformance_suite {
+    ($backend:ident: $($case:ident),* $(,)?) => {
+        mod $backend {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    if let Some(backend) = super::$backend(stringify!($case)).await {
+                        super::cases::$case(backend).await;
+                    }
+                }
+            )*
+        }
+    };
+}
+
+macro_rules! for_each_backend {
+    ($($case:ident),* $(,)?) => {
+        conformance_suite!(memory: $($case),*);
+        conformance_suite!(sqlite: $($case),*);
+        conformance_suite!(postgres: $($case),*);
+    };
+}
+
+for_each_backend!(
+    device_created_registers_gateway_and_device,
+    ensure_gateway_updates_name,
+    measurement_upsert_keeps_last_value,
+    keep_first_ignores_later_values,
+    reject_accepts_redelivery_and_refuses_conflicts,
+    last_seen_only_moves_forward,
+    removal_and_reactivation_respect_timestamps,
+    metadata_upsert_clears_removal_and_keeps_created_at,
+    late_metadata_does_not_undo_removal,
+    updates_for_unknown_devices_are_ignored,
+    failed_batch_is_rolled_back,
+    failed_batch_restores_updated_rows,
+    measurement_without_value_is_rejected,
+    rejected_measurements_are_kept_apart_from_values,
+    alarm_lifecycle_merges_out_of_order_events,
+    edge_windows_are_replaced_on_redelivery,
+    device_of_unknown_gateway_is_rejected,
+    batch_writes_measurements_after_devices,
+    queries_respect_range_limit_and_buckets,
+);
+
+mod cases {
+    use super::*;
+    use shared_models::{AggregateWindow, AlarmKind, AlarmRecord, AlarmSeverity};
+    use telemetry::domain::queries::{AggregateQuery, AlarmQuery, AlarmState, MeasurementQuery};
+
+    // Recent enough for every timestamp type, aligned to a full hour
+    const T0: i64 = 1_767_225_600_000;
+
+    fn ensure(gateway: &str, device: &str) -> Vec<Intent> {
+        vec![
+            Intent::EnsureGatewayExists {
+                gateway_id: gateway.into(),
+                gateway_name: "Gateway".into(),
+            },
+            Intent::EnsureDeviceExists {
+                device_id: device.into(),
+                gateway_id: gateway.into(),
+            },
+        ]
+    }
+
+    fn measurement(gateway: &str, device: &str, timestamp: i64, value: Option<f64>) -> Intent {
+        with_policy(gateway, device, timestamp, value, DuplicatePolicy::KeepLast)
+    }
+
+    fn with_policy(
+        gateway: &str,
+        device: &str,
+        timestamp: i64,
+        value: Option<f64>,
+        on_duplicate: DuplicatePolicy,
+    ) -> Intent {
+        Intent::RecordMeasurement {
+            device_id: device.into(),
+            gateway_id: gateway.into(),
+            value,
+            timestamp,
+            on_duplicate,
+        }
+    }
+
+    fn metadata(gateway: &str, device: &str, name: &str, timestamp: i64) -> Intent {
+        Intent::UpsertDeviceMetadata {
+            device_id: device.into(),
+            gateway_id: gateway.into(),
+            gateway_name: "Gateway".into(),
+            device_name: name.into(),
+            timestamp,
+        }
+    }
+
+    async fn device(b: &Backend, device: &str) -> Option<DeviceRecord> {
+        b.query
+            .list_devices(Some(&b.gateway))
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|d| d.id == device)
+    }
+
+    async fn values(b: &Backend, device: &str) -> Vec<(i64, f64)> {
+        let query = MeasurementQuery {
+            gateway_id: b.gateway.clone(),
+            device_id: device.into(),
+            from: 0,
+            to: i64::MAX / 2,
+            limit: 1000,
+        };
+        b.query
+            .measurements(&query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.timestamp, m.value))
+            .collect()
+    }
+
+    async fn gateway_name(b: &Backend) -> Option<String> {
+        b.query
+            .list_gateways()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|g| g.id == b.gateway)
+            .map(|g| g.name)
+    }
+
+    pub async fn device_created_registers_gateway_and_device(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(metadata(&b.gateway, "temp", "Temperature", T0));
+        b.storage.execute(intents).await.unwrap();
+
+        assert_eq!(gateway_name(&b).await.as_deref(), Some("Gateway"));
+        let d = device(&b, "temp").await.unwrap();
+        assert_eq!(d.name.as_deref(), Some("Temperature"));
+        assert_eq!(d.created_at, Some(T0));
+        assert_eq!(d.last_seen, Some(T0));
+        assert_eq!(d.removed_at, None);
+    }
+
+    pub async fn ensure_gateway_updates_name(b: Backend) {
+        b.storage.execute(ensure(&b.gateway, "temp")).await.unwrap();
+        b.storage
+            .execute(vec![Intent::EnsureGatewayExists {
+                gateway_id: b.gateway.clone(),
+                gateway_name: "Renamed".into(),
+            }])
+            .await
+            .unwrap();
+
+        assert_eq!(gateway_name(&b).await.as_deref(), Some("Renamed"));
+    }
+
+    pub async fn measurement_upsert_keeps_last_value(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(measurement(&b.gateway, "temp", T0, Some(1.0)));
+        intents.push(measurement(&b.gateway, "temp", T0, Some(2.0)));
+        b.storage.execute(intents).await.unwrap();
+
+        b.storage
+            .execute(vec![measurement(&b.gateway, "temp", T0 + 1, Some(3.0))])
+            .await
+            .unwrap();
+        b.storage
+            .execute(vec![measurement(&b.gateway, "temp", T0 + 1, Some(4.0))])
+            .await
+            .unwrap();
+
+        assert_eq!(values(&b, "temp").await, vec![(T0, 2.0), (T0 + 1, 4.0)]);
+    }
+
+    pub async fn keep_first_ignores_later_values(b: Backend) {
+        let first = |value| {
+            with_policy(
+                &b.gateway,
+                "temp",
+                T0,
+                Some(value),
+                DuplicatePolicy::KeepFirst,
+            )
+        };
+
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(first(1.0));
+        intents.push(first(2.0));
+        b.storage.execute(intents).await.unwrap();
+        b.storage.execute(vec![first(3.0)]).await.unwrap();
+
+        assert_eq!(values(&b, "temp").await, vec![(T0, 1.0)]);
+    }
+
+    pub async fn reject_accepts_redelivery_and_refuses_conflicts(b: Backend) {
+        let reject = |timestamp, value| {
+            with_policy(
+                &b.gateway,
+                "temp",
+                timestamp,
+                Some(value),
+                DuplicatePolicy::Reject,
+            )
+        };
+
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(reject(T0, 1.0));
+        b.storage.execute(intents.clone()).await.unwrap();
+        // A redelivered message is idempotent
+        b.storage.execute(intents).await.unwrap();
+
+        // The conflicting value fails the whole batch, including the new reading
+        let err = b
+            .storage
+            .execute(vec![reject(T0 + 1, 5.0), reject(T0, 2.0)])
+            .await
+            .unwrap_err();
+        assert!(!err.is_transient(), "{err}");
+
+        let err = b
+            .storage
+            .execute(vec![reject(T0 + 2, 1.0), reject(T0 + 2, 2.0)])
+            .await
+            .unwrap_err();
+        assert!(!err.is_transient(), "{err}");
+
+        assert_eq!(values(&b, "temp").await, vec![(T0, 1.0)]);
+    }
+
+    pub async fn last_seen_only_moves_forward(b: Backend) {
+        b.storage.execute(ensure(&b.gateway, "temp")).await.unwrap();
+
+        for timestamp in [T0 + 2000, T0 + 1000] {
+            b.storage
+                .execute(vec![Intent::UpdateDeviceLastSeen {
+                    device_id: "temp".into(),
+                    gateway_id: b.gateway.clone(),
+                    timestamp,
+                }])
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(device(&b, "temp").await.unwrap().last_seen, Some(T0 + 2000));
+    }
+
+    pub async fn removal_and_reactivation_respect_timestamps(b: Backend) {
+        b.storage.execute(ensure(&b.gateway, "temp")).await.unwrap();
+
+        let removed = |timestamp| Intent::MarkDeviceRemoved {
+            device_id: "temp".into(),
+            gateway_id: b.gateway.clone(),
+            timestamp,
+        };
+        let reactivated = |timestamp| Intent::ReactivateDevice {
+            device_id: "temp".into(),
+            gateway_id: b.gateway.clone(),
+            timestamp,
+        };
+
+        b.storage.execute(vec![removed(T0 + 2000)]).await.unwrap();
+        // An older removal does not move removed_at back
+        b.storage.execute(vec![removed(T0 + 1000)]).await.unwrap();
+        assert_eq!(
+            device(&b, "temp").await.unwrap().removed_at,
+            Some(T0 + 2000)
+        );
+
+        // A value observed before the removal does not bring the device back
+        b.storage
+            .execute(vec![reactivated(T0 + 1500)])
+            .await
+            .unwrap();
+        assert_eq!(
+            device(&b, "temp").await.unwrap().removed_at,
+            Some(T0 + 2000)
+        );
+
+        b.storage
+            .execute(vec![reactivated(T0 + 3000)])
+            .await
+            .unwrap();
+        assert_eq!(device(&b, "temp").await.unwrap().removed_at, None);
+    }
+
+    pub async fn metadata_upsert_clears_removal_and_keeps_created_at(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(metadata(&b.gateway, "temp", "Temperature", T0));
+        b.storage.execute(intents).await.unwrap();
+
+        b.storage
+            .execute(vec![Intent::MarkDeviceRemoved {
+                device_id: "temp".into(),
+                gateway_id: b.gateway.clone(),
+                timestamp: T0 + 1000,
+            }])
+            .await
+            .unwrap();
+        b.storage
+            .execute(vec![metadata(&b.gateway, "temp", "Outside", T0 + 2000)])
+            .await
+            .unwrap();
+
+        let d = device(&b, "temp").await.unwrap();
+        assert_eq!(d.name.as_deref(), Some("Outside"));
+        assert_eq!(d.created_at, Some(T0));
+        assert_eq!(d.last_seen, Some(T0 + 2000));
+        assert_eq!(d.removed_at, None);
+    }
+
+    pub async fn late_metadata_does_not_undo_removal(b: Backend) {
+        b.storage.execute(ensure(&b.gateway, "temp")).await.unwrap();
+        b.storage
+            .execute(vec![Intent::MarkDeviceRemoved {
+                device_id: "temp".into(),
+                gateway_id: b.gateway.clone(),
+                timestamp: T0 + 2000,
+            }])
+            .await
+            .unwrap();
+
+        b.storage
+            .execute(vec![metadata(&b.gateway, "temp", "Temperature", T0 + 1000)])
+            .await
+            .unwrap();
+
+        let d = device(&b, "temp").await.unwrap();
+        assert_eq!(d.name.as_deref(), Some("Temperature"));
+        assert_eq!(d.removed_at, Some(T0 + 2000));
+    }
+
+    pub async fn updates_for_unknown_devices_are_ignored(b: Backend) {
+        b.storage
+            .execute(vec![
+                Intent::EnsureGatewayExists {
+                    gateway_id: b.gateway.clone(),
+                    gateway_name: "Gateway".into(),
+                },
+                Intent::UpdateDeviceLastSeen {
+                    device_id: "ghost".into(),
+                    gateway_id: b.gateway.clone(),
+                    timestamp: T0,
+                },
+                Intent::MarkDeviceRemoved {
+                    device_id: "ghost".into(),
+                    gateway_id: b.gateway.clone(),
+                    timestamp: T0,
+                },
+                metadata(&b.gateway, "ghost", "Ghost", T0),
+            ])
+            .await
+            .unwrap();
+
+        assert!(device(&b, "ghost").await.is_none());
+    }
+
+    pub async fn failed_batch_is_rolled_back(b: Backend) {
+        let mut first = ensure(&b.gateway, "temp");
+        first.push(measurement(&b.gateway, "temp", T0, Some(1.0)));
+        let second = vec![measurement(&b.gateway, "missing", T0, Some(2.0))];
+
+        let err = b.storage.execute_batch(&[first, second]).await.unwrap_err();
+
+        assert!(!err.is_transient(), "{err}");
+        assert_eq!(gateway_name(&b).await, None);
+        assert!(values(&b, "temp").await.is_empty());
+    }
+
+    pub async fn failed_batch_restores_updated_rows(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(metadata(&b.gateway, "temp", "Temperature", T0));
+        b.storage.execute(intents).await.unwrap();
+
+        // Updates the device and renames the gateway before an intent fails
+        let failing = vec![
+            metadata(&b.gateway, "temp", "Renamed", T0 + 1_000),
+            Intent::EnsureGatewayExists {
+                gateway_id: b.gateway.clone(),
+                gateway_name: "Renamed".into(),
+            },
+            measurement(&b.gateway, "missing", T0, Some(2.0)),
+        ];
+        b.storage.execute(failing).await.unwrap_err();
+
+        assert_eq!(gateway_name(&b).await.as_deref(), Some("Gateway"));
+        let device = device(&b, "temp").await.unwrap();
+        assert_eq!(device.name.as_deref(), Some("Temperature"));
+        assert_eq!(device.last_seen, Some(T0));
+    }
+
+    pub async fn measurement_without_value_is_rejected(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(measurement(&b.gateway, "temp", T0, None));
+
+        let err = b.storage.execute(intents).await.unwrap_err();
+
+        assert!(!err.is_transient(), "{err}");
+        assert!(device(&b, "temp").await.is_none());
+    }
+
+    // NaN included: it must be storable even though SQL cannot compare it
+    pub async fn rejected_measurements_are_kept_apart_from_values(b: Backend) {
+        let reject = |value| Intent::RejectMeasurement {
+            device_id: "temp".into(),
+            gateway_id: b.gateway.clone(),
+            value,
+            timestamp: T0,
+            reason: "out of range".into(),
+        };
+
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(reject(500.0));
+        intents.push(reject(f64::NAN));
+        b.storage.execute(intents).await.unwrap();
+
+        assert!(device(&b, "temp").await.is_some());
+        assert!(values(&b, "temp").await.is_empty());
+    }
+
+    pub async fn alarm_lifecycle_merges_out_of_order_events(b: Backend) {
+        let raised = AlarmRecord {
+            id: format!("temp-hi-{}", T0),
+            kind: AlarmKind::Hi,
+            severity: AlarmSeverity::Warning,
+            value: Some(31.0),
+            message: "value 31 above 30".into(),
+            raised_at: T0,
+            acknowledged_at: None,
+            acknowledged_by: None,
+            cleared_at: None,
+        };
+        let acknowledged = AlarmRecord {
+            acknowledged_at: Some(T0 + 1_000),
+            acknowledged_by: Some("operator".into()),
+            ..raised.clone()
+        };
+        let cleared = AlarmRecord {
+            cleared_at: Some(T0 + 2_000),
+            ..raised.clone()
+        };
+        let record = |alarm: &AlarmRecord| Intent::RecordAlarm {
+            device_id: "temp".into(),
+            gateway_id: b.gateway.clone(),
+            alarm: alarm.clone(),
+        };
+        let query = |state| AlarmQuery {
+            gateway_id: Some(b.gateway.clone()),
+            device_id: None,
+            state,
+            limit: 10,
+        };
+
+        // The clear arrives first, the raise and acknowledgement later
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(record(&cleared));
+        b.storage.execute(intents).await.unwrap();
+        b.storage
+            .execute(vec![record(&raised), record(&acknowledged)])
+            .await
+            .unwrap();
+
+        let alarms = b.query.alarms(&query(None)).await.unwrap();
+        assert_eq!(alarms.len(), 1);
+        assert_eq!(alarms[0].state, AlarmState::Cleared);
+        assert_eq!(alarms[0].kind, "hi");
+        assert_eq!(alarms[0].acknowledged_at, Some(T0 + 1_000));
+        assert_eq!(alarms[0].acknowledged_by.as_deref(), Some("operator"));
+        assert_eq!(alarms[0].cleared_at, Some(T0 + 2_000));
+
+        let open = b.query.alarms(&query(Some(AlarmState::Active))).await;
+        assert!(open.unwrap().is_empty());
+    }
+
+    pub async fn edge_windows_are_replaced_on_redelivery(b: Backend) {
+        let window = |start: i64, count: u64| AggregateWindow {
+            start,
+            end: start + 60_000,
+            count,
+            min: 1.0,
+            max: 4.0,
+            avg: 2.5,
+            last: 4.0,
+            first_timestamp: start + 500,
+            last_timestamp: start + 59_500,
+        };
+        let record = |window: AggregateWindow| Intent::RecordAggregate {
+            device_id: "temp".into(),
+            gateway_id: b.gateway.clone(),
+            window,
+        };
+
+        let mut intents = ensure(&b.gateway, "temp");
+        intents.push(record(window(T0, 3)));
+        intents.push(record(window(T0 + 60_000, 5)));
+        b.storage.execute(intents).await.unwrap();
+        b.storage
+            .execute(vec![record(window(T0, 4))])
+            .await
+            .unwrap();
+
+        let query = MeasurementQuery {
+            gateway_id: b.gateway.clone(),
+            device_id: "temp".into(),
+            from: T0,
+            to: T0 + 120_000,
+            limit: 10,
+        };
+        let windows = b.query.windows(&query).await.unwrap();
+        assert_eq!(windows, vec![window(T0, 4), window(T0 + 60_000, 5)]);
+
+        // Windows are device data like values, unknown devices are refused
+        let err = b
+            .storage
+            .execute(vec![Intent::RecordAggregate {
+                device_id: "missing".into(),
+                gateway_id: b.gateway.clone(),
+                window: window(T0, 1),
+            }])
+            .await
+            .unwrap_err();
+        assert!(!err.is_transient(), "{err}");
+    }
+
+    pub async fn device_of_unknown_gateway_is_rejected(b: Backend) {
+        let err = b
+            .storage
+            .execute(vec![Intent::EnsureDeviceExists {
+                device_id: "temp".into(),
+                gateway_id: b.gateway.clone(),
+            }])
+            .await
+            .unwrap_err();
+
+        assert!(!err.is_transient(), "{err}");
+    }
+
+    pub async fn batch_writes_measurements_after_devices(b: Backend) {
+        let batch = [
+            vec![measurement(&b.gateway, "temp", T0, Some(1.0))],
+            ensure(&b.gateway, "temp"),
+        ];
+
+        b.storage.execute_batch(&batch).await.unwrap();
+
+        assert_eq!(values(&b, "temp").await, vec![(T0, 1.0)]);
+    }
+
+    pub async fn queries_respect_range_limit_and_buckets(b: Backend) {
+        let mut intents = ensure(&b.gateway, "temp");
+        // Two one-minute buckets: 1, 3, 2 and then 10
+        for (offset, value) in [(0, 1.0), (20_000, 3.0), (40_000, 2.0), (60_000, 10.0)] {
+            intents.push(measurement(&b.gateway, "temp", T0 + offset, Some(value)));
+        }
+        b.storage.execute(intents).await.unwrap();
+
+        let range = MeasurementQuery {
+            gateway_id: b.gateway.clone(),
+            device_id: "temp".into(),
+            from: T0 + 20_000,
+            to: T0 + 60_000,
+            limit: 1,
+        };
+        let rows = b.query.measurements(&range).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].timestamp, rows[0].value), (T0 + 20_000, 3.0));
+
+        let buckets = b
+            .query
+            .aggregate(&AggregateQuery {
+                gateway_id: b.gateway.clone(),
+                device_id: "temp".into(),
+                from: T0,
+                to: T0 + 120_000,
+                bucket_ms: 60_000,
+                limit: 10,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].bucket, T0);
+        assert_eq!((buckets[0].min, buckets[0].max), (1.0, 3.0));
+        assert_eq!(buckets[0].avg, 2.0);
+        assert_eq!(buckets[0].last, 2.0);
+        assert_eq!(buckets[0].count, 3);
+        assert_eq!(buckets[1].bucket, T0 + 60_000);
+        assert_eq!(buckets[1].last, 10.0);
+    }
+}
@@ -0,0 +1,131 @@
+// Partition maintenance against a real Postgres; skipped unless TEST_DATABASE_URL is set.
+// Each test works in its own schema so it can share the database with other suites.
+use sqlx::{
+    PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use telemetry::{
+    adapters::postgres_storage::{migrations, timeseries},
+    config::{TimeseriesConfig, TimeseriesMode},
+};
+
+async fn schema(name: &str) -> Option<(PgPool, String)> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping Postgres time-series tests");
+        return None;
+    };
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let schema = format!("ts_{}_{}_{}", name, std::process::id(), nanos);
+
+    let admin = PgPool::connect(&url).await.unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .unwrap();
+
+    let options = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new().connect_with(options).await.unwrap();
+    migrations::run(&pool).await.unwrap();
+    Some((pool, schema))
+}
+
+async fn drop_schema(pool: &PgPool, schema: &str) {
+    sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema))
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+fn config() -> TimeseriesConfig {
+    TimeseriesConfig {
+        mode: TimeseriesMode::Partitioned,
+        chunk_interval: "1 day".into(),
+        compress_after: None,
+        retention: Some("30 days".into()),
+        aggregates: false,
+        maintenance_interval_secs: 300,
+    }
+}
+
+async fn partitions(pool: &PgPool) -> Vec<String> {
+    sqlx::query_scalar(
+        r#"
+        SELECT c.relname::text FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'device_values'::regclass
+        ORDER BY 1
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn partitions_are_created_ahead_and_expired_ones_dropped() {
+    let Some((pool, schema)) = schema("partitions").await else {
+        return;
+    };
+
+    let mode = timeseries::setup(&pool, &config()).await.unwrap();
+    assert_eq!(mode, TimeseriesMode::Partitioned);
+
+    let created = partitions(&pool).await;
+    assert!(created.contains(&"device_values_legacy".to_string()));
+    assert!(created.contains(&"device_values_default".to_string()));
+    assert!(
+        created
+            .iter()
+            .filter(|p| p.starts_with("device_values_p"))
+            .count()
+            >= 1,
+        "{:?}",
+        created
+    );
+
+    // Make room for an expired partition whose name needs quoting
+    for statement in [
+        "ALTER TABLE device_values DETACH PARTITION device_values_legacy",
+        "DROP TABLE device_values_legacy",
+        r#"CREATE TABLE "Expired ""old"" values" PARTITION OF device_values
+           FOR VALUES FROM ('2000-01-01 00:00:00') TO ('2000-01-02 00:00:00')"#,
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+    assert!(
+        partitions(&pool)
+            .await
+            .contains(&r#"Expired "old" values"#.to_string())
+    );
+
+    // Setup is idempotent and runs maintenance again
+    timeseries::setup(&pool, &config()).await.unwrap();
+    let remaining = partitions(&pool).await;
+    assert!(
+        !remaining.contains(&r#"Expired "old" values"#.to_string()),
+        "{:?}",
+        remaining
+    );
+    assert_eq!(
+        remaining
+            .iter()
+            .filter(|p| p.starts_with("device_values_p"))
+            .count(),
+        created
+            .iter()
+            .filter(|p| p.starts_with("device_values_p"))
+            .count()
+    );
+
+    drop_schema(&pool, &schema).await;
+}
@@ -0,0 +1,122 @@
+// Rules file → ValidatingProcessor → TelemetryService → MemoryStorage
+use std::sync::Arc;
+use telemetry::{
+    adapters::{
+        dead_letter_file::FileDeadLetterStore, memory_storage::MemoryStorage,
+        mqtt::parse_telemetry_message,
+    },
+    config::RetryConfig,
+    core::{
+        health::Health,
+        metrics::Metrics,
+        ports::{DeadLetterPort, TelemetryInputPort, TelemetryQueryPort},
+        processors::{DefaultProcessor, ValidatingProcessor, ValidationRules},
+        services::TelemetryService,
+    },
+    domain::{dead_letter::RawMessage, queries::MeasurementQuery},
+};
+
+const RULES: &str = r#"
+[[rules]]
+gateway_id = "gw1"
+device_id = "outdoor"
+convert = "fahrenheit_to_celsius"
+offset = 0.5
+min = -40.0
+max = 60.0
+"#;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "telemetry-validation-{}-{}",
+        name,
+        std::process::id()
+    ))
+}
+
+async fn publish(service: &TelemetryService, device_id: &str, timestamp: i64, value: f64) {
+    let payload = serde_json::json!({
+        "ctx": {
+            "gateway_id": "gw1",
+            "gateway_name": "Gateway 1",
+            "device_name": "Outdoor",
+            "device_id": device_id,
+        },
+        "value": value,
+        "meta": { "timestamp": timestamp },
+    });
+    let raw = RawMessage {
+        topic: format!("gw1/devices/{}/value", device_id),
+        payload: serde_json::to_vec(&payload).unwrap(),
+    };
+    let msg = parse_telemetry_message(&raw.topic, &raw.payload).unwrap();
+    service.on_message(raw, msg).await;
+}
+
+#[tokio::test]
+async fn rules_file_converts_values_and_records_rejections() {
+    let rules_path = temp_path("rules.toml");
+    std::fs::write(&rules_path, RULES).unwrap();
+    let dead_letter_path = temp_path("dead_letters.jsonl");
+    let _ = std::fs::remove_file(&dead_letter_path);
+
+    let storage = MemoryStorage::new();
+    let dead_letters = Arc::new(FileDeadLetterStore::new(dead_letter_path.clone()));
+    let service = TelemetryService {
+        processor: Box::new(ValidatingProcessor {
+            inner: Box::new(DefaultProcessor::default()),
+            rules: ValidationRules::load(rules_path.to_str().unwrap()).unwrap(),
+        }),
+        storage: Box::new(storage.clone()),
+        dead_letters: dead_letters.clone(),
+        retry: RetryConfig {
+            max_attempts: 1,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+        },
+        metrics: Metrics::new(),
+        health: Health::new(),
+    };
+
+    publish(&service, "outdoor", 1_000, 68.0).await;
+    publish(&service, "outdoor", 2_000, 212.0).await;
+    // No rule for this device, stored unchanged
+    publish(&service, "indoor", 3_000, 212.0).await;
+
+    let values = |device_id: &str| MeasurementQuery {
+        gateway_id: "gw1".into(),
+        device_id: device_id.into(),
+        from: 0,
+        to: 10_000,
+        limit: 10,
+    };
+    let outdoor = storage.measurements(&values("outdoor")).await.unwrap();
+    assert_eq!(outdoor.len(), 1);
+    assert_eq!((outdoor[0].timestamp, outdoor[0].value), (1_000, 20.5));
+    let indoor = storage.measurements(&values("indoor")).await.unwrap();
+    assert_eq!(indoor[0].value, 212.0);
+
+    let rejections = storage.rejections();
+    assert_eq!(rejections.len(), 1);
+    assert_eq!(
+        (rejections[0].timestamp, rejections[0].value),
+        (2_000, 212.0)
+    );
+    assert!(rejections[0].reason.contains("maximum 60"));
+
+    // Rejections are an outcome of processing, not a failure
+    assert!(dead_letters.list().await.unwrap().is_empty());
+
+    let _ = std::fs::remove_file(&rules_path);
+}
+
+#[test]
+fn inverted_ranges_are_refused_at_load() {
+    let path = temp_path("inverted.toml");
+    std::fs::write(&path, "[[rules]]\nmin = 10.0\nmax = 0.0\n").unwrap();
+
+    let err = ValidationRules::load(path.to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("greater than max"), "{}", err);
+
+    let _ = std::fs::remove_file(&path);
+}